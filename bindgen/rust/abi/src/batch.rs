@@ -0,0 +1,97 @@
+//! Batches: many calls of a function in a single guest entry, so that calling
+//! it over a large input takes one `allocate`, one call and one `deallocate`.
+//!
+//! `#[wasmedge_bindgen(batch)]` exports a function `f` as
+//! `__wasmedge_bindgen_batch_f(pointer: i32, len: i32) -> i64`, called like
+//! its [packed](crate::packed) variant, and flags it in the manifest. Its
+//! parameters buffer is `count: u32`, then a `size: u32` in bytes and a packed
+//! parameters buffer per call. Its results buffer is `flag: u8`, then
+//! `count: u32` and a size and packed results buffer per call on success
+//! (flag 0), so each call succeeds or fails on its own, or the UTF-8 message
+//! when the batch itself can't be read (flag 1).
+
+use crate::{check_len, u32_at, DecodeError};
+
+/// Prefix of the name of batch exports.
+pub const PREFIX: &str = "__wasmedge_bindgen_batch_";
+
+/// Name of the batch variant of the export `name`.
+pub fn export_name(name: &str) -> String {
+	format!("{}{}", PREFIX, name)
+}
+
+/// Writes the packed buffers of a batch parameters or results buffer.
+#[derive(Clone, Debug)]
+pub struct Writer {
+	bytes: Vec<u8>,
+}
+
+impl Writer {
+	/// Starts a parameters buffer of `count` calls.
+	pub fn params(count: usize) -> Self {
+		let mut bytes = Vec::with_capacity(4 + count * 16);
+		bytes.extend_from_slice(&(count as u32).to_le_bytes());
+		Writer { bytes }
+	}
+
+	/// Starts a successful results buffer of `count` calls.
+	pub fn results(count: usize) -> Self {
+		let mut bytes = Vec::with_capacity(5 + count * 16);
+		bytes.push(0);
+		bytes.extend_from_slice(&(count as u32).to_le_bytes());
+		Writer { bytes }
+	}
+
+	/// Adds the packed buffer of the next call.
+	pub fn push(&mut self, packed: &[u8]) {
+		self.bytes.extend_from_slice(&(packed.len() as u32).to_le_bytes());
+		self.bytes.extend_from_slice(packed);
+	}
+
+	pub fn finish(self) -> Vec<u8> {
+		self.bytes
+	}
+}
+
+/// Reads the packed buffers of a batch parameters or successful results buffer.
+#[derive(Clone, Debug)]
+pub struct Reader<'a> {
+	bytes: &'a [u8],
+	remaining: u32,
+}
+
+impl<'a> Reader<'a> {
+	pub fn params(bytes: &'a [u8]) -> Result<Self, DecodeError> {
+		check_len(bytes, 4)?;
+		Ok(Reader { remaining: u32_at(bytes, 0), bytes: &bytes[4..] })
+	}
+
+	/// Reads a results buffer, or returns the message of a batch that failed as a whole.
+	pub fn results(bytes: &'a [u8]) -> Result<Result<Self, String>, DecodeError> {
+		check_len(bytes, 1)?;
+		match bytes[0] {
+			0 => Reader::params(&bytes[1..]).map(Ok),
+			1 => Ok(Err(String::from_utf8_lossy(&bytes[1..]).into_owned())),
+			flag => Err(DecodeError::InvalidFlag(flag)),
+		}
+	}
+
+	/// Number of calls left to read.
+	pub fn remaining(&self) -> u32 {
+		self.remaining
+	}
+
+	/// Packed buffer of the next call, `None` after the last one.
+	pub fn next_call(&mut self) -> Result<Option<&'a [u8]>, DecodeError> {
+		if self.remaining == 0 {
+			return Ok(None);
+		}
+		check_len(self.bytes, 4)?;
+		let size = u32_at(self.bytes, 0) as usize;
+		check_len(&self.bytes[4..], size)?;
+		let (packed, rest) = self.bytes[4..].split_at(size);
+		self.bytes = rest;
+		self.remaining -= 1;
+		Ok(Some(packed))
+	}
+}
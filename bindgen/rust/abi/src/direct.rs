@@ -0,0 +1,28 @@
+//! Direct exports: functions taking and returning only numbers skip the
+//! linear memory altogether.
+//!
+//! #[wasmedge_bindgen] exports such a function `f` as
+//! `__wasmedge_bindgen_direct_f` with its parameters as plain wasm values,
+//! `i32` for the integers up to 32 bits, `i64`, `f32` and `f64` for the others,
+//! and flags it in the manifest. It returns its value the same way, so direct
+//! exports return at most one value: functions returning a `Result` aren't
+//! direct, nor are those returning several values, as Rust can't export
+//! multi-value functions.
+
+use crate::WireType;
+
+/// Prefix of the name of direct exports.
+pub const PREFIX: &str = "__wasmedge_bindgen_direct_";
+
+/// Name of the direct variant of the export `name`.
+pub fn export_name(name: &str) -> String {
+	format!("{}{}", PREFIX, name)
+}
+
+/// Whether values of `ty` are passed as plain wasm values by direct exports.
+pub fn is_direct(ty: WireType) -> bool {
+	matches!(
+		ty,
+		WireType::U8 | WireType::I8 | WireType::U16 | WireType::I16 | WireType::U32 | WireType::I32 | WireType::U64 | WireType::I64 | WireType::F32 | WireType::F64
+	)
+}
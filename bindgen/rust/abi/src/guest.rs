@@ -0,0 +1,185 @@
+//! Guest side of the protocol, called from the code #[wasmedge_bindgen] generates.
+
+use crate::batch;
+use crate::packed::{self, Reader, Writer};
+use crate::{ParamEntry, ResultEntry, ReturnHeader, Wire, PARAM_ENTRY_SIZE, DecodeError};
+
+/// Reserves a `size` bytes buffer for the host, backing the `allocate` export.
+#[cfg(target_arch = "wasm32")]
+pub fn allocate(size: u32) -> u32 {
+	let buffer = std::mem::ManuallyDrop::new(Vec::<u8>::with_capacity(size as usize));
+	buffer.as_ptr() as usize as u32
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn allocate(size: u32) -> u32 {
+	crate::linear::allocate(size)
+}
+
+/// Frees a buffer, backing the `deallocate` export.
+///
+/// # Safety
+///
+/// `pointer` and `size` must come from a previous `allocate(size)`, or from a
+/// buffer handed over by a #[wasmedge_bindgen] function.
+pub unsafe fn deallocate(pointer: u32, size: u32) {
+	#[cfg(target_arch = "wasm32")]
+	drop(Vec::from_raw_parts(pointer as usize as *mut u8, size as usize, size as usize));
+	#[cfg(not(target_arch = "wasm32"))]
+	crate::linear::deallocate(pointer, size);
+}
+
+/// Takes ownership of a `len` bytes buffer the host got from `allocate(len)`.
+#[cfg(target_arch = "wasm32")]
+unsafe fn take_bytes(pointer: u32, len: usize) -> Result<Vec<u8>, String> {
+	if len == 0 {
+		return Ok(Vec::new());
+	}
+	Ok(Vec::from_raw_parts(pointer as usize as *mut u8, len, len))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+unsafe fn take_bytes(pointer: u32, len: usize) -> Result<Vec<u8>, String> {
+	if len == 0 {
+		return Ok(Vec::new());
+	}
+	let bytes = u32::try_from(len)
+		.ok()
+		.and_then(|len| crate::linear::read(pointer, len))
+		.ok_or_else(|| format!("Parameter of {} bytes at {:#x} is out of the linear memory", len, pointer))?;
+	crate::linear::deallocate(pointer, len as u32);
+	Ok(bytes)
+}
+
+/// Hands a buffer over to the host, which frees it with `deallocate(pointer, len)`.
+#[cfg(target_arch = "wasm32")]
+fn leak(bytes: Vec<u8>) -> (u32, u32) {
+	let len = bytes.len() as u32;
+	let pointer = Box::into_raw(bytes.into_boxed_slice()) as *mut u8;
+	(pointer as usize as u32, len)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn leak(bytes: Vec<u8>) -> (u32, u32) {
+	let len = bytes.len() as u32;
+	let pointer = crate::linear::allocate(len);
+	crate::linear::write(pointer, &bytes);
+	(pointer, len)
+}
+
+/// Reads and frees the parameter frame of an export expecting `expected` parameters.
+///
+/// On a count mismatch the parameters themselves are left allocated, as
+/// their sizes depend on types the export doesn't know.
+///
+/// # Safety
+///
+/// `pointer` must come from the host's `allocate(count * 8)` and hold `count` entries.
+pub unsafe fn take_frame(pointer: u32, count: i32, expected: usize) -> Result<Vec<ParamEntry>, String> {
+	let count_usize = usize::try_from(count).unwrap_or(0);
+	if count_usize != expected || count < 0 {
+		let _ = take_bytes(pointer, count_usize * PARAM_ENTRY_SIZE);
+		return Err(DecodeError::InvalidParamsCount { expected, got: count }.to_string());
+	}
+	let bytes = take_bytes(pointer, count_usize * PARAM_ENTRY_SIZE)?;
+	ParamEntry::decode_all(&bytes, expected).map_err(|e| e.to_string())
+}
+
+/// Decodes and frees one parameter.
+///
+/// # Safety
+///
+/// `entry` must describe a buffer the host got from `allocate` and filled with a `T`.
+pub unsafe fn take_param<T: Wire>(entry: &ParamEntry) -> Result<T, String> {
+	let len = T::checked_byte_len(entry.length).ok_or_else(|| DecodeError::InvalidLength { ty: T::TYPE, len: entry.length as usize }.to_string())?;
+	let bytes = take_bytes(entry.pointer, len)?;
+	T::decode_owned(bytes).map_err(|e| e.to_string())
+}
+
+/// Leaks an encoded return value for the host to read.
+pub fn result_entry<T: Wire>(value: T) -> ResultEntry {
+	let (pointer, size) = leak(value.into_bytes());
+	ResultEntry { pointer, ty: T::TYPE, size }
+}
+
+/// Returns a pointer to a success header for `entries`.
+pub fn return_ok(entries: &[ResultEntry]) -> i32 {
+	let (pointer, _) = leak(ResultEntry::encode_all(entries));
+	return_header(ReturnHeader::ok(pointer, entries.len() as u32))
+}
+
+/// Returns a pointer to an error header carrying `message`.
+pub fn return_error(message: impl Into<String>) -> i32 {
+	let (pointer, len) = leak(message.into().into_bytes());
+	return_header(ReturnHeader::err(pointer, len))
+}
+
+fn return_header(header: ReturnHeader) -> i32 {
+	let (pointer, _) = leak(header.encode().to_vec());
+	pointer as i32
+}
+
+/// Takes ownership of the parameters buffer of a packed export.
+///
+/// # Safety
+///
+/// `pointer` must come from the host's `allocate(len)`.
+pub unsafe fn take_packed(pointer: u32, len: u32) -> Result<Vec<u8>, String> {
+	take_bytes(pointer, len as usize)
+}
+
+/// Reads the records of a packed parameters buffer, checking that there are `expected` of them.
+pub fn packed_params(bytes: &[u8], expected: usize) -> Result<Reader<'_>, String> {
+	let params = Reader::params(bytes).map_err(|e| e.to_string())?;
+	if params.remaining() as usize != expected {
+		return Err(DecodeError::InvalidParamsCount { expected, got: params.remaining() as i32 }.to_string());
+	}
+	Ok(params)
+}
+
+/// Decodes the next packed parameter, the `index`th.
+pub fn packed_param<T: Wire>(params: &mut Reader, index: usize) -> Result<T, String> {
+	match params.next_value() {
+		Ok(Some((ty, bytes))) if ty == T::TYPE => T::decode(bytes).map_err(|e| e.to_string()),
+		Ok(Some((ty, _))) => Err(DecodeError::MismatchedType { index, expected: T::TYPE, got: ty }.to_string()),
+		Ok(None) => Err(DecodeError::InvalidParamsCount { expected: index + 1, got: index as i32 }.to_string()),
+		Err(e) => Err(e.to_string()),
+	}
+}
+
+pub fn packed_result<T: Wire>(results: &mut Writer, value: T) {
+	results.push(T::TYPE, |out| value.encode(out));
+}
+
+/// Results buffer of a packed call failing with `message`.
+pub fn packed_error(message: impl Into<String>) -> Vec<u8> {
+	packed::error(&message.into())
+}
+
+/// Hands a packed results buffer over to the host, returning its pointer and length.
+pub fn return_packed(results: Vec<u8>) -> u64 {
+	let (pointer, len) = leak(results);
+	(len as u64) << 32 | pointer as u64
+}
+
+pub fn return_packed_error(message: impl Into<String>) -> u64 {
+	return_packed(packed_error(message))
+}
+
+/// Runs each call of a batch parameters buffer, `run` turning its packed
+/// parameters into its packed results, and hands the results over to the host.
+pub fn run_batch(bytes: &[u8], mut run: impl FnMut(&[u8]) -> Vec<u8>) -> u64 {
+	let mut calls = match batch::Reader::params(bytes) {
+		Ok(calls) => calls,
+		Err(e) => return return_packed_error(e.to_string()),
+	};
+	// The count comes from the host, don't trust it for the capacity
+	let mut results = batch::Writer::results(calls.remaining().min(1024) as usize);
+	loop {
+		match calls.next_call() {
+			Ok(Some(params)) => results.push(&run(params)),
+			Ok(None) => return return_packed(results.finish()),
+			Err(e) => return return_packed_error(e.to_string()),
+		}
+	}
+}
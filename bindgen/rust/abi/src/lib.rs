@@ -0,0 +1,498 @@
+use std::any::Any;
+use std::fmt;
+
+pub mod batch;
+pub mod direct;
+pub mod guest;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod linear;
+pub mod manifest;
+pub mod packed;
+
+/// Type tags written into result entries.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[repr(i32)]
+pub enum WireType {
+	U8 = 1,
+	I8 = 2,
+	U16 = 3,
+	I16 = 4,
+	U32 = 5,
+	I32 = 6,
+	U64 = 7,
+	I64 = 8,
+	F32 = 9,
+	F64 = 10,
+	Bool = 11,
+	Char = 12,
+	U8Array = 21,
+	I8Array = 22,
+	U16Array = 23,
+	I16Array = 24,
+	U32Array = 25,
+	I32Array = 26,
+	U64Array = 27,
+	I64Array = 28,
+	String = 31,
+}
+
+impl WireType {
+	pub fn from_i32(v: i32) -> Option<Self> {
+		let ty = match v {
+			1 => WireType::U8,
+			2 => WireType::I8,
+			3 => WireType::U16,
+			4 => WireType::I16,
+			5 => WireType::U32,
+			6 => WireType::I32,
+			7 => WireType::U64,
+			8 => WireType::I64,
+			9 => WireType::F32,
+			10 => WireType::F64,
+			11 => WireType::Bool,
+			12 => WireType::Char,
+			21 => WireType::U8Array,
+			22 => WireType::I8Array,
+			23 => WireType::U16Array,
+			24 => WireType::I16Array,
+			25 => WireType::U32Array,
+			26 => WireType::I32Array,
+			27 => WireType::U64Array,
+			28 => WireType::I64Array,
+			31 => WireType::String,
+			_ => return None,
+		};
+		Some(ty)
+	}
+
+	/// Size in bytes of one element. Scalars are a single element.
+	pub fn elem_size(self) -> usize {
+		match self {
+			WireType::U8 | WireType::I8 | WireType::Bool => 1,
+			WireType::U16 | WireType::I16 => 2,
+			WireType::U32 | WireType::I32 | WireType::F32 | WireType::Char => 4,
+			WireType::U64 | WireType::I64 | WireType::F64 => 8,
+			WireType::U8Array | WireType::I8Array | WireType::String => 1,
+			WireType::U16Array | WireType::I16Array => 2,
+			WireType::U32Array | WireType::I32Array => 4,
+			WireType::U64Array | WireType::I64Array => 8,
+		}
+	}
+
+	pub fn is_scalar(self) -> bool {
+		(self as i32) < 21
+	}
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DecodeError {
+	UnknownType(i32),
+	InvalidLength { ty: WireType, len: usize },
+	InvalidBool(u8),
+	InvalidChar(u32),
+	InvalidUtf8,
+	InvalidFlag(u8),
+	Truncated { expected: usize, got: usize },
+	InvalidParamsCount { expected: usize, got: i32 },
+	/// A parameter of another type than the function takes.
+	MismatchedType { index: usize, expected: WireType, got: WireType },
+	/// A manifest record of an unknown version.
+	UnsupportedVersion(u8),
+	NotWasm,
+}
+
+impl fmt::Display for DecodeError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			DecodeError::UnknownType(v) => write!(f, "Unknown type tag {}", v),
+			DecodeError::InvalidLength { ty, len } => write!(f, "Invalid length {} for {:?}", len, ty),
+			DecodeError::InvalidBool(v) => write!(f, "Invalid bool value {}", v),
+			DecodeError::InvalidChar(v) => write!(f, "Invalid char value {:#x}", v),
+			DecodeError::InvalidUtf8 => write!(f, "Invalid UTF-8 string"),
+			DecodeError::InvalidFlag(v) => write!(f, "Invalid return flag {}", v),
+			DecodeError::Truncated { expected, got } => write!(f, "Expect {} bytes, got {}", expected, got),
+			DecodeError::InvalidParamsCount { expected, got } => write!(f, "Invalid params count, expect {}, got {}", expected, got),
+			DecodeError::MismatchedType { index, expected, got } => write!(f, "Parameter {} is {:?}, expect {:?}", index, got, expected),
+			DecodeError::UnsupportedVersion(v) => write!(f, "Unsupported manifest version {}", v),
+			DecodeError::NotWasm => write!(f, "Not a wasm module"),
+		}
+	}
+}
+
+impl std::error::Error for DecodeError {}
+
+fn check_len(bytes: &[u8], expected: usize) -> Result<(), DecodeError> {
+	if bytes.len() < expected {
+		return Err(DecodeError::Truncated { expected, got: bytes.len() });
+	}
+	Ok(())
+}
+
+fn u32_at(bytes: &[u8], offset: usize) -> u32 {
+	u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
+
+pub const HEADER_SIZE: usize = 9;
+
+/// The 9 bytes a bindgen export returns a pointer to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ReturnHeader {
+	pub is_error: bool,
+	/// Result entries on success, message bytes on error.
+	pub pointer: u32,
+	/// Number of result entries on success, message length on error.
+	pub len: u32,
+}
+
+impl ReturnHeader {
+	pub fn ok(pointer: u32, count: u32) -> Self {
+		ReturnHeader { is_error: false, pointer, len: count }
+	}
+
+	pub fn err(pointer: u32, len: u32) -> Self {
+		ReturnHeader { is_error: true, pointer, len }
+	}
+
+	pub fn encode(&self) -> [u8; HEADER_SIZE] {
+		let mut bytes = [0; HEADER_SIZE];
+		bytes[0] = self.is_error as u8;
+		bytes[1..5].copy_from_slice(&self.pointer.to_le_bytes());
+		bytes[5..9].copy_from_slice(&self.len.to_le_bytes());
+		bytes
+	}
+
+	pub fn decode(bytes: &[u8]) -> Result<Self, DecodeError> {
+		check_len(bytes, HEADER_SIZE)?;
+		let is_error = match bytes[0] {
+			0 => false,
+			1 => true,
+			flag => return Err(DecodeError::InvalidFlag(flag)),
+		};
+		Ok(ReturnHeader {
+			is_error,
+			pointer: u32_at(bytes, 1),
+			len: u32_at(bytes, 5),
+		})
+	}
+}
+
+pub const ENTRY_SIZE: usize = 12;
+
+/// Location and type of one returned value.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ResultEntry {
+	pub pointer: u32,
+	pub ty: WireType,
+	/// Size in bytes.
+	pub size: u32,
+}
+
+impl ResultEntry {
+	pub fn encode_all(entries: &[ResultEntry]) -> Vec<u8> {
+		let mut bytes = Vec::with_capacity(entries.len() * ENTRY_SIZE);
+		for entry in entries {
+			bytes.extend_from_slice(&entry.pointer.to_le_bytes());
+			bytes.extend_from_slice(&(entry.ty as i32).to_le_bytes());
+			bytes.extend_from_slice(&entry.size.to_le_bytes());
+		}
+		bytes
+	}
+
+	pub fn decode_all(bytes: &[u8], count: usize) -> Result<Vec<ResultEntry>, DecodeError> {
+		check_len(bytes, count.saturating_mul(ENTRY_SIZE))?;
+		let mut entries = Vec::with_capacity(count);
+		for chunk in bytes.chunks_exact(ENTRY_SIZE).take(count) {
+			let raw_ty = u32_at(chunk, 4) as i32;
+			entries.push(ResultEntry {
+				pointer: u32_at(chunk, 0),
+				ty: WireType::from_i32(raw_ty).ok_or(DecodeError::UnknownType(raw_ty))?,
+				size: u32_at(chunk, 8),
+			});
+		}
+		Ok(entries)
+	}
+}
+
+pub const PARAM_ENTRY_SIZE: usize = 8;
+
+/// Location of one parameter in the frame the host passes to an export.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ParamEntry {
+	pub pointer: u32,
+	/// Number of elements, not bytes.
+	pub length: u32,
+}
+
+impl ParamEntry {
+	pub fn encode_all(entries: &[ParamEntry]) -> Vec<u8> {
+		let mut bytes = Vec::with_capacity(entries.len() * PARAM_ENTRY_SIZE);
+		for entry in entries {
+			bytes.extend_from_slice(&entry.pointer.to_le_bytes());
+			bytes.extend_from_slice(&entry.length.to_le_bytes());
+		}
+		bytes
+	}
+
+	pub fn decode_all(bytes: &[u8], count: usize) -> Result<Vec<ParamEntry>, DecodeError> {
+		check_len(bytes, count.saturating_mul(PARAM_ENTRY_SIZE))?;
+		Ok(bytes
+			.chunks_exact(PARAM_ENTRY_SIZE)
+			.take(count)
+			.map(|chunk| ParamEntry {
+				pointer: u32_at(chunk, 0),
+				length: u32_at(chunk, 4),
+			})
+			.collect())
+	}
+}
+
+/// Fixed-size element of a scalar or an array value.
+pub trait Elem: Copy {
+	const SIZE: usize;
+	fn put(self, out: &mut Vec<u8>);
+	fn get(bytes: &[u8]) -> Self;
+}
+
+macro_rules! impl_elem {
+	($($t:ty),*) => {
+		$(
+		impl Elem for $t {
+			const SIZE: usize = std::mem::size_of::<$t>();
+
+			fn put(self, out: &mut Vec<u8>) {
+				out.extend_from_slice(&self.to_le_bytes());
+			}
+
+			fn get(bytes: &[u8]) -> Self {
+				<$t>::from_le_bytes(bytes[..Self::SIZE].try_into().unwrap())
+			}
+		}
+		)*
+	};
+}
+
+impl_elem!(u8, i8, u16, i16, u32, i32, u64, i64, f32, f64);
+
+pub fn encode_slice<T: Elem>(v: &[T]) -> Vec<u8> {
+	let mut bytes = Vec::with_capacity(v.len() * T::SIZE);
+	for x in v {
+		x.put(&mut bytes);
+	}
+	bytes
+}
+
+pub fn decode_vec<T: Elem>(bytes: &[u8], ty: WireType) -> Result<Vec<T>, DecodeError> {
+	if !bytes.len().is_multiple_of(T::SIZE) {
+		return Err(DecodeError::InvalidLength { ty, len: bytes.len() });
+	}
+	Ok(bytes.chunks_exact(T::SIZE).map(T::get).collect())
+}
+
+/// A value that can cross the guest/host boundary.
+pub trait Wire: Sized {
+	const TYPE: WireType;
+
+	/// Element count written into the parameter frame.
+	fn length(&self) -> u32;
+
+	fn encode(&self, out: &mut Vec<u8>);
+
+	fn decode(bytes: &[u8]) -> Result<Self, DecodeError>;
+
+	fn to_bytes(&self) -> Vec<u8> {
+		let mut bytes = Vec::new();
+		self.encode(&mut bytes);
+		bytes
+	}
+
+	/// Like `to_bytes`, reusing the allocation when the value already is a byte buffer.
+	fn into_bytes(self) -> Vec<u8> {
+		self.to_bytes()
+	}
+
+	/// Like `decode`, reusing the allocation when the value is a byte buffer.
+	fn decode_owned(bytes: Vec<u8>) -> Result<Self, DecodeError> {
+		Self::decode(&bytes)
+	}
+
+	/// Bytes occupied by a value of `length` elements.
+	fn byte_len(length: u32) -> usize {
+		length as usize * Self::TYPE.elem_size()
+	}
+
+	/// Like `byte_len`, or `None` when the size doesn't fit in a `usize`.
+	fn checked_byte_len(length: u32) -> Option<usize> {
+		(length as usize).checked_mul(Self::TYPE.elem_size())
+	}
+}
+
+macro_rules! impl_wire_scalar {
+	($($t:ty => $tag:ident),*) => {
+		$(
+		impl Wire for $t {
+			const TYPE: WireType = WireType::$tag;
+
+			fn length(&self) -> u32 {
+				1
+			}
+
+			fn encode(&self, out: &mut Vec<u8>) {
+				self.put(out);
+			}
+
+			fn decode(bytes: &[u8]) -> Result<Self, DecodeError> {
+				if bytes.len() != <$t as Elem>::SIZE {
+					return Err(DecodeError::InvalidLength { ty: Self::TYPE, len: bytes.len() });
+				}
+				Ok(<$t as Elem>::get(bytes))
+			}
+		}
+		)*
+	};
+}
+
+impl_wire_scalar!(u8 => U8, i8 => I8, u16 => U16, i16 => I16, u32 => U32, i32 => I32, u64 => U64, i64 => I64, f32 => F32, f64 => F64);
+
+impl Wire for bool {
+	const TYPE: WireType = WireType::Bool;
+
+	fn length(&self) -> u32 {
+		1
+	}
+
+	fn encode(&self, out: &mut Vec<u8>) {
+		out.push(*self as u8);
+	}
+
+	fn decode(bytes: &[u8]) -> Result<Self, DecodeError> {
+		match bytes {
+			[0] => Ok(false),
+			[1] => Ok(true),
+			[v] => Err(DecodeError::InvalidBool(*v)),
+			_ => Err(DecodeError::InvalidLength { ty: Self::TYPE, len: bytes.len() }),
+		}
+	}
+}
+
+impl Wire for char {
+	const TYPE: WireType = WireType::Char;
+
+	fn length(&self) -> u32 {
+		1
+	}
+
+	fn encode(&self, out: &mut Vec<u8>) {
+		(*self as u32).put(out);
+	}
+
+	fn decode(bytes: &[u8]) -> Result<Self, DecodeError> {
+		let v = u32::decode(bytes).map_err(|_| DecodeError::InvalidLength { ty: Self::TYPE, len: bytes.len() })?;
+		char::from_u32(v).ok_or(DecodeError::InvalidChar(v))
+	}
+}
+
+impl Wire for String {
+	const TYPE: WireType = WireType::String;
+
+	fn length(&self) -> u32 {
+		self.len() as u32
+	}
+
+	fn encode(&self, out: &mut Vec<u8>) {
+		out.extend_from_slice(self.as_bytes());
+	}
+
+	fn decode(bytes: &[u8]) -> Result<Self, DecodeError> {
+		Self::decode_owned(bytes.to_vec())
+	}
+
+	fn into_bytes(self) -> Vec<u8> {
+		String::into_bytes(self)
+	}
+
+	fn decode_owned(bytes: Vec<u8>) -> Result<Self, DecodeError> {
+		String::from_utf8(bytes).map_err(|_| DecodeError::InvalidUtf8)
+	}
+}
+
+macro_rules! impl_wire_vec {
+	($($t:ty => $tag:ident),*) => {
+		$(
+		impl Wire for Vec<$t> {
+			const TYPE: WireType = WireType::$tag;
+
+			fn length(&self) -> u32 {
+				self.len() as u32
+			}
+
+			fn encode(&self, out: &mut Vec<u8>) {
+				out.reserve(self.len() * <$t as Elem>::SIZE);
+				for x in self {
+					x.put(out);
+				}
+			}
+
+			fn decode(bytes: &[u8]) -> Result<Self, DecodeError> {
+				decode_vec(bytes, Self::TYPE)
+			}
+		}
+		)*
+	};
+}
+
+impl Wire for Vec<u8> {
+	const TYPE: WireType = WireType::U8Array;
+
+	fn length(&self) -> u32 {
+		self.len() as u32
+	}
+
+	fn encode(&self, out: &mut Vec<u8>) {
+		out.extend_from_slice(self);
+	}
+
+	fn decode(bytes: &[u8]) -> Result<Self, DecodeError> {
+		Ok(bytes.to_vec())
+	}
+
+	fn into_bytes(self) -> Vec<u8> {
+		self
+	}
+
+	fn decode_owned(bytes: Vec<u8>) -> Result<Self, DecodeError> {
+		Ok(bytes)
+	}
+}
+
+impl_wire_vec!(i8 => I8Array, u16 => U16Array, i16 => I16Array, u32 => U32Array, i32 => I32Array, u64 => U64Array, i64 => I64Array);
+
+/// Decodes a returned value into the Rust type matching its tag.
+pub fn decode_any(ty: WireType, bytes: &[u8]) -> Result<Box<dyn Any + Send + Sync>, DecodeError> {
+	fn boxed<T: Wire + Send + Sync + 'static>(bytes: &[u8]) -> Result<Box<dyn Any + Send + Sync>, DecodeError> {
+		Ok(Box::new(T::decode(bytes)?))
+	}
+
+	match ty {
+		WireType::U8 => boxed::<u8>(bytes),
+		WireType::I8 => boxed::<i8>(bytes),
+		WireType::U16 => boxed::<u16>(bytes),
+		WireType::I16 => boxed::<i16>(bytes),
+		WireType::U32 => boxed::<u32>(bytes),
+		WireType::I32 => boxed::<i32>(bytes),
+		WireType::U64 => boxed::<u64>(bytes),
+		WireType::I64 => boxed::<i64>(bytes),
+		WireType::F32 => boxed::<f32>(bytes),
+		WireType::F64 => boxed::<f64>(bytes),
+		WireType::Bool => boxed::<bool>(bytes),
+		WireType::Char => boxed::<char>(bytes),
+		WireType::U8Array => boxed::<Vec<u8>>(bytes),
+		WireType::I8Array => boxed::<Vec<i8>>(bytes),
+		WireType::U16Array => boxed::<Vec<u16>>(bytes),
+		WireType::I16Array => boxed::<Vec<i16>>(bytes),
+		WireType::U32Array => boxed::<Vec<u32>>(bytes),
+		WireType::I32Array => boxed::<Vec<i32>>(bytes),
+		WireType::U64Array => boxed::<Vec<u64>>(bytes),
+		WireType::I64Array => boxed::<Vec<i64>>(bytes),
+		WireType::String => boxed::<String>(bytes),
+	}
+}
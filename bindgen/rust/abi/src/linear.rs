@@ -0,0 +1,160 @@
+//! Linear memory simulated on targets other than wasm32.
+//!
+//! Addresses on the wire are 32 bits wide, which only holds real pointers
+//! inside wasm32. When a guest crate is linked natively, e.g. to test it with
+//! `cargo test`, its buffers live here instead, at offsets that fit the wire
+//! format, and the host reads and writes them through [`read`] and [`write`].
+
+use std::collections::BTreeMap;
+use std::sync::{Mutex, MutexGuard};
+
+pub const PAGE_SIZE: usize = 65536;
+
+const ALIGN: u32 = 8;
+
+struct LinearMemory {
+	data: Vec<u8>,
+	/// Free blocks by offset, with their sizes.
+	free: BTreeMap<u32, u32>,
+	/// End of the highest block ever handed out.
+	top: u32,
+	in_use: u64,
+}
+
+// Offset 0 stays unused so that no allocation looks like a null pointer.
+static MEMORY: Mutex<LinearMemory> = Mutex::new(LinearMemory {
+	data: Vec::new(),
+	free: BTreeMap::new(),
+	top: ALIGN,
+	in_use: 0,
+});
+
+fn memory() -> MutexGuard<'static, LinearMemory> {
+	MEMORY.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn round_up(size: u32) -> Option<u32> {
+	size.checked_add(ALIGN - 1).map(|s| s & !(ALIGN - 1))
+}
+
+impl LinearMemory {
+	fn allocate(&mut self, size: u32) -> Option<u32> {
+		let size = round_up(size)?;
+		if size == 0 {
+			// Still inside the memory, for empty reads and writes to succeed
+			self.grow(ALIGN as usize);
+			return Some(ALIGN);
+		}
+
+		let fit = self.free.iter().find(|(_, &len)| len >= size).map(|(&offset, &len)| (offset, len));
+		let offset = match fit {
+			Some((offset, len)) => {
+				self.free.remove(&offset);
+				if len > size {
+					self.free.insert(offset + size, len - size);
+				}
+				offset
+			}
+			None => {
+				let offset = self.top;
+				self.top = offset.checked_add(size)?;
+				offset
+			}
+		};
+
+		self.grow((offset + size) as usize);
+		self.in_use += size as u64;
+		Some(offset)
+	}
+
+	fn grow(&mut self, end: usize) {
+		if end > self.data.len() {
+			let pages = end.div_ceil(PAGE_SIZE);
+			self.data.resize(pages * PAGE_SIZE, 0);
+		}
+	}
+
+	fn deallocate(&mut self, offset: u32, size: u32) {
+		let size = match round_up(size) {
+			Some(size) if size > 0 && offset >= ALIGN && offset.checked_add(size).is_some_and(|end| end <= self.top) => size,
+			_ => return,
+		};
+		// Ignore blocks overlapping free ones, like a double free
+		let overlaps_prev = self.free.range(..=offset).next_back().is_some_and(|(&prev, &len)| prev + len > offset);
+		let overlaps_next = self.free.range(offset..offset + size).next().is_some();
+		if overlaps_prev || overlaps_next {
+			return;
+		}
+		self.in_use = self.in_use.saturating_sub(size as u64);
+
+		let (mut offset, mut size) = (offset, size);
+		// Merge with the free neighbours
+		if let Some((&prev, &len)) = self.free.range(..offset).next_back() {
+			if prev + len == offset {
+				self.free.remove(&prev);
+				offset = prev;
+				size += len;
+			}
+		}
+		if let Some(len) = self.free.remove(&(offset + size)) {
+			size += len;
+		}
+		if offset + size == self.top {
+			self.top = offset;
+		} else {
+			self.free.insert(offset, size);
+		}
+	}
+
+	fn range(&self, offset: u32, len: u32) -> Option<std::ops::Range<usize>> {
+		let start = offset as usize;
+		let end = start.checked_add(len as usize)?;
+		match end <= self.data.len() {
+			true => Some(start..end),
+			false => None,
+		}
+	}
+}
+
+/// Reserves `size` bytes and returns their offset.
+///
+/// # Panics
+///
+/// When the memory would grow past 4GiB, like a wasm32 guest running out of memory.
+pub fn allocate(size: u32) -> u32 {
+	memory().allocate(size).expect("simulated linear memory is full")
+}
+
+/// Releases a block returned by [`allocate`]. Blocks outside the allocated
+/// area or overlapping free ones, like on a double free, are ignored.
+pub fn deallocate(offset: u32, size: u32) {
+	memory().deallocate(offset, size)
+}
+
+/// Copies `len` bytes out of the memory, or `None` when out of bounds.
+pub fn read(offset: u32, len: u32) -> Option<Vec<u8>> {
+	let memory = memory();
+	memory.range(offset, len).map(|r| memory.data[r].to_vec())
+}
+
+/// Copies `data` into the memory, returning false when out of bounds.
+pub fn write(offset: u32, data: &[u8]) -> bool {
+	let mut memory = memory();
+	match memory.range(offset, data.len() as u32) {
+		Some(r) => {
+			memory.data[r].copy_from_slice(data);
+			true
+		}
+		None => false,
+	}
+}
+
+/// Size of the memory in bytes.
+pub fn size() -> u64 {
+	memory().data.len() as u64
+}
+
+/// Bytes currently allocated, rounded up to the allocation alignment.
+pub fn in_use() -> u64 {
+	memory().in_use
+}
@@ -0,0 +1,170 @@
+//! Signatures of the functions exported with #[wasmedge_bindgen], stored in
+//! a custom section of the module so that hosts can check them without
+//! running it.
+//!
+//! The section holds one record per function:
+//! `version: u8`, `name_len: u16`, the name, `flags: u8` (bit 0 set when the
+//! function returns a `Result`, bit 1 when it has a [packed](crate::packed)
+//! export, bit 2 when it has a [direct](crate::direct) one, bit 3 when it has
+//! a [batch](crate::batch) one), `params: u8` type tags, `results: u8` type tags.
+
+use std::fmt;
+
+use crate::{DecodeError, WireType};
+
+/// Name of the custom section.
+pub const SECTION: &str = "wasmedge_bindgen";
+
+pub const VERSION: u8 = 1;
+
+const FALLIBLE: u8 = 1;
+
+const PACKED: u8 = 2;
+
+const DIRECT: u8 = 4;
+
+const BATCH: u8 = 8;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Signature {
+	pub name: String,
+	pub params: Vec<WireType>,
+	pub results: Vec<WireType>,
+	/// Whether the function returns a `Result`, and so may fail with a guest error.
+	pub fallible: bool,
+	/// Whether the function also has a [packed](crate::packed) export.
+	pub packed: bool,
+	/// Whether the function also has a [direct](crate::direct) export.
+	pub direct: bool,
+	/// Whether the function also has a [batch](crate::batch) export.
+	pub batch: bool,
+}
+
+impl fmt::Display for Signature {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "{}{:?} -> {:?}", self.name, self.params, self.results)?;
+		if self.fallible {
+			write!(f, " or error")?;
+		}
+		Ok(())
+	}
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Manifest {
+	pub functions: Vec<Signature>,
+}
+
+impl Manifest {
+	/// Reads the manifest of a wasm module, `None` when it has none, e.g. when
+	/// built with an older `wasmedge-bindgen`.
+	pub fn from_module(wasm: &[u8]) -> Result<Option<Manifest>, DecodeError> {
+		custom_section(wasm, SECTION)?.map(|s| Manifest::decode(&s)).transpose()
+	}
+
+	pub fn decode(mut bytes: &[u8]) -> Result<Manifest, DecodeError> {
+		let mut functions = Vec::new();
+		while !bytes.is_empty() {
+			let version = take(&mut bytes, 1)?[0];
+			// Padding the linker may insert between records
+			if version == 0 {
+				continue;
+			}
+			if version != VERSION {
+				return Err(DecodeError::UnsupportedVersion(version));
+			}
+			let name_len = u16::from_le_bytes(take(&mut bytes, 2)?.try_into().unwrap());
+			let name = String::from_utf8(take(&mut bytes, name_len as usize)?.to_vec()).map_err(|_| DecodeError::InvalidUtf8)?;
+			let flags = take(&mut bytes, 1)?[0];
+			let params = take_types(&mut bytes)?;
+			let results = take_types(&mut bytes)?;
+			functions.push(Signature { name, params, results, fallible: flags & FALLIBLE != 0, packed: flags & PACKED != 0, direct: flags & DIRECT != 0, batch: flags & BATCH != 0 });
+		}
+		Ok(Manifest { functions })
+	}
+
+	pub fn encode(&self) -> Vec<u8> {
+		let mut out = Vec::new();
+		for f in self.functions.iter() {
+			out.push(VERSION);
+			out.extend_from_slice(&(f.name.len() as u16).to_le_bytes());
+			out.extend_from_slice(f.name.as_bytes());
+			let flags = [(f.fallible, FALLIBLE), (f.packed, PACKED), (f.direct, DIRECT), (f.batch, BATCH)];
+			out.push(flags.iter().filter(|(set, _)| *set).fold(0, |all, (_, flag)| all | flag));
+			for types in [&f.params, &f.results] {
+				out.push(types.len() as u8);
+				out.extend(types.iter().map(|ty| *ty as i32 as u8));
+			}
+		}
+		out
+	}
+
+	pub fn function(&self, name: &str) -> Option<&Signature> {
+		self.functions.iter().find(|f| f.name == name)
+	}
+
+	/// Checks that `new` still exports every function of this manifest with the
+	/// same signature, so that callers of this module can use `new` instead.
+	/// Functions only in `new` are fine, and so is a change of calling convention.
+	pub fn check_compatible(&self, new: &Manifest) -> Result<(), String> {
+		for old in self.functions.iter() {
+			match new.function(&old.name) {
+				None => return Err(format!("{} was removed", old.name)),
+				Some(f) if (&f.params, &f.results, f.fallible) != (&old.params, &old.results, old.fallible) => {
+					return Err(format!("{} changed from {} to {}", old.name, old, f));
+				}
+				Some(_) => {}
+			}
+		}
+		Ok(())
+	}
+}
+
+fn take<'a>(bytes: &mut &'a [u8], len: usize) -> Result<&'a [u8], DecodeError> {
+	if bytes.len() < len {
+		return Err(DecodeError::Truncated { expected: len, got: bytes.len() });
+	}
+	let (head, tail) = bytes.split_at(len);
+	*bytes = tail;
+	Ok(head)
+}
+
+fn take_types(bytes: &mut &[u8]) -> Result<Vec<WireType>, DecodeError> {
+	let count = take(bytes, 1)?[0];
+	take(bytes, count as usize)?.iter()
+		.map(|&tag| WireType::from_i32(tag as i32).ok_or(DecodeError::UnknownType(tag as i32)))
+		.collect()
+}
+
+fn leb128(bytes: &mut &[u8]) -> Result<u32, DecodeError> {
+	let mut value = 0u32;
+	for shift in (0..35).step_by(7) {
+		let byte = take(bytes, 1)?[0];
+		value |= ((byte & 0x7f) as u32).checked_shl(shift).unwrap_or(0);
+		if byte & 0x80 == 0 {
+			return Ok(value);
+		}
+	}
+	Err(DecodeError::NotWasm)
+}
+
+/// Payload of the custom sections called `name`, concatenated.
+fn custom_section(wasm: &[u8], name: &str) -> Result<Option<Vec<u8>>, DecodeError> {
+	let mut bytes = wasm;
+	if take(&mut bytes, 8).ok() != Some(b"\0asm\x01\0\0\0") {
+		return Err(DecodeError::NotWasm);
+	}
+	let mut section: Option<Vec<u8>> = None;
+	while !bytes.is_empty() {
+		let id = take(&mut bytes, 1)?[0];
+		let size = leb128(&mut bytes)?;
+		let mut payload = take(&mut bytes, size as usize)?;
+		if id == 0 {
+			let name_len = leb128(&mut payload)?;
+			if take(&mut payload, name_len as usize)? == name.as_bytes() {
+				section.get_or_insert_with(Vec::new).extend_from_slice(payload);
+			}
+		}
+	}
+	Ok(section)
+}
@@ -0,0 +1,114 @@
+//! Packed frames: all the parameters of a call in a single buffer, and all its
+//! results in another, so that a call takes one `allocate`, the call itself
+//! and one `deallocate` whatever the number of values.
+//!
+//! #[wasmedge_bindgen] exports the packed variant of a function `f` as
+//! `__wasmedge_bindgen_packed_f(pointer: i32, len: i32) -> i64`, next to the
+//! classic export, and flags it in the manifest. It takes the parameters
+//! buffer the host got from `allocate(len)`: `count: u32` then one record per
+//! parameter. It returns the pointer in the low and the length in the high 32
+//! bits of a results buffer, for the host to read and `deallocate`:
+//! `flag: u8`, then `count: u32` and one record per result on success (flag
+//! 0), or the UTF-8 message on error (flag 1).
+//!
+//! A record is `ty: u8`, `size: u32` in bytes, then the encoded value.
+
+use crate::{check_len, u32_at, DecodeError, WireType};
+
+/// Prefix of the name of packed exports.
+pub const PREFIX: &str = "__wasmedge_bindgen_packed_";
+
+/// Name of the packed variant of the export `name`.
+pub fn export_name(name: &str) -> String {
+	format!("{}{}", PREFIX, name)
+}
+
+/// Writes the records of a parameters or results buffer.
+#[derive(Clone, Debug)]
+pub struct Writer {
+	bytes: Vec<u8>,
+}
+
+impl Writer {
+	/// Starts a parameters buffer of `count` values.
+	pub fn params(count: usize) -> Self {
+		let mut bytes = Vec::with_capacity(4 + count * 13);
+		bytes.extend_from_slice(&(count as u32).to_le_bytes());
+		Writer { bytes }
+	}
+
+	/// Starts a successful results buffer of `count` values.
+	pub fn results(count: usize) -> Self {
+		let mut bytes = Vec::with_capacity(5 + count * 13);
+		bytes.push(0);
+		bytes.extend_from_slice(&(count as u32).to_le_bytes());
+		Writer { bytes }
+	}
+
+	/// Adds a record, the value being encoded by `encode` into the buffer.
+	pub fn push(&mut self, ty: WireType, encode: impl FnOnce(&mut Vec<u8>)) {
+		self.bytes.push(ty as i32 as u8);
+		let size_at = self.bytes.len();
+		self.bytes.extend_from_slice(&[0; 4]);
+		encode(&mut self.bytes);
+		let size = (self.bytes.len() - size_at - 4) as u32;
+		self.bytes[size_at..size_at + 4].copy_from_slice(&size.to_le_bytes());
+	}
+
+	pub fn finish(self) -> Vec<u8> {
+		self.bytes
+	}
+}
+
+/// Results buffer of a failed call.
+pub fn error(message: &str) -> Vec<u8> {
+	let mut bytes = Vec::with_capacity(1 + message.len());
+	bytes.push(1);
+	bytes.extend_from_slice(message.as_bytes());
+	bytes
+}
+
+/// Reads the records of a parameters or successful results buffer.
+#[derive(Clone, Debug)]
+pub struct Reader<'a> {
+	bytes: &'a [u8],
+	remaining: u32,
+}
+
+impl<'a> Reader<'a> {
+	pub fn params(bytes: &'a [u8]) -> Result<Self, DecodeError> {
+		check_len(bytes, 4)?;
+		Ok(Reader { remaining: u32_at(bytes, 0), bytes: &bytes[4..] })
+	}
+
+	/// Reads a results buffer, or returns the message of a failed call.
+	pub fn results(bytes: &'a [u8]) -> Result<Result<Self, String>, DecodeError> {
+		check_len(bytes, 1)?;
+		match bytes[0] {
+			0 => Reader::params(&bytes[1..]).map(Ok),
+			1 => Ok(Err(String::from_utf8_lossy(&bytes[1..]).into_owned())),
+			flag => Err(DecodeError::InvalidFlag(flag)),
+		}
+	}
+
+	/// Number of records left to read.
+	pub fn remaining(&self) -> u32 {
+		self.remaining
+	}
+
+	/// Type and encoded bytes of the next record, `None` after the last one.
+	pub fn next_value(&mut self) -> Result<Option<(WireType, &'a [u8])>, DecodeError> {
+		if self.remaining == 0 {
+			return Ok(None);
+		}
+		check_len(self.bytes, 5)?;
+		let raw_ty = self.bytes[0] as i32;
+		let ty = WireType::from_i32(raw_ty).ok_or(DecodeError::UnknownType(raw_ty))?;
+		let size = u32_at(self.bytes, 1) as usize;
+		check_len(&self.bytes[5..], size)?;
+		let (value, rest) = self.bytes[5..].split_at(size);
+		self.bytes = rest;
+		self.remaining -= 1;
+		Ok(Some((ty, value)))
+	}
+}
@@ -0,0 +1,80 @@
+use wasmedge_bindgen_abi::manifest::*;
+use wasmedge_bindgen_abi::*;
+
+fn manifest() -> Manifest {
+	Manifest {
+		functions: vec![
+			Signature { name: String::from("say"), params: vec![WireType::String], results: vec![WireType::U16, WireType::String], fallible: true, packed: false, direct: false, batch: false },
+			Signature { name: String::from("sum"), params: vec![WireType::I32Array], results: vec![WireType::I64], fallible: false, packed: false, direct: false, batch: false },
+		],
+	}
+}
+
+/// A module with nothing but `payload` in the manifest section.
+fn module(payload: &[u8]) -> Vec<u8> {
+	let mut section = vec![SECTION.len() as u8];
+	section.extend_from_slice(SECTION.as_bytes());
+	section.extend_from_slice(payload);
+	let mut wasm = b"\0asm\x01\0\0\0".to_vec();
+	wasm.push(0);
+	let mut size = section.len() as u32;
+	loop {
+		let byte = (size & 0x7f) as u8;
+		size >>= 7;
+		wasm.push(if size == 0 { byte } else { byte | 0x80 });
+		if size == 0 {
+			break;
+		}
+	}
+	wasm.extend_from_slice(&section);
+	wasm
+}
+
+#[test]
+fn encode_decode() {
+	let bytes = manifest().encode();
+	assert_eq!(&bytes[..7], &[1, 3, 0, b's', b'a', b'y', 1]);
+	assert_eq!(Manifest::decode(&bytes).unwrap(), manifest());
+	assert!(matches!(Manifest::decode(&bytes[..bytes.len() - 1]), Err(DecodeError::Truncated { .. })));
+	assert!(matches!(Manifest::decode(&[2]), Err(DecodeError::UnsupportedVersion(2))));
+}
+
+#[test]
+fn padding_between_records() {
+	let mut bytes = manifest().encode();
+	bytes.splice(12..12, [0, 0, 0]);
+	assert_eq!(Manifest::decode(&bytes[..]).unwrap().functions.len(), 2);
+}
+
+#[test]
+fn from_module() {
+	let wasm = module(&manifest().encode());
+	assert_eq!(Manifest::from_module(&wasm).unwrap(), Some(manifest()));
+	assert_eq!(Manifest::from_module(b"\0asm\x01\0\0\0").unwrap(), None);
+	assert!(matches!(Manifest::from_module(b"not wasm"), Err(DecodeError::NotWasm)));
+}
+
+#[test]
+fn compatibility() {
+	let old = manifest();
+	let mut new = manifest();
+	new.functions.push(Signature { name: String::from("more"), params: vec![], results: vec![], fallible: false, packed: false, direct: false, batch: false });
+	assert!(old.check_compatible(&new).is_ok());
+	assert!(new.check_compatible(&old).unwrap_err().contains("more was removed"));
+
+	new.functions[1].results = vec![WireType::U64];
+	assert_eq!(old.check_compatible(&new).unwrap_err(), "sum changed from sum[I32Array] -> [I64] to sum[I32Array] -> [U64]");
+}
+
+#[test]
+fn flags() {
+	let mut manifest = manifest();
+	manifest.functions[0].packed = true;
+	manifest.functions[1].direct = true;
+	manifest.functions[1].batch = true;
+	let bytes = manifest.encode();
+	assert_eq!(bytes[6], 1 | 2);
+	assert_eq!(bytes[bytes.len() - 5], 4 | 8);
+	assert_eq!(Manifest::decode(&bytes).unwrap(), manifest);
+	assert!(manifest.check_compatible(&self::manifest()).is_ok());
+}
@@ -0,0 +1,174 @@
+use std::any::Any;
+use std::fmt::Debug;
+
+use wasmedge_bindgen_abi::*;
+
+fn roundtrip<T: Wire + PartialEq + Debug + Clone + Send + Sync + 'static>(v: T) {
+	let bytes = v.to_bytes();
+	assert_eq!(bytes.len(), T::byte_len(v.length()), "{:?}", v);
+	assert_eq!(T::decode(&bytes).unwrap(), v);
+	assert_eq!(T::decode_owned(v.clone().into_bytes()).unwrap(), v);
+
+	let any: Box<dyn Any + Send + Sync> = decode_any(T::TYPE, &bytes).unwrap();
+	assert_eq!(*any.downcast::<T>().unwrap(), v);
+}
+
+#[test]
+fn scalars() {
+	roundtrip(0xabu8);
+	roundtrip(-5i8);
+	roundtrip(0xbeefu16);
+	roundtrip(-1234i16);
+	roundtrip(0xdeadbeefu32);
+	roundtrip(i32::MIN);
+	roundtrip(u64::MAX);
+	roundtrip(i64::MIN + 1);
+	roundtrip(3.25f32);
+	roundtrip(-1.0e300f64);
+	roundtrip(true);
+	roundtrip(false);
+	roundtrip('a');
+	roundtrip('🦀');
+}
+
+#[test]
+fn arrays() {
+	roundtrip(vec![0u8, 1, 255]);
+	roundtrip(vec![-128i8, 0, 127]);
+	roundtrip(vec![1u16, u16::MAX]);
+	roundtrip(vec![i16::MIN, -1, i16::MAX]);
+	roundtrip(vec![7u32; 5]);
+	roundtrip(vec![-7i32, 0, 7]);
+	roundtrip(vec![u64::MAX, 0]);
+	roundtrip(vec![i64::MIN, i64::MAX]);
+	roundtrip(Vec::<u16>::new());
+	roundtrip(String::from("hello wasmedge-bindgen"));
+	roundtrip(String::new());
+}
+
+#[test]
+fn scalar_lengths() {
+	// A scalar always counts as one element, whatever its width.
+	assert_eq!(1u16.length(), 1);
+	assert_eq!(u16::byte_len(1), 2);
+	assert_eq!(i16::byte_len(1), 2);
+	assert_eq!(char::byte_len(1), 4);
+	assert_eq!(Vec::<u64>::byte_len(3), 24);
+	assert_eq!(String::byte_len(3), 3);
+}
+
+#[test]
+fn invalid_values() {
+	assert_eq!(bool::decode(&[2]), Err(DecodeError::InvalidBool(2)));
+	assert_eq!(char::decode(&0xd800u32.to_le_bytes()), Err(DecodeError::InvalidChar(0xd800)));
+	assert_eq!(String::decode(&[0xff, 0xfe]), Err(DecodeError::InvalidUtf8));
+	assert!(matches!(u32::decode(&[1, 2]), Err(DecodeError::InvalidLength { .. })));
+	assert!(matches!(Vec::<u16>::decode(&[1, 2, 3]), Err(DecodeError::InvalidLength { .. })));
+}
+
+#[test]
+fn slices_match_vecs() {
+	let v = vec![1i32, -2, 3];
+	assert_eq!(encode_slice(&v), v.to_bytes());
+	assert_eq!(encode_slice(&v[..]), v.to_bytes());
+}
+
+#[test]
+fn type_tags() {
+	for tag in (0..40).filter_map(WireType::from_i32) {
+		assert_eq!(WireType::from_i32(tag as i32), Some(tag));
+	}
+	assert_eq!(WireType::from_i32(31), Some(WireType::String));
+	assert_eq!(WireType::from_i32(13), None);
+	assert!(WireType::Char.is_scalar());
+	assert!(!WireType::U8Array.is_scalar());
+}
+
+#[test]
+fn header() {
+	for header in [ReturnHeader::ok(0x1000, 2), ReturnHeader::err(0xfffffff0, 17)] {
+		let bytes = header.encode();
+		assert_eq!(bytes.len(), HEADER_SIZE);
+		assert_eq!(ReturnHeader::decode(&bytes).unwrap(), header);
+	}
+	assert_eq!(ReturnHeader::ok(1, 2).encode(), [0, 1, 0, 0, 0, 2, 0, 0, 0]);
+	assert_eq!(ReturnHeader::decode(&[2; HEADER_SIZE]), Err(DecodeError::InvalidFlag(2)));
+	assert!(matches!(ReturnHeader::decode(&[0; 4]), Err(DecodeError::Truncated { .. })));
+}
+
+#[test]
+fn result_entries() {
+	let entries = vec![
+		ResultEntry { pointer: 8, ty: WireType::U16, size: 2 },
+		ResultEntry { pointer: 1024, ty: WireType::String, size: 11 },
+	];
+	let bytes = ResultEntry::encode_all(&entries);
+	assert_eq!(bytes.len(), entries.len() * ENTRY_SIZE);
+	assert_eq!(ResultEntry::decode_all(&bytes, entries.len()).unwrap(), entries);
+	assert!(ResultEntry::decode_all(&bytes, 3).is_err());
+
+	let mut bad = bytes.clone();
+	bad[4] = 99;
+	assert_eq!(ResultEntry::decode_all(&bad, 1), Err(DecodeError::UnknownType(99)));
+}
+
+#[test]
+fn param_frame() {
+	let entries = vec![ParamEntry { pointer: 16, length: 1 }, ParamEntry { pointer: 32, length: 5 }];
+	let bytes = ParamEntry::encode_all(&entries);
+	assert_eq!(bytes, [16, 0, 0, 0, 1, 0, 0, 0, 32, 0, 0, 0, 5, 0, 0, 0]);
+	assert_eq!(ParamEntry::decode_all(&bytes, 2).unwrap(), entries);
+	assert!(ParamEntry::decode_all(&bytes, 3).is_err());
+}
+
+#[test]
+fn packed_frames() {
+	let mut params = packed::Writer::params(2);
+	params.push(WireType::U16, |out| 7u16.encode(out));
+	params.push(WireType::String, |out| "hi".to_string().encode(out));
+	let bytes = params.finish();
+	assert_eq!(bytes, [2, 0, 0, 0, 3, 2, 0, 0, 0, 7, 0, 31, 2, 0, 0, 0, b'h', b'i']);
+
+	let mut reader = packed::Reader::params(&bytes).unwrap();
+	assert_eq!(reader.remaining(), 2);
+	assert_eq!(reader.next_value().unwrap(), Some((WireType::U16, &[7, 0][..])));
+	assert_eq!(reader.next_value().unwrap(), Some((WireType::String, &b"hi"[..])));
+	assert_eq!(reader.next_value().unwrap(), None);
+	let mut truncated = packed::Reader::params(&bytes[..bytes.len() - 1]).unwrap();
+	truncated.next_value().unwrap();
+	assert!(matches!(truncated.next_value(), Err(DecodeError::Truncated { .. })));
+
+	let mut results = packed::Writer::results(1);
+	results.push(WireType::Bool, |out| true.encode(out));
+	let bytes = results.finish();
+	let mut reader = packed::Reader::results(&bytes).unwrap().unwrap();
+	assert_eq!(reader.next_value().unwrap(), Some((WireType::Bool, &[1][..])));
+	assert_eq!(packed::Reader::results(&packed::error("nope")).unwrap().unwrap_err(), "nope");
+	assert_eq!(packed::Reader::results(&[2]).unwrap_err(), DecodeError::InvalidFlag(2));
+}
+
+#[test]
+fn batch_frames() {
+	let mut params = batch::Writer::params(2);
+	params.push(&[1, 0, 0, 0]);
+	params.push(&[]);
+	let bytes = params.finish();
+	assert_eq!(bytes, [2, 0, 0, 0, 4, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0]);
+
+	let mut reader = batch::Reader::params(&bytes).unwrap();
+	assert_eq!(reader.remaining(), 2);
+	assert_eq!(reader.next_call().unwrap(), Some(&[1, 0, 0, 0][..]));
+	assert_eq!(reader.next_call().unwrap(), Some(&[][..]));
+	assert_eq!(reader.next_call().unwrap(), None);
+	let mut truncated = batch::Reader::params(&bytes[..7]).unwrap();
+	assert!(matches!(truncated.next_call(), Err(DecodeError::Truncated { .. })));
+
+	let mut results = batch::Writer::results(2);
+	results.push(&packed::error("first"));
+	results.push(&packed::Writer::results(0).finish());
+	let bytes = results.finish();
+	let mut reader = batch::Reader::results(&bytes).unwrap().unwrap();
+	assert_eq!(packed::Reader::results(reader.next_call().unwrap().unwrap()).unwrap().unwrap_err(), "first");
+	assert_eq!(packed::Reader::results(reader.next_call().unwrap().unwrap()).unwrap().unwrap().remaining(), 0);
+	assert_eq!(batch::Reader::results(&packed::error("bad batch")).unwrap().unwrap_err(), "bad batch");
+}
@@ -1,626 +1,276 @@
 extern crate proc_macro;
 
 use proc_macro::TokenStream;
-use quote::{quote, ToTokens};
-use syn;
-
-static mut FUNC_NUMBER: i32 = 0;
-
-enum RetTypes {
-	U8 = 1,
-	I8 = 2,
-	U16 = 3,
-	I16 = 4,
-	U32 = 5,
-	I32 = 6,
-	U64 = 7,
-	I64 = 8,
-	F32 = 9,
-	F64 = 10,
-	Bool = 11,
-	Char = 12,
-	U8Array = 21,
-	I8Array = 22,
-	U16Array = 23,
-	I16Array = 24,
-	U32Array = 25,
-	I32Array = 26,
-	U64Array = 27,
-	I64Array = 28,
-	String = 31,
-}
+use quote::{format_ident, quote};
 
 #[proc_macro_attribute]
-pub fn wasmedge_bindgen(_: TokenStream, item: TokenStream) -> TokenStream {
+pub fn wasmedge_bindgen(attr: TokenStream, item: TokenStream) -> TokenStream {
 	let mut ast: syn::ItemFn = syn::parse(item).unwrap();
+	let options = match parse_options(attr) {
+		Ok(options) => options,
+		Err(e) => return e.to_compile_error().into(),
+	};
 
-	let func_ident = ast.sig.ident;
+	let func_ident = ast.sig.ident.clone();
 
-	let ori_run: String;
-	unsafe {
-		ori_run = format!("run{}", FUNC_NUMBER);
-		FUNC_NUMBER += 1;
-	}
-	let ori_run_ident = proc_macro2::Ident::new(ori_run.as_str(), proc_macro2::Span::call_site());
+	let ori_run_ident = format_ident!("__wasmedge_bindgen_{}", func_ident);
 	ast.sig.ident = ori_run_ident.clone();
 
-	let (arg_names, arg_values) = parse_params(&ast);
-	let (ret_names, ret_pointers, ret_types, ret_sizes, is_rust_result) = parse_returns(&ast);
-	let ret_len = ret_names.len();
-	let ret_i = (0..ret_len).map(syn::Index::from);
+	let (arg_names, arg_types) = match parse_params(&ast) {
+		Ok(r) => r,
+		Err(e) => return e.to_compile_error().into(),
+	};
+	let (ret_types, is_rust_result) = parse_returns(&ast);
+	let ret_names: Vec<syn::Ident> = (0..ret_types.len()).map(|pos| format_ident!("ret{}", pos)).collect();
+
+	// Only numbers in and at most one out can be passed as plain wasm values:
+	// several would need a multi-value export, which Rust can't declare
+	let direct = !is_rust_result && ret_types.len() <= 1 && arg_types.iter().chain(ret_types.iter()).all(is_number);
+	if options.direct && !direct {
+		let message = match ret_types.len() {
+			0 | 1 => "#[wasmedge_bindgen(direct)] functions take only numbers and return at most one, not in a Result",
+			_ => "#[wasmedge_bindgen(direct)] functions return at most one number, as Rust can't export multi-value functions",
+		};
+		return syn::Error::new_spanned(&ast.sig, message).to_compile_error().into();
+	}
+	let rets_len = ret_types.len();
 
 	let params_len = arg_names.len();
 	let i = (0..params_len).map(syn::Index::from);
+	let index = 0..params_len;
+
+	let ret_pattern = match ret_names.len() {
+		1 => quote! { #(#ret_names)* },
+		_ => quote! { (#(#ret_names),*) },
+	};
+	let return_result = quote! {
+		let entries = [#(
+			::wasmedge_bindgen::abi::guest::result_entry::<#ret_types>(#ret_names)
+		),*];
+		::wasmedge_bindgen::abi::guest::return_ok(&entries)
+	};
 
-	let ret_result = match is_rust_result {
+	let packed_results = quote! {
+		#[allow(unused_mut)]
+		let mut results = ::wasmedge_bindgen::abi::packed::Writer::results(#rets_len);
+		#(
+			::wasmedge_bindgen::abi::guest::packed_result::<#ret_types>(&mut results, #ret_names);
+		)*
+		results.finish()
+	};
+
+	let run = |return_result: proc_macro2::TokenStream, return_error: proc_macro2::TokenStream| match is_rust_result {
 		true => quote! {
 			match #ori_run_ident(#(#arg_names),*) {
-				Ok((#(#ret_names),*)) => {
-					let mut result_vec = vec![0; #ret_len * 3];
-					#(
-						result_vec[#ret_i * 3 + 2] = #ret_sizes;
-						result_vec[#ret_i * 3] = #ret_pointers;
-						result_vec[#ret_i * 3 + 1] = #ret_types;
-					)*
-					let result_vec = std::mem::ManuallyDrop::new(result_vec);
-					// return_result
-					let mut rvec = vec![0 as u8; 9];
-					rvec.splice(1..5, (result_vec.as_ptr() as i32).to_le_bytes());
-					rvec.splice(5..9, (#ret_len as i32).to_le_bytes());
-					let rvec = std::mem::ManuallyDrop::new(rvec);
-					return rvec.as_ptr() as i32;
+				Ok(#ret_pattern) => {
+					#return_result
 				}
 				Err(message) => {
-					let message = std::mem::ManuallyDrop::new(message);
-					// return_error
-					let mut rvec = vec![1 as u8; 9];
-					rvec.splice(1..5, (message.as_ptr() as i32).to_le_bytes());
-					rvec.splice(5..9, (message.len() as i32).to_le_bytes());
-					let rvec = std::mem::ManuallyDrop::new(rvec);
-					return rvec.as_ptr() as i32;
+					#return_error(message)
 				}
 			}
 		},
 		false => quote! {
-			let (#(#ret_names),*) = #ori_run_ident(#(#arg_names),*);
-			let mut result_vec = vec![0; #ret_len * 3];
-			#(
-				result_vec[#ret_i * 3 + 2] = #ret_sizes;
-				result_vec[#ret_i * 3] = #ret_pointers;
-				result_vec[#ret_i * 3 + 1] = #ret_types;
-			)*
-			let result_vec = std::mem::ManuallyDrop::new(result_vec);
-			// return_result
-			let mut rvec = vec![0 as u8; 9];
-			rvec.splice(1..5, (result_vec.as_ptr() as i32).to_le_bytes());
-			rvec.splice(5..9, (#ret_len as i32).to_le_bytes());
-			let rvec = std::mem::ManuallyDrop::new(rvec);
-			return rvec.as_ptr() as i32;
+			let #ret_pattern = #ori_run_ident(#(#arg_names),*);
+			#return_result
 		}
 	};
+	let ret_result = run(return_result, quote! { ::wasmedge_bindgen::abi::guest::return_error });
+	let packed_ret_result = run(packed_results, quote! { ::wasmedge_bindgen::abi::guest::packed_error });
+
+	let manifest_ident = format_ident!("__wasmedge_bindgen_manifest_{}", func_ident);
+	let packed_ident = format_ident!("__wasmedge_bindgen_packed_{}", func_ident);
+	let packed_run_ident = format_ident!("__wasmedge_bindgen_packed_run_{}", func_ident);
+	let name = func_ident.to_string();
+	let [name_len_lo, name_len_hi] = (name.len() as u16).to_le_bytes();
+	let name_bytes = name.bytes();
+	let (params_count, rets_count) = (arg_types.len() as u8, ret_types.len() as u8);
+	// Bit 0: the function returns a `Result`, bit 1: the packed export below is
+	// there, bit 2: the direct one, bit 3: the batch one, as in `abi::manifest`
+	let flags = is_rust_result as u8 | 2 | (direct as u8) << 2 | (options.batch as u8) << 3;
+	let record_len = 1 + 2 + name.len() + 1 + 1 + arg_types.len() + 1 + ret_types.len();
+	// Record of the signature in the manifest section, see `abi::manifest`
+	let manifest_record = quote! {
+		#[doc(hidden)]
+		#[used]
+		#[cfg_attr(target_arch = "wasm32", link_section = "wasmedge_bindgen")]
+		#[allow(non_upper_case_globals)]
+		pub static #manifest_ident: [u8; #record_len] = [
+			::wasmedge_bindgen::abi::manifest::VERSION, #name_len_lo, #name_len_hi, #(#name_bytes,)* #flags,
+			#params_count, #(<#arg_types as ::wasmedge_bindgen::abi::Wire>::TYPE as i32 as u8,)*
+			#rets_count, #(<#ret_types as ::wasmedge_bindgen::abi::Wire>::TYPE as i32 as u8,)*
+		];
+	};
+
+	let direct_export = match direct {
+		true => {
+			let direct_ident = format_ident!("__wasmedge_bindgen_direct_{}", func_ident);
+			let output = match ret_types.first() {
+				Some(ty) => quote! { -> #ty },
+				None => quote! {},
+			};
+			quote! {
+				#[cfg_attr(target_arch = "wasm32", no_mangle)]
+				pub extern "C" fn #direct_ident(#(#arg_names: #arg_types),*) #output {
+					#ori_run_ident(#(#arg_names),*)
+				}
+			}
+		}
+		false => quote! {},
+	};
+
+	let batch_export = match options.batch {
+		true => {
+			let batch_ident = format_ident!("__wasmedge_bindgen_batch_{}", func_ident);
+			quote! {
+				#[cfg_attr(target_arch = "wasm32", no_mangle)]
+				pub unsafe extern "C" fn #batch_ident(params_pointer: u32, params_len: u32) -> u64 {
+					let bytes = match ::wasmedge_bindgen::abi::guest::take_packed(params_pointer, params_len) {
+						Ok(bytes) => bytes,
+						Err(message) => return ::wasmedge_bindgen::abi::guest::return_packed_error(message),
+					};
+					::wasmedge_bindgen::abi::guest::run_batch(&bytes, #packed_run_ident)
+				}
+			}
+		}
+		false => quote! {},
+	};
 
 	let gen = quote! {
+		#manifest_record
 
-		#[no_mangle]
+		#direct_export
+
+		#batch_export
+
+		#[cfg_attr(target_arch = "wasm32", no_mangle)]
 		pub unsafe extern "C" fn #func_ident(params_pointer: *mut u32, params_count: i32) -> i32 {
-			if #params_len != params_count as usize {
-				let err_msg = format!("Invalid params count, expect {}, got {}", #params_len, params_count);
-				let err_msg = std::mem::ManuallyDrop::new(err_msg);
-				// return_error
-				let mut rvec = vec![1 as u8; 9];
-				rvec.splice(1..5, (err_msg.as_ptr() as i32).to_le_bytes());
-				rvec.splice(5..9, (err_msg.len() as i32).to_le_bytes());
-				let rvec = std::mem::ManuallyDrop::new(rvec);
-				return rvec.as_ptr() as i32;
-			}
+			let frame = match ::wasmedge_bindgen::abi::guest::take_frame(params_pointer as usize as u32, params_count, #params_len) {
+				Ok(frame) => frame,
+				Err(message) => return ::wasmedge_bindgen::abi::guest::return_error(message),
+			};
+
+			#(
+			let #arg_names: #arg_types = match ::wasmedge_bindgen::abi::guest::take_param(&frame[#i]) {
+				Ok(v) => v,
+				Err(message) => return ::wasmedge_bindgen::abi::guest::return_error(message),
+			};
+			)*
+
+			#ret_result
+		}
+
+		#[cfg_attr(target_arch = "wasm32", no_mangle)]
+		pub unsafe extern "C" fn #packed_ident(params_pointer: u32, params_len: u32) -> u64 {
+			let bytes = match ::wasmedge_bindgen::abi::guest::take_packed(params_pointer, params_len) {
+				Ok(bytes) => bytes,
+				Err(message) => return ::wasmedge_bindgen::abi::guest::return_packed_error(message),
+			};
+			::wasmedge_bindgen::abi::guest::return_packed(#packed_run_ident(&bytes))
+		}
+
+		/// Turns a packed parameters buffer into the packed results buffer.
+		fn #packed_run_ident(bytes: &[u8]) -> Vec<u8> {
+			#[allow(unused_mut, unused_variables)]
+			let mut params = match ::wasmedge_bindgen::abi::guest::packed_params(bytes, #params_len) {
+				Ok(params) => params,
+				Err(message) => return ::wasmedge_bindgen::abi::guest::packed_error(message),
+			};
 
 			#(
-			let pointer = *params_pointer.offset(#i * 2) as *mut u8;
-			let size= *params_pointer.offset(#i * 2 + 1);
-			let #arg_names = #arg_values;
+			let #arg_names: #arg_types = match ::wasmedge_bindgen::abi::guest::packed_param(&mut params, #index) {
+				Ok(v) => v,
+				Err(message) => return ::wasmedge_bindgen::abi::guest::packed_error(message),
+			};
 			)*
 
-			#ret_result;
+			#packed_ret_result
 		}
+
+		#ast
 	};
 
-	let ori_run_str = ast.to_token_stream().to_string();
-	let x = gen.to_string() + &ori_run_str;
-	x.parse().unwrap()
+	gen.into()
 }
 
+#[derive(Default)]
+struct Options {
+	/// Fail to compile unless the function gets a direct export.
+	direct: bool,
+	/// Also export the function for many calls at once, see `abi::batch`.
+	batch: bool,
+}
 
-fn parse_returns(ast: &syn::ItemFn) -> (Vec::<syn::Ident>, Vec::<proc_macro2::TokenStream>, Vec::<i32>, Vec::<proc_macro2::TokenStream>, bool) {
-	let mut ret_names = Vec::<syn::Ident>::new();
-	let mut ret_pointers = Vec::<proc_macro2::TokenStream>::new();
-	let mut ret_types = Vec::<i32>::new();
-	let mut ret_sizes = Vec::<proc_macro2::TokenStream>::new();
-	let mut is_rust_result = false;
-
-	let mut prep_types = |seg: &syn::PathSegment, pos: usize| {
-		let ret_name = quote::format_ident!("ret{}", pos.to_string());
-		match seg.ident.to_string().as_str() {
-			"u8" => {
-				ret_pointers.push(quote! {{
-					let x = #ret_name.to_le_bytes()[..].to_vec();
-					std::mem::ManuallyDrop::new(x).as_ptr() as *const u8 as i32
-				}});
-				ret_names.push(ret_name);
-				ret_types.push(RetTypes::U8 as i32);
-				ret_sizes.push(quote! {
-					1
-				});
-			}
-			"i8" => {
-				ret_pointers.push(quote! {{
-					let x = #ret_name.to_le_bytes()[..].to_vec();
-					std::mem::ManuallyDrop::new(x).as_ptr() as *const i8 as i32
-				}});
-				ret_names.push(ret_name);
-				ret_types.push(RetTypes::I8 as i32);
-				ret_sizes.push(quote! {
-					1
-				});
-			}
-			"u16" => {
-				ret_pointers.push(quote! {{
-					let x = #ret_name.to_le_bytes()[..].to_vec();
-					std::mem::ManuallyDrop::new(x).as_ptr() as *const u16 as i32
-				}});
-				ret_names.push(ret_name);
-				ret_types.push(RetTypes::U16 as i32);
-				ret_sizes.push(quote! {
-					2
-				});
-			}
-			"i16" => {
-				ret_pointers.push(quote! {{
-					let x = #ret_name.to_le_bytes()[..].to_vec();
-					std::mem::ManuallyDrop::new(x).as_ptr() as *const i16 as i32
-				}});
-				ret_names.push(ret_name);
-				ret_types.push(RetTypes::I16 as i32);
-				ret_sizes.push(quote! {
-					2
-				});
-			}
-			"u32" => {
-				ret_pointers.push(quote! {{
-					let x = #ret_name.to_le_bytes()[..].to_vec();
-					std::mem::ManuallyDrop::new(x).as_ptr() as *const u32 as i32
-				}});
-				ret_names.push(ret_name);
-				ret_types.push(RetTypes::U32 as i32);
-				ret_sizes.push(quote! {
-					4
-				});
-			}
-			"i32" => {
-				ret_pointers.push(quote! {{
-					let x = #ret_name.to_le_bytes()[..].to_vec();
-					std::mem::ManuallyDrop::new(x).as_ptr() as *const i32 as i32
-				}});
-				ret_names.push(ret_name);
-				ret_types.push(RetTypes::I32 as i32);
-				ret_sizes.push(quote! {
-					4
-				});
-			}
-			"u64" => {
-				ret_pointers.push(quote! {{
-					let x = #ret_name.to_le_bytes()[..].to_vec();
-					std::mem::ManuallyDrop::new(x).as_ptr() as *const u64 as i32
-				}});
-				ret_names.push(ret_name);
-				ret_types.push(RetTypes::U64 as i32);
-				ret_sizes.push(quote! {
-					8
-				});
-			}
-			"i64" => {
-				ret_pointers.push(quote! {{
-					let x = #ret_name.to_le_bytes()[..].to_vec();
-					std::mem::ManuallyDrop::new(x).as_ptr() as *const i64 as i32
-				}});
-				ret_names.push(ret_name);
-				ret_types.push(RetTypes::I64 as i32);
-				ret_sizes.push(quote! {
-					8
-				});
-			}
-			"f32" => {
-				ret_pointers.push(quote! {{
-					let x = #ret_name.to_le_bytes()[..].to_vec();
-					std::mem::ManuallyDrop::new(x).as_ptr() as *const f32 as i32
-				}});
-				ret_names.push(ret_name);
-				ret_types.push(RetTypes::F32 as i32);
-				ret_sizes.push(quote! {
-					4
-				});
-			}
-			"f64" => {
-				ret_pointers.push(quote! {{
-					let x = #ret_name.to_le_bytes()[..].to_vec();
-					std::mem::ManuallyDrop::new(x).as_ptr() as *const f64 as i32
-				}});
-				ret_names.push(ret_name);
-				ret_types.push(RetTypes::F64 as i32);
-				ret_sizes.push(quote! {
-					8
-				});
-			}
-			"bool" => {
-				ret_pointers.push(quote! {{
-					let x = #ret_name.to_le_bytes()[..].to_vec();
-					std::mem::ManuallyDrop::new(x).as_ptr() as *const bool as i32
-				}});
-				ret_names.push(ret_name);
-				ret_types.push(RetTypes::Bool as i32);
-				ret_sizes.push(quote! {
-					1
-				});
-			}
-			"char" => {
-				ret_pointers.push(quote! {{
-					let x = #ret_name.to_le_bytes()[..].to_vec();
-					std::mem::ManuallyDrop::new(x).as_ptr() as *const char as i32
-				}});
-				ret_names.push(ret_name);
-				ret_types.push(RetTypes::Char as i32);
-				ret_sizes.push(quote! {
-					4
-				});
-			}
-			"String" => {
-				ret_pointers.push(quote! {
-					std::mem::ManuallyDrop::new(#ret_name).as_ptr() as i32
-				});
-				ret_types.push(RetTypes::String as i32);
-				ret_sizes.push(quote! {
-					#ret_name.len() as i32
-				});
-				ret_names.push(ret_name);
-			}
-			"Vec" => {
-				match &seg.arguments {
-					syn::PathArguments::AngleBracketed(args) => {
-						match args.args.first().unwrap() {
-							syn::GenericArgument::Type(arg_type) => {
-								match arg_type {
-									syn::Type::Path(arg_type_path) => {
-										let arg_seg = arg_type_path.path.segments.first().unwrap();
-										match arg_seg.ident.to_string().as_str() {
-											"u8" => {
-												ret_pointers.push(quote! {
-													std::mem::ManuallyDrop::new(#ret_name).as_ptr() as i32
-												});
-												ret_sizes.push(quote! {
-													#ret_name.len() as i32
-												});
-												ret_types.push(RetTypes::U8Array as i32);
-												ret_names.push(ret_name);
-											}
-											"i8" => {
-												ret_pointers.push(quote! {
-													std::mem::ManuallyDrop::new(#ret_name).as_ptr() as i32
-												});
-												ret_sizes.push(quote! {
-													#ret_name.len() as i32
-												});
-												ret_types.push(RetTypes::I8Array as i32);
-												ret_names.push(ret_name);
-											}
-											"u16" => {
-												ret_pointers.push(quote! {
-													std::mem::ManuallyDrop::new(#ret_name).as_ptr() as i32
-												});
-												ret_sizes.push(quote! {
-													#ret_name.len() as i32 * 2
-												});
-												ret_types.push(RetTypes::U16Array as i32);
-												ret_names.push(ret_name);
-											}
-											"i16" => {
-												ret_pointers.push(quote! {
-													std::mem::ManuallyDrop::new(#ret_name).as_ptr() as i32
-												});
-												ret_sizes.push(quote! {
-													#ret_name.len() as i32 * 2
-												});
-												ret_types.push(RetTypes::I16Array as i32);
-												ret_names.push(ret_name);
-											}
-											"u32" => {
-												ret_pointers.push(quote! {
-													std::mem::ManuallyDrop::new(#ret_name).as_ptr() as i32
-												});
-												ret_sizes.push(quote! {
-													#ret_name.len() as i32 * 4
-												});
-												ret_types.push(RetTypes::U32Array as i32);
-												ret_names.push(ret_name);
-											}
-											"i32" => {
-												ret_pointers.push(quote! {
-													std::mem::ManuallyDrop::new(#ret_name).as_ptr() as i32
-												});
-												ret_sizes.push(quote! {
-													#ret_name.len() as i32 * 4
-												});
-												ret_types.push(RetTypes::I32Array as i32);
-												ret_names.push(ret_name);
-											}
-											"u64" => {
-												ret_pointers.push(quote! {
-													std::mem::ManuallyDrop::new(#ret_name).as_ptr() as i32
-												});
-												ret_sizes.push(quote! {
-													#ret_name.len() as i32 * 8
-												});
-												ret_types.push(RetTypes::U64Array as i32);
-												ret_names.push(ret_name);
-											}
-											"i64" => {
-												ret_pointers.push(quote! {
-													std::mem::ManuallyDrop::new(#ret_name).as_ptr() as i32
-												});
-												ret_sizes.push(quote! {
-													#ret_name.len() as i32 * 8
-												});
-												ret_types.push(RetTypes::I64Array as i32);
-												ret_names.push(ret_name);
-											}
-											_ => {}
-										}
-									}
-									_ => {}
-								}
-							}
-							_ => {}
-						}
-					}
-					_ => {}
-				}
-			}	
-			_ => {}
+fn parse_options(attr: TokenStream) -> syn::Result<Options> {
+	use syn::parse::Parser;
+
+	let mut options = Options::default();
+	let idents = syn::punctuated::Punctuated::<syn::Ident, syn::Token![,]>::parse_terminated.parse(attr)?;
+	for ident in idents {
+		match ident.to_string().as_str() {
+			"direct" => options.direct = true,
+			"batch" => options.batch = true,
+			_ => return Err(syn::Error::new_spanned(&ident, "unknown option, expected `direct` or `batch`")),
 		}
+	}
+	Ok(options)
+}
+
+/// Whether `ty` is one of the number types passed as plain wasm values, see `abi::direct`.
+fn is_number(ty: &syn::Type) -> bool {
+	const NUMBERS: [&str; 10] = ["u8", "i8", "u16", "i16", "u32", "i32", "u64", "i64", "f32", "f64"];
+	match ty {
+		syn::Type::Path(path) if path.qself.is_none() => path.path.get_ident().is_some_and(|ident| NUMBERS.iter().any(|n| ident == n)),
+		syn::Type::Paren(paren) => is_number(&paren.elem),
+		_ => false,
+	}
+}
+
+/// Splits the returned type into the list of values sent back to the host,
+/// unwrapping `Result<T, String>` and tuples.
+fn parse_returns(ast: &syn::ItemFn) -> (Vec<syn::Type>, bool) {
+	let rt = match &ast.sig.output {
+		syn::ReturnType::Type(_, rt) => &**rt,
+		syn::ReturnType::Default => return (Vec::new(), false),
 	};
 
-	match ast.sig.output {
-		syn::ReturnType::Type(_, ref rt) => {
-			match &**rt {
-				syn::Type::Path(type_path) => {
-					let seg = &type_path.path.segments.first().unwrap();
-					let seg_type = seg.ident.to_string();
-					if seg_type == "Result"  {
-						is_rust_result = true;
-						match &seg.arguments {
-							syn::PathArguments::AngleBracketed(args) => {
-								match args.args.first().unwrap() {
-									syn::GenericArgument::Type(arg_type) => {
-										match arg_type {
-											syn::Type::Path(arg_type_path) => {
-												let arg_seg = arg_type_path.path.segments.first().unwrap();
-												prep_types(&arg_seg, 0)
-											}
-											syn::Type::Tuple(arg_type_tuple) => {
-												for (pos, elem) in arg_type_tuple.elems.iter().enumerate() {
-													match elem {
-														syn::Type::Path(type_path) => {
-															let seg = &type_path.path.segments.first().unwrap();
-															prep_types(&seg, pos);
-														}
-														_ => {}
-													}
-												}
-											}
-											_ => {}
-										}
-									}
-									_ => {}
-								}
-							}
-							_ => {}
-						}
-					} else {
-						prep_types(&seg, 0);
-					}
-				}
-				syn::Type::Tuple(type_tuple) => {
-					for (pos, elem) in type_tuple.elems.iter().enumerate() {
-						match elem {
-							syn::Type::Path(type_path) => {
-								let seg = &type_path.path.segments.first().unwrap();
-								prep_types(&seg, pos);
-							}
-							_ => {}
-						}
-					}
+	if let syn::Type::Path(type_path) = rt {
+		let seg = type_path.path.segments.last().unwrap();
+		if seg.ident == "Result" {
+			if let syn::PathArguments::AngleBracketed(args) = &seg.arguments {
+				if let Some(syn::GenericArgument::Type(ok_type)) = args.args.first() {
+					return (split_tuple(ok_type), true);
 				}
-				_ => {}
 			}
 		}
-		_ => {}
 	}
 
-	(ret_names, ret_pointers, ret_types, ret_sizes, is_rust_result)
+	(split_tuple(rt), false)
+}
+
+fn split_tuple(ty: &syn::Type) -> Vec<syn::Type> {
+	match ty {
+		syn::Type::Tuple(type_tuple) => type_tuple.elems.iter().cloned().collect(),
+		syn::Type::Paren(type_paren) => split_tuple(&type_paren.elem),
+		_ => vec![ty.clone()],
+	}
 }
 
-fn parse_params(ast: &syn::ItemFn) -> (Vec::<syn::Ident>, Vec::<proc_macro2::TokenStream>) {
+fn parse_params(ast: &syn::ItemFn) -> syn::Result<(Vec<syn::Ident>, Vec<syn::Type>)> {
 	let mut arg_names = Vec::<syn::Ident>::new();
-	let mut arg_values = Vec::<proc_macro2::TokenStream>::new();
+	let mut arg_types = Vec::<syn::Type>::new();
 
-	let params_iter = ast.sig.inputs.iter();
-	for (pos, param) in params_iter.enumerate() {
+	for (pos, param) in ast.sig.inputs.iter().enumerate() {
 		match param {
 			syn::FnArg::Typed(param_type) => {
-				match &*param_type.ty {
-					syn::Type::Path(type_path) => {
-						let seg = &type_path.path.segments.first().unwrap();
-						match seg.ident.to_string().as_str() {
-							"Vec" => {
-								match &seg.arguments {
-									syn::PathArguments::AngleBracketed(args) => {
-										match args.args.first().unwrap() {
-											syn::GenericArgument::Type(arg_type) => {
-												match arg_type {
-													syn::Type::Path(arg_type_path) => {
-														let arg_seg = arg_type_path.path.segments.first().unwrap();
-														match arg_seg.ident.to_string().as_str() {
-															"u8" => {
-																arg_names.push(quote::format_ident!("arg{}", pos));
-																arg_values.push(quote! {
-																	Vec::from_raw_parts(pointer, size as usize, size as usize)
-																})
-															}
-															"i8" => {
-																arg_names.push(quote::format_ident!("arg{}", pos));
-																arg_values.push(quote! {
-																	Vec::from_raw_parts(pointer as *mut i8, size as usize, size as usize)
-																})
-															}
-															"u16" => {
-																arg_names.push(quote::format_ident!("arg{}", pos));
-																arg_values.push(quote! {
-																	Vec::from_raw_parts(pointer as *mut u16, size as usize, size as usize)
-																})
-															}
-															"i16" => {
-																arg_names.push(quote::format_ident!("arg{}", pos));
-																arg_values.push(quote! {
-																	Vec::from_raw_parts(pointer as *mut i16, size as usize, size as usize)
-																})
-															}
-															"u32" => {
-																arg_names.push(quote::format_ident!("arg{}", pos));
-																arg_values.push(quote! {
-																	Vec::from_raw_parts(pointer as *mut u32, size as usize, size as usize)
-																})
-															}
-															"i32" => {
-																arg_names.push(quote::format_ident!("arg{}", pos));
-																arg_values.push(quote! {
-																	Vec::from_raw_parts(pointer as *mut i32, size as usize, size as usize)
-																})
-															}
-															"u64" => {
-																arg_names.push(quote::format_ident!("arg{}", pos));
-																arg_values.push(quote! {
-																	Vec::from_raw_parts(pointer as *mut u64, size as usize, size as usize)
-																})
-															}
-															"i64" => {
-																arg_names.push(quote::format_ident!("arg{}", pos));
-																arg_values.push(quote! {
-																	Vec::from_raw_parts(pointer as *mut i64, size as usize, size as usize)
-																})
-															}
-															_ => {}
-														}
-													}
-													_ => {}
-												}
-											}
-											_ => {}
-										}
-									}
-									_ => {}
-								}
-							}
-							"bool" => {
-								arg_names.push(quote::format_ident!("arg{}", pos));
-								arg_values.push(quote! {
-									Vec::from_raw_parts(pointer as *mut bool, size as usize, size as usize)[0]
-								})
-							}
-							"char" => {
-								arg_names.push(quote::format_ident!("arg{}", pos));
-								arg_values.push(quote! {
-									Vec::from_raw_parts(pointer as *mut char, size as usize, size as usize)[0]
-								})
-							}
-							"i8" => {
-								arg_names.push(quote::format_ident!("arg{}", pos));
-								arg_values.push(quote! {
-									Vec::from_raw_parts(pointer as *mut i8, size as usize, size as usize)[0]
-								})
-							}
-							"u8" => {
-								arg_names.push(quote::format_ident!("arg{}", pos));
-								arg_values.push(quote! {
-									Vec::from_raw_parts(pointer as *mut u8, size as usize, size as usize)[0]
-								})
-							}
-							"i16" => {
-								arg_names.push(quote::format_ident!("arg{}", pos));
-								arg_values.push(quote! {
-									Vec::from_raw_parts(pointer as *mut i16, size as usize, size as usize)[0]
-								})
-							}
-							"u16" => {
-								arg_names.push(quote::format_ident!("arg{}", pos));
-								arg_values.push(quote! {
-									Vec::from_raw_parts(pointer as *mut u16, size as usize, size as usize)[0]
-								})
-							}
-							"i32" => {
-								arg_names.push(quote::format_ident!("arg{}", pos));
-								arg_values.push(quote! {
-									Vec::from_raw_parts(pointer as *mut i32, size as usize, size as usize)[0]
-								})
-							}
-							"u32" => {
-								arg_names.push(quote::format_ident!("arg{}", pos));
-								arg_values.push(quote! {
-									Vec::from_raw_parts(pointer as *mut u32, size as usize, size as usize)[0]
-								})
-							}
-							"i64" => {
-								arg_names.push(quote::format_ident!("arg{}", pos));
-								arg_values.push(quote! {
-									Vec::from_raw_parts(pointer as *mut i64, size as usize, size as usize)[0]
-								})
-							}
-							"u64" => {
-								arg_names.push(quote::format_ident!("arg{}", pos));
-								arg_values.push(quote! {
-									Vec::from_raw_parts(pointer as *mut u64, size as usize, size as usize)[0]
-								})
-							}
-							"f32" => {
-								arg_names.push(quote::format_ident!("arg{}", pos));
-								arg_values.push(quote! {
-									Vec::from_raw_parts(pointer as *mut f32, size as usize, size as usize)[0]
-								})
-							}
-							"f64" => {
-								arg_names.push(quote::format_ident!("arg{}", pos));
-								arg_values.push(quote! {
-									Vec::from_raw_parts(pointer as *mut f64, size as usize, size as usize)[0]
-								})
-							}
-							"String" => {
-								arg_names.push(quote::format_ident!("arg{}", pos));
-								arg_values.push(quote! {
-									std::str::from_utf8(&Vec::from_raw_parts(pointer, size as usize, size as usize)).unwrap().to_string()
-								})
-							}
-							_ => {}
-						}
-					}
-					syn::Type::Reference(_) => {
-
-					}
-					syn::Type::Slice(_) => {
-
-					}
-					_ => {}
-				}
+				arg_names.push(format_ident!("arg{}", pos));
+				arg_types.push((*param_type.ty).clone());
+			}
+			syn::FnArg::Receiver(receiver) => {
+				return Err(syn::Error::new_spanned(receiver, "#[wasmedge_bindgen] can't be used on methods"));
 			}
-			_ => {}
 		}
 	}
 
-	(arg_names, arg_values)
-}
\ No newline at end of file
+	Ok((arg_names, arg_types))
+}
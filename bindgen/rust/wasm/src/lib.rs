@@ -1,17 +1,24 @@
-use std::mem;
+pub use wasmedge_bindgen_abi as abi;
 
 /// We hand over the the pointer to the allocated memory.
 /// Caller has to ensure that the memory gets freed again.
-#[no_mangle]
-pub unsafe extern fn allocate(size: i32) -> *const u8 {
-	let buffer = Vec::with_capacity(size as usize);
-
-	let buffer = mem::ManuallyDrop::new(buffer);
-	buffer.as_ptr() as *const u8
+///
+/// Outside of wasm32 the returned value is an offset in the linear memory
+/// simulated by [`abi::linear`] rather than a real pointer.
+///
+/// # Safety
+///
+/// Only meant to be called by the host through the export.
+#[cfg_attr(target_arch = "wasm32", no_mangle)]
+pub unsafe extern "C" fn allocate(size: i32) -> *const u8 {
+	abi::guest::allocate(size as u32) as usize as *const u8
 }
 
-#[no_mangle]
-pub unsafe extern fn deallocate(pointer: *mut u8, size: i32) {
-	drop(Vec::from_raw_parts(pointer, size as usize, size as usize));
+/// # Safety
+///
+/// `pointer` and `size` must come from a previous `allocate(size)`, or from a
+/// buffer handed over by a #[wasmedge_bindgen] function.
+#[cfg_attr(target_arch = "wasm32", no_mangle)]
+pub unsafe extern "C" fn deallocate(pointer: *mut u8, size: i32) {
+	abi::guest::deallocate(pointer as usize as u32, size as u32)
 }
-
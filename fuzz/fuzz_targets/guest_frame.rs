@@ -0,0 +1,84 @@
+//! Feeds arbitrary parameter frames to exports generated by #[wasmedge_bindgen],
+//! linked natively over the simulated linear memory.
+//!
+//! Input: one byte picking the export and the params count, the frame
+//! entries, then a payload the entries point into (offsets are relative to it).
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use wasmedge_bindgen_abi::{linear, ResultEntry, ReturnHeader, ENTRY_SIZE, HEADER_SIZE, PARAM_ENTRY_SIZE};
+use wasmedge_bindgen_macro::wasmedge_bindgen;
+
+#[wasmedge_bindgen]
+pub fn texts(s: String, v: Vec<u16>, c: char) -> Result<(String, u32), String> {
+	match s.is_empty() {
+		true => Err(String::from("empty")),
+		false => Ok((s + &c.to_string(), v.len() as u32)),
+	}
+}
+
+#[wasmedge_bindgen]
+pub fn numbers(b: bool, n: u64, v: Vec<i64>, f: f32) -> (Vec<i64>, bool, f64) {
+	(v, b && n > 0, f as f64)
+}
+
+fn take(data: &mut &[u8], len: usize) -> Vec<u8> {
+	let len = len.min(data.len());
+	let (head, tail) = data.split_at(len);
+	*data = tail;
+	head.to_vec()
+}
+
+fn place(bytes: &[u8]) -> u32 {
+	let pointer = linear::allocate(bytes.len() as u32);
+	assert!(linear::write(pointer, bytes));
+	pointer
+}
+
+fn free(pointer: u32, len: u32) -> Vec<u8> {
+	let bytes = linear::read(pointer, len).expect("guest returned a buffer out of the memory");
+	linear::deallocate(pointer, len);
+	bytes
+}
+
+fuzz_target!(|data: &[u8]| {
+	let mut data = data;
+	let Some((&selector, rest)) = data.split_first() else {
+		return;
+	};
+	data = rest;
+	let count = (selector as i8 >> 1) as i32;
+
+	let mut frame = take(&mut data, count.max(0) as usize * PARAM_ENTRY_SIZE);
+	let payload = place(data);
+	for entry in frame.chunks_exact_mut(4).step_by(2) {
+		let offset = u32::from_le_bytes(entry.try_into().unwrap());
+		entry.copy_from_slice(&payload.wrapping_add(offset).to_le_bytes());
+	}
+	frame.resize(count.max(0) as usize * PARAM_ENTRY_SIZE, 0);
+	let frame = place(&frame);
+
+	let header = unsafe {
+		match selector & 1 {
+			0 => texts(frame as usize as *mut u32, count),
+			_ => numbers(frame as usize as *mut u32, count),
+		}
+	};
+
+	// Whatever the frame, the export answers with a well formed header and buffers
+	let header = ReturnHeader::decode(&free(header as u32, HEADER_SIZE as u32)).expect("invalid header");
+	match header.is_error {
+		true => {
+			String::from_utf8(free(header.pointer, header.len)).expect("error message isn't UTF-8");
+		}
+		false => {
+			let bytes = free(header.pointer, header.len * ENTRY_SIZE as u32);
+			for entry in ResultEntry::decode_all(&bytes, header.len as usize).expect("invalid result entries") {
+				let value = free(entry.pointer, entry.size);
+				wasmedge_bindgen_abi::decode_any(entry.ty, &value).expect("invalid result value");
+			}
+		}
+	}
+	linear::deallocate(payload, data.len() as u32);
+});
@@ -0,0 +1,59 @@
+//! Feeds arbitrary return values and guest memory to the host decoder.
+//!
+//! Input: the header pointer the export returns, then the guest memory.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use wasmedge_bindgen_host_core::{Backend, Bindgen, Error, Param, Val};
+
+struct FuzzBackend {
+	header: i32,
+	memory: Vec<u8>,
+}
+
+impl Backend for FuzzBackend {
+	fn call(&mut self, name: &str, _args: &[Val]) -> Result<Vec<Val>, Error> {
+		match name {
+			// Parameters are written over the start of the memory
+			"allocate" => Ok(vec![Val::I32(0)]),
+			"deallocate" => Ok(vec![]),
+			_ => Ok(vec![Val::I32(self.header)]),
+		}
+	}
+
+	fn read(&self, _memory: &str, offset: u32, len: u32) -> Result<Vec<u8>, Error> {
+		let range = offset as usize..offset as usize + len as usize;
+		self.memory.get(range).map(|b| b.to_vec()).ok_or(Error::MemoryAccess { offset, len })
+	}
+
+	fn write(&mut self, _memory: &str, offset: u32, data: &[u8]) -> Result<(), Error> {
+		let range = offset as usize..offset as usize + data.len();
+		let len = data.len() as u32;
+		self.memory.get_mut(range).map(|b| b.copy_from_slice(data)).ok_or(Error::MemoryAccess { offset, len })
+	}
+
+	fn memory_size(&self, _memory: &str) -> Result<u64, Error> {
+		Ok(self.memory.len() as u64)
+	}
+
+	fn has_function(&self, _name: &str) -> bool {
+		true
+	}
+}
+
+fuzz_target!(|data: &[u8]| {
+	let Some((header, memory)) = data.split_first_chunk::<4>() else {
+		return;
+	};
+	let backend = FuzzBackend {
+		header: i32::from_le_bytes(*header),
+		memory: memory.to_vec(),
+	};
+	let mut bindgen = Bindgen::new(backend);
+	let params: Vec<Param> = match memory.len() {
+		0 => vec![],
+		_ => vec![Param::U8(memory[0])],
+	};
+	let _ = bindgen.run_wasm("fuzz", params);
+});
@@ -0,0 +1,54 @@
+//! Decodes arbitrary bytes as every wire type, checking that whatever
+//! decodes encodes back to the same bytes.
+//!
+//! Input: the type tag, then the encoded value.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use wasmedge_bindgen_abi::{decode_any, Wire, WireType};
+use wasmedge_bindgen_host_core::Param;
+
+fn round_trip<T: Wire>(bytes: &[u8]) -> bool {
+	match T::decode(bytes) {
+		Ok(v) => {
+			assert_eq!(v.to_bytes(), bytes, "{:?} doesn't round trip", T::TYPE);
+			true
+		}
+		Err(_) => false,
+	}
+}
+
+fuzz_target!(|data: &[u8]| {
+	let Some((&tag, bytes)) = data.split_first() else {
+		return;
+	};
+	let Some(ty) = WireType::from_i32(tag as i32) else {
+		return;
+	};
+	let valid = match ty {
+		WireType::U8 => round_trip::<u8>(bytes),
+		WireType::I8 => round_trip::<i8>(bytes),
+		WireType::U16 => round_trip::<u16>(bytes),
+		WireType::I16 => round_trip::<i16>(bytes),
+		WireType::U32 => round_trip::<u32>(bytes),
+		WireType::I32 => round_trip::<i32>(bytes),
+		WireType::U64 => round_trip::<u64>(bytes),
+		WireType::I64 => round_trip::<i64>(bytes),
+		WireType::F32 => round_trip::<f32>(bytes),
+		WireType::F64 => round_trip::<f64>(bytes),
+		WireType::Bool => round_trip::<bool>(bytes),
+		WireType::Char => round_trip::<char>(bytes),
+		WireType::U8Array => round_trip::<Vec<u8>>(bytes),
+		WireType::I8Array => round_trip::<Vec<i8>>(bytes),
+		WireType::U16Array => round_trip::<Vec<u16>>(bytes),
+		WireType::I16Array => round_trip::<Vec<i16>>(bytes),
+		WireType::U32Array => round_trip::<Vec<u32>>(bytes),
+		WireType::I32Array => round_trip::<Vec<i32>>(bytes),
+		WireType::U64Array => round_trip::<Vec<u64>>(bytes),
+		WireType::I64Array => round_trip::<Vec<i64>>(bytes),
+		WireType::String => round_trip::<String>(bytes),
+	};
+	assert_eq!(decode_any(ty, bytes).is_ok(), valid, "decode_any disagrees on {:?}", ty);
+	assert_eq!(Param::decode(ty, bytes).is_ok(), valid, "Param::decode disagrees on {:?}", ty);
+});
@@ -10,6 +10,7 @@ import (
 	"github.com/second-state/WasmEdge-go/wasmedge"
 )
 
+// Type tags of the result entries, see bindgen/rust/abi for the wire format.
 const (
 	U8 int32 = 1
 	I8 = 2
@@ -0,0 +1,246 @@
+use std::collections::VecDeque;
+use std::future::Future;
+use std::ops::{Deref, DerefMut};
+use std::panic::{self, AssertUnwindSafe};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::task::{Context, Poll, Waker};
+
+use crate::{pool, Backend, Bindgen, Error, Param, WasmResult};
+
+/// One or more [`Bindgen`]s shared between tasks, whose calls run on a pool
+/// of threads dedicated to blocking work instead of the executor's threads.
+///
+/// Calls are queued by the `AsyncBindgen` (and its clones) and handed to the
+/// pool only once one of its instances is idle, so a slow instance never
+/// holds up the threads other `AsyncBindgen`s need. Each instance runs one
+/// call at a time; [`pooled`](Self::pooled) instances run calls in parallel.
+pub struct AsyncBindgen<B> {
+	instances: Arc<Instances<B>>,
+}
+
+impl<B> Clone for AsyncBindgen<B> {
+	fn clone(&self) -> Self {
+		AsyncBindgen {
+			instances: self.instances.clone(),
+		}
+	}
+}
+
+impl<B: Backend + Send + 'static> AsyncBindgen<B> {
+	pub fn new(bindgen: Bindgen<B>) -> Self {
+		Self::pooled([bindgen])
+	}
+
+	/// Spreads the calls over `bindgens`, e.g. instances of the same module,
+	/// each call running on whichever is idle first.
+	///
+	/// # Panics
+	///
+	/// Panics when `bindgens` is empty.
+	pub fn pooled(bindgens: impl IntoIterator<Item = Bindgen<B>>) -> Self {
+		let bindgens: Vec<_> = bindgens.into_iter().map(Mutex::new).collect();
+		assert!(!bindgens.is_empty(), "an AsyncBindgen needs at least one instance");
+		AsyncBindgen {
+			instances: Arc::new(Instances {
+				queue: Mutex::new(Queue {
+					idle: (0..bindgens.len()).rev().collect(),
+					calls: VecDeque::new(),
+				}),
+				released: Condvar::new(),
+				bindgens,
+			}),
+		}
+	}
+
+	/// Number of instances calls are spread over.
+	pub fn instance_count(&self) -> usize {
+		self.instances.bindgens.len()
+	}
+
+	/// Takes an idle instance for synchronous use, blocking until one is idle
+	/// with no call queued. The instance is given back when the guard drops.
+	pub fn lock(&self) -> Locked<'_, B> {
+		let mut queue = self.instances.queue();
+		let index = loop {
+			match queue.idle.pop() {
+				Some(index) => break index,
+				None => queue = self.instances.released.wait(queue).unwrap_or_else(|e| e.into_inner()),
+			}
+		};
+		drop(queue);
+		Locked {
+			bindgen: Some(self.instances.bindgens[index].lock().unwrap_or_else(|e| e.into_inner())),
+			instances: &self.instances,
+			index,
+		}
+	}
+
+	/// Like [`Bindgen::run_wasm`], resolving once the guest returned.
+	///
+	/// Dropping the future before the call started cancels it. A call already
+	/// running in the guest can't be interrupted: it completes in the
+	/// background and its results are freed and discarded.
+	pub fn run_wasm_async(&self, func_name: impl Into<String>, inputs: Vec<Param<'static>>) -> RunWasm {
+		let shared = Arc::new(Shared {
+			state: Mutex::new(State { result: None, waker: None }),
+			cancelled: AtomicBool::new(false),
+		});
+
+		let func_name = func_name.into();
+		let worker = shared.clone();
+		self.instances.dispatch(Box::new(move |bindgen: &mut Bindgen<B>| {
+			if worker.cancelled.load(Ordering::Acquire) {
+				return;
+			}
+			let result = panic::catch_unwind(AssertUnwindSafe(|| bindgen.run_wasm(&func_name, inputs)))
+				.unwrap_or_else(|_| Err(Error::Runtime(format!("{} panicked", func_name).into())));
+			worker.complete(result);
+		}));
+
+		RunWasm { shared }
+	}
+}
+
+impl<B: Backend + Send + 'static> Bindgen<B> {
+	pub fn into_async(self) -> AsyncBindgen<B> {
+		AsyncBindgen::new(self)
+	}
+}
+
+type Call<B> = Box<dyn FnOnce(&mut Bindgen<B>) + Send>;
+
+struct Instances<B> {
+	bindgens: Vec<Mutex<Bindgen<B>>>,
+	queue: Mutex<Queue<B>>,
+	// Signalled when an instance is idle again
+	released: Condvar,
+}
+
+struct Queue<B> {
+	idle: Vec<usize>,
+	calls: VecDeque<Call<B>>,
+}
+
+impl<B: Backend + Send + 'static> Instances<B> {
+	fn queue(&self) -> MutexGuard<'_, Queue<B>> {
+		self.queue.lock().unwrap_or_else(|e| e.into_inner())
+	}
+
+	/// Runs `call` on an idle instance, or queues it until one is released.
+	fn dispatch(self: &Arc<Self>, call: Call<B>) {
+		let mut queue = self.queue();
+		match queue.idle.pop() {
+			Some(index) => {
+				drop(queue);
+				self.spawn(index, call);
+			}
+			None => queue.calls.push_back(call),
+		}
+	}
+
+	fn spawn(self: &Arc<Self>, index: usize, call: Call<B>) {
+		let instances = self.clone();
+		pool::spawn(move || {
+			call(&mut instances.bindgens[index].lock().unwrap_or_else(|e| e.into_inner()));
+			instances.release(index);
+		});
+	}
+
+	/// Hands instance `index` to the next queued call, going back to the end
+	/// of the pool's queue so that other instances get their turn, or marks
+	/// it idle.
+	fn release(self: &Arc<Self>, index: usize) {
+		let mut queue = self.queue();
+		match queue.calls.pop_front() {
+			Some(call) => {
+				drop(queue);
+				self.spawn(index, call);
+			}
+			None => {
+				queue.idle.push(index);
+				self.released.notify_one();
+			}
+		}
+	}
+}
+
+/// An instance of an [`AsyncBindgen`] taken by [`lock`](AsyncBindgen::lock).
+pub struct Locked<'a, B: Backend + Send + 'static> {
+	bindgen: Option<MutexGuard<'a, Bindgen<B>>>,
+	instances: &'a Arc<Instances<B>>,
+	index: usize,
+}
+
+impl<B: Backend + Send + 'static> Deref for Locked<'_, B> {
+	type Target = Bindgen<B>;
+
+	fn deref(&self) -> &Bindgen<B> {
+		self.bindgen.as_ref().unwrap()
+	}
+}
+
+impl<B: Backend + Send + 'static> DerefMut for Locked<'_, B> {
+	fn deref_mut(&mut self) -> &mut Bindgen<B> {
+		self.bindgen.as_mut().unwrap()
+	}
+}
+
+impl<B: Backend + Send + 'static> Drop for Locked<'_, B> {
+	fn drop(&mut self) {
+		// Unlocked first, so the next call doesn't wait for it
+		self.bindgen = None;
+		self.instances.release(self.index);
+	}
+}
+
+struct State {
+	result: Option<Result<WasmResult, Error>>,
+	waker: Option<Waker>,
+}
+
+struct Shared {
+	state: Mutex<State>,
+	cancelled: AtomicBool,
+}
+
+impl Shared {
+	fn lock(&self) -> MutexGuard<'_, State> {
+		self.state.lock().unwrap_or_else(|e| e.into_inner())
+	}
+
+	fn complete(&self, result: Result<WasmResult, Error>) {
+		let mut state = self.lock();
+		state.result = Some(result);
+		if let Some(waker) = state.waker.take() {
+			waker.wake();
+		}
+	}
+}
+
+/// Future returned by [`AsyncBindgen::run_wasm_async`].
+pub struct RunWasm {
+	shared: Arc<Shared>,
+}
+
+impl Future for RunWasm {
+	type Output = Result<WasmResult, Error>;
+
+	fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+		let mut state = self.shared.lock();
+		match state.result.take() {
+			Some(result) => Poll::Ready(result),
+			None => {
+				state.waker = Some(cx.waker().clone());
+				Poll::Pending
+			}
+		}
+	}
+}
+
+impl Drop for RunWasm {
+	fn drop(&mut self) {
+		self.shared.cancelled.store(true, Ordering::Release);
+	}
+}
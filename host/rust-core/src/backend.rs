@@ -0,0 +1,131 @@
+use std::borrow::Cow;
+
+use wasmedge_bindgen_abi::manifest::Manifest;
+
+use crate::{Error, Output};
+
+/// A wasm value passed to or returned from an export.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Val {
+	I32(i32),
+	I64(i64),
+	F32(f32),
+	F64(f64),
+}
+
+impl Val {
+	pub fn to_i32(&self) -> Option<i32> {
+		match self {
+			Val::I32(v) => Some(*v),
+			_ => None,
+		}
+	}
+}
+
+/// Type of a wasm value, in the signatures of exports.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ValType {
+	I32,
+	I64,
+	F32,
+	F64,
+	V128,
+	FuncRef,
+	ExternRef,
+}
+
+/// An export looked up once by [`Backend::resolve`], to call it without
+/// looking it up by name again. What the number means is up to the backend.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct FuncHandle(pub u32);
+
+/// What a wasm runtime has to provide to run bindgen functions.
+///
+/// Everything else, from allocating parameters to decoding results, is done
+/// by [`Bindgen`](crate::Bindgen) on top of these few operations.
+pub trait Backend {
+	fn call(&mut self, name: &str, args: &[Val]) -> Result<Vec<Val>, Error>;
+
+	/// Looks up the function export `name` for [`call_handle`](Self::call_handle),
+	/// or returns `None` when calls by handle aren't supported and go by name.
+	/// Handles stay valid across [`reinstantiate`](Self::reinstantiate) and
+	/// [`reload`](Self::reload), calling the export of the same name.
+	fn resolve(&mut self, _name: &str) -> Result<Option<FuncHandle>, Error> {
+		Ok(None)
+	}
+
+	fn call_handle(&mut self, _handle: FuncHandle, _args: &[Val]) -> Result<Vec<Val>, Error> {
+		Err(Error::Unsupported(String::from("calls by handle")))
+	}
+
+	fn read(&self, memory: &str, offset: u32, len: u32) -> Result<Vec<u8>, Error>;
+
+	fn write(&mut self, memory: &str, offset: u32, data: &[u8]) -> Result<(), Error>;
+
+	/// Like `read`, borrowing the bytes when the memory can be lent out.
+	fn view(&self, memory: &str, offset: u32, len: u32) -> Result<Cow<'_, [u8]>, Error> {
+		self.read(memory, offset, len).map(Cow::Owned)
+	}
+
+	/// Lends `len` bytes of the memory for writing, or `None` when the memory
+	/// can't be lent out and has to be written with `write`.
+	fn view_mut(&mut self, _memory: &str, _offset: u32, _len: u32) -> Result<Option<&mut [u8]>, Error> {
+		Ok(None)
+	}
+
+	/// Size of the memory in bytes.
+	fn memory_size(&self, memory: &str) -> Result<u64, Error>;
+
+	/// Grows the memory by `pages` of 64 KiB.
+	fn grow(&mut self, memory: &str, _pages: u32) -> Result<(), Error> {
+		Err(Error::Unsupported(format!("growing memory {}", memory)))
+	}
+
+	fn has_function(&self, name: &str) -> bool;
+
+	/// Parameter and result types of the function export `name`, or `None`
+	/// when it is missing or the backend can't tell.
+	fn signature(&self, _name: &str) -> Option<(Vec<ValType>, Vec<ValType>)> {
+		None
+	}
+
+	/// Names and values of the exported mutable globals, saved by snapshots.
+	fn globals(&self) -> Result<Vec<(String, Val)>, Error> {
+		Ok(Vec::new())
+	}
+
+	fn set_global(&mut self, name: &str, _value: Val) -> Result<(), Error> {
+		Err(Error::Unsupported(format!("setting global {}", name)))
+	}
+
+	/// Whether [`reinstantiate`](Self::reinstantiate) is implemented.
+	fn can_reinstantiate(&self) -> bool {
+		false
+	}
+
+	/// Replaces the instance with a fresh one of the same module, after a trap
+	/// may have left its memory or allocator corrupt.
+	fn reinstantiate(&mut self) -> Result<(), Error> {
+		Err(Error::Unsupported(String::from("re-instantiation")))
+	}
+
+	/// Takes what the guest wrote to stdout and stderr since the last time,
+	/// when the backend captures it.
+	fn take_output(&mut self) -> Output {
+		Output::default()
+	}
+
+	/// Manifest of the module, when the backend was given its bytes.
+	fn manifest(&self) -> Option<Manifest> {
+		None
+	}
+
+	/// Replaces the module with `wasm`, keeping the host functions it was
+	/// linked with. The new instance is only swapped in once `validate` accepts it.
+	fn reload(&mut self, _wasm: &[u8], _validate: &dyn Fn(&Self) -> Result<(), Error>) -> Result<(), Error>
+	where
+		Self: Sized,
+	{
+		Err(Error::Unsupported(String::from("reloading the module")))
+	}
+}
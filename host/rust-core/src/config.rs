@@ -0,0 +1,141 @@
+use crate::{Backend, Error, ValType};
+
+/// Signature of the guest's allocation export.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AllocSignature {
+	/// `allocate(size: i32) -> i32`
+	Size,
+	/// `allocate(size: i32, align: i32) -> i32`
+	SizeAlign,
+}
+
+impl AllocSignature {
+	fn params(self) -> &'static [ValType] {
+		match self {
+			AllocSignature::Size => &[ValType::I32],
+			AllocSignature::SizeAlign => &[ValType::I32, ValType::I32],
+		}
+	}
+}
+
+/// Signature of the guest's deallocation export.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DeallocSignature {
+	/// `free(pointer: i32)`
+	Ptr,
+	/// `deallocate(pointer: i32, size: i32)`
+	PtrSize,
+	/// `deallocate(pointer: i32, size: i32, align: i32)`
+	PtrSizeAlign,
+}
+
+impl DeallocSignature {
+	fn params(self) -> &'static [ValType] {
+		match self {
+			DeallocSignature::Ptr => &[ValType::I32],
+			DeallocSignature::PtrSize => &[ValType::I32, ValType::I32],
+			DeallocSignature::PtrSizeAlign => &[ValType::I32, ValType::I32, ValType::I32],
+		}
+	}
+}
+
+/// Names and signatures of the exports the host relies on.
+///
+/// The default matches guests built with the `wasmedge-bindgen` crate.
+#[derive(Clone, Debug)]
+pub struct BindgenConfig {
+	pub(crate) allocate: String,
+	pub(crate) deallocate: String,
+	pub(crate) alloc_signature: AllocSignature,
+	pub(crate) dealloc_signature: DeallocSignature,
+	pub(crate) align: i32,
+	pub(crate) memory: String,
+	pub(crate) isolated: bool,
+}
+
+impl Default for BindgenConfig {
+	fn default() -> Self {
+		BindgenConfig {
+			allocate: String::from("allocate"),
+			deallocate: String::from("deallocate"),
+			alloc_signature: AllocSignature::Size,
+			dealloc_signature: DeallocSignature::PtrSize,
+			align: 1,
+			memory: String::from("memory"),
+			isolated: false,
+		}
+	}
+}
+
+impl BindgenConfig {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Preset for guests exporting libc-style `malloc(size)` / `free(pointer)`.
+	pub fn malloc() -> Self {
+		Self::new()
+			.allocator("malloc", AllocSignature::Size)
+			.deallocator("free", DeallocSignature::Ptr)
+	}
+
+	pub fn allocator(mut self, name: impl Into<String>, signature: AllocSignature) -> Self {
+		self.allocate = name.into();
+		self.alloc_signature = signature;
+		self
+	}
+
+	pub fn deallocator(mut self, name: impl Into<String>, signature: DeallocSignature) -> Self {
+		self.deallocate = name.into();
+		self.dealloc_signature = signature;
+		self
+	}
+
+	/// Alignment passed to allocators that take one. Must be a power of two.
+	pub fn align(mut self, align: i32) -> Self {
+		self.align = align;
+		self
+	}
+
+	pub fn memory(mut self, name: impl Into<String>) -> Self {
+		self.memory = name.into();
+		self
+	}
+
+	/// Resets the instance to its [snapshot](crate::Bindgen::snapshot) after
+	/// every call, taking one before the first call if there is none.
+	pub fn isolated(mut self, isolated: bool) -> Self {
+		self.isolated = isolated;
+		self
+	}
+
+	pub fn is_isolated(&self) -> bool {
+		self.isolated
+	}
+
+	pub fn memory_name(&self) -> &str {
+		&self.memory
+	}
+
+	pub(crate) fn validate(&self, backend: &impl Backend) -> Result<(), Error> {
+		if self.align <= 0 || self.align & (self.align - 1) != 0 {
+			return Err(Error::InvalidConfig(format!("Invalid allocator alignment {}, expect a power of two", self.align)));
+		}
+		let expected: [(&String, &[ValType], &[ValType]); 2] = [
+			(&self.allocate, self.alloc_signature.params(), &[ValType::I32]),
+			(&self.deallocate, self.dealloc_signature.params(), &[]),
+		];
+		for (name, params, results) in expected {
+			if !backend.has_function(name) {
+				return Err(Error::MissingFunction(name.clone()));
+			}
+			if let Some((actual_params, actual_results)) = backend.signature(name) {
+				if actual_params != params || actual_results != results {
+					return Err(Error::InvalidConfig(format!("{} has signature {:?} -> {:?}, expect {:?} -> {:?}", name, actual_params, actual_results, params, results)));
+				}
+			}
+		}
+		backend.memory_size(&self.memory)?;
+		Ok(())
+	}
+}
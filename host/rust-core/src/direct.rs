@@ -0,0 +1,43 @@
+use wasmedge_bindgen_abi::{Wire, WireType};
+
+use crate::{Param, Signature, Val};
+
+/// Whether `inputs` are the numbers a direct export of `signature` takes.
+pub(crate) fn accepts(signature: &Signature, inputs: &[Param]) -> bool {
+	inputs.len() == signature.params.len() && inputs.iter().zip(&signature.params).all(|(input, ty)| input.wire_type() == *ty && to_val(input).is_some())
+}
+
+pub(crate) fn to_val(param: &Param) -> Option<Val> {
+	let val = match *param {
+		Param::I8(v) => Val::I32(v as i32),
+		Param::U8(v) => Val::I32(v as i32),
+		Param::I16(v) => Val::I32(v as i32),
+		Param::U16(v) => Val::I32(v as i32),
+		Param::I32(v) => Val::I32(v),
+		Param::U32(v) => Val::I32(v as i32),
+		Param::I64(v) => Val::I64(v),
+		Param::U64(v) => Val::I64(v as i64),
+		Param::F32(v) => Val::F32(v),
+		Param::F64(v) => Val::F64(v),
+		_ => return None,
+	};
+	Some(val)
+}
+
+/// Wire encoding of a value of type `ty` returned by a direct export.
+pub(crate) fn from_val(ty: WireType, val: Val) -> Option<Vec<u8>> {
+	let bytes = match (ty, val) {
+		(WireType::I8, Val::I32(v)) => (v as i8).to_bytes(),
+		(WireType::U8, Val::I32(v)) => (v as u8).to_bytes(),
+		(WireType::I16, Val::I32(v)) => (v as i16).to_bytes(),
+		(WireType::U16, Val::I32(v)) => (v as u16).to_bytes(),
+		(WireType::I32, Val::I32(v)) => v.to_bytes(),
+		(WireType::U32, Val::I32(v)) => (v as u32).to_bytes(),
+		(WireType::I64, Val::I64(v)) => v.to_bytes(),
+		(WireType::U64, Val::I64(v)) => (v as u64).to_bytes(),
+		(WireType::F32, Val::F32(v)) => v.to_bytes(),
+		(WireType::F64, Val::F64(v)) => v.to_bytes(),
+		_ => return None,
+	};
+	Some(bytes)
+}
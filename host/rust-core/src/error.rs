@@ -0,0 +1,78 @@
+use std::fmt;
+
+#[derive(Debug)]
+pub enum Error {
+	/// The runtime failed to run a function, e.g. it isn't exported with that
+	/// signature, without running the guest.
+	Runtime(Box<dyn std::error::Error + Send + Sync>),
+	/// The guest was stopped midway by a trap, possibly leaving its memory or
+	/// allocator corrupt.
+	Trap(Box<dyn std::error::Error + Send + Sync>),
+	MissingFunction(String),
+	MissingMemory(String),
+	/// No instance of a [`Router`](crate::Router) has this name.
+	MissingModule(String),
+	/// A read or write went past the end of the guest memory.
+	MemoryAccess { offset: u32, len: u32 },
+	InvalidConfig(String),
+	/// An export returned values of unexpected types.
+	InvalidReturn(String),
+	/// `reset` was called before any `snapshot`.
+	NoSnapshot,
+	/// The backend can't do what was asked, e.g. snapshot a shared memory.
+	Unsupported(String),
+	/// JSON that doesn't describe a [`Value`](crate::Value).
+	InvalidValue(String),
+	/// A reloaded module doesn't export the functions of the running one.
+	Incompatible(String),
+	/// A [`GuestValue`](crate::GuestValue) or [`GuestParam`](crate::GuestParam) of another instance, or freed by a
+	/// reset, recycle or reload since it was kept.
+	StaleValue,
+	Io(std::io::Error),
+}
+
+impl Error {
+	pub fn runtime(e: impl std::error::Error + Send + Sync + 'static) -> Self {
+		Error::Runtime(Box::new(e))
+	}
+
+	pub fn trap(e: impl std::error::Error + Send + Sync + 'static) -> Self {
+		Error::Trap(Box::new(e))
+	}
+
+	/// Whether the guest trapped, and the instance is poisoned.
+	pub fn is_trap(&self) -> bool {
+		matches!(self, Error::Trap(_))
+	}
+}
+
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Error::Runtime(e) => write!(f, "Runtime error: {}", e),
+			Error::Trap(e) => write!(f, "Guest trapped: {}", e),
+			Error::MissingFunction(name) => write!(f, "Function {} is not exported", name),
+			Error::MissingMemory(name) => write!(f, "Memory {} is not exported", name),
+			Error::MissingModule(name) => write!(f, "Module {} is not registered", name),
+			Error::MemoryAccess { offset, len } => write!(f, "Out of bounds memory access at {} for {} bytes", offset, len),
+			Error::InvalidConfig(msg) => write!(f, "Invalid config: {}", msg),
+			Error::InvalidReturn(msg) => write!(f, "Invalid return value: {}", msg),
+			Error::NoSnapshot => write!(f, "No snapshot to reset to"),
+			Error::Unsupported(msg) => write!(f, "Unsupported: {}", msg),
+			Error::InvalidValue(json) => write!(f, "Invalid value: {}", json),
+			Error::Incompatible(msg) => write!(f, "Incompatible module: {}", msg),
+			Error::StaleValue => write!(f, "Guest value is not in this instance"),
+			Error::Io(e) => write!(f, "I/O error: {}", e),
+		}
+	}
+}
+
+impl std::error::Error for Error {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		match self {
+			Error::Runtime(e) | Error::Trap(e) => Some(&**e),
+			Error::Io(e) => Some(e),
+			_ => None,
+		}
+	}
+}
@@ -0,0 +1,113 @@
+use std::any::Any;
+use std::borrow::Cow;
+use std::ops::Deref;
+
+use wasmedge_bindgen_abi as abi;
+use wasmedge_bindgen_abi::{DecodeError, ResultEntry, WireType};
+
+use crate::{Backend, Bindgen, Error, GuestParam, Param};
+
+/// Results of [`Bindgen::run_wasm_borrowed`], still in the guest memory.
+///
+/// They are freed by [`release`](Self::release), or when dropped. The
+/// `Bindgen` stays borrowed meanwhile, so no call can move or overwrite them.
+pub struct GuestResults<'a, B: Backend> {
+	bindgen: &'a mut Bindgen<B>,
+	entries: Vec<ResultEntry>,
+	released: bool,
+}
+
+impl<'a, B: Backend> GuestResults<'a, B> {
+	pub(crate) fn new(bindgen: &'a mut Bindgen<B>, entries: Vec<ResultEntry>) -> Self {
+		GuestResults { bindgen, entries, released: false }
+	}
+
+	pub fn len(&self) -> usize {
+		self.entries.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.entries.is_empty()
+	}
+
+	/// Borrows the encoded bytes of the `index`th result. Backends that can't
+	/// lend their memory hand out a copy.
+	///
+	/// # Panics
+	///
+	/// When `index` is out of bounds.
+	pub fn get(&self, index: usize) -> Result<GuestBytes<'_>, Error> {
+		let entry = &self.entries[index];
+		let bytes = self.bindgen.backend.view(&self.bindgen.config.memory, entry.pointer, entry.size)?;
+		Ok(GuestBytes { ty: entry.ty, bytes })
+	}
+
+	/// Frees the results in the guest, and resets an isolated instance.
+	pub fn release(mut self) -> Result<(), Error> {
+		self.free()
+	}
+
+	/// Hands the results over as parameters of the next call to the same
+	/// instance, which frees them. An isolated instance is reset after that call.
+	pub(crate) fn into_params(mut self) -> Vec<Param<'static>> {
+		self.released = true;
+		let owner = self.bindgen.kept.owner();
+		self.entries.iter()
+			.map(|e| Param::Guest(GuestParam { ty: e.ty, pointer: e.pointer, length: e.size / e.ty.elem_size() as u32, size: e.size, owner: owner.clone() }))
+			.collect()
+	}
+
+	fn free(&mut self) -> Result<(), Error> {
+		if std::mem::replace(&mut self.released, true) {
+			return Ok(());
+		}
+		let mut result = Ok(());
+		for entry in self.entries.iter() {
+			result = result.and(self.bindgen.deallocate(entry.pointer, entry.size));
+		}
+		if self.bindgen.config.isolated {
+			result = result.and(self.bindgen.reset());
+		}
+		result
+	}
+}
+
+impl<B: Backend> Drop for GuestResults<'_, B> {
+	fn drop(&mut self) {
+		let _ = self.free();
+	}
+}
+
+/// One result of a [`GuestResults`], in its wire encoding.
+pub struct GuestBytes<'a> {
+	ty: WireType,
+	bytes: Cow<'a, [u8]>,
+}
+
+impl<'a> GuestBytes<'a> {
+	pub(crate) fn new(ty: WireType, bytes: Cow<'a, [u8]>) -> Self {
+		GuestBytes { ty, bytes }
+	}
+
+	pub fn ty(&self) -> WireType {
+		self.ty
+	}
+
+	/// Whether the bytes are borrowed from the guest memory rather than copied.
+	pub fn is_borrowed(&self) -> bool {
+		matches!(self.bytes, Cow::Borrowed(_))
+	}
+
+	/// Decodes the value like [`Bindgen::run_wasm`] does.
+	pub fn decode(&self) -> Result<Box<dyn Any + Send + Sync>, DecodeError> {
+		abi::decode_any(self.ty, &self.bytes)
+	}
+}
+
+impl Deref for GuestBytes<'_> {
+	type Target = [u8];
+
+	fn deref(&self) -> &[u8] {
+		&self.bytes
+	}
+}
@@ -0,0 +1,116 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use wasmedge_bindgen_abi::{ResultEntry, WireType};
+
+use crate::{GuestParam, Param};
+
+/// A result of [`Bindgen::run_wasm_kept`](crate::Bindgen::run_wasm_kept) left
+/// in the guest memory, to pass as a parameter of a later call without
+/// copying it out and back in.
+///
+/// Passing it to a call hands it over to the guest, which frees it. Dropped
+/// instead, it is freed at the start of the next call of the `Bindgen` that
+/// returned it. It only belongs to that instance, and not after a reset,
+/// recycle or reload, when using it fails with [`Error::StaleValue`](crate::Error::StaleValue).
+#[derive(Debug)]
+pub struct GuestValue {
+	ty: WireType,
+	pointer: u32,
+	size: u32,
+	owner: Owner,
+	handed_over: bool,
+}
+
+impl GuestValue {
+	pub(crate) fn new(entry: &ResultEntry, owner: Owner) -> Self {
+		GuestValue { ty: entry.ty, pointer: entry.pointer, size: entry.size, owner, handed_over: false }
+	}
+
+	pub fn ty(&self) -> WireType {
+		self.ty
+	}
+
+	/// Number of elements.
+	pub fn len(&self) -> u32 {
+		self.size / self.ty.elem_size() as u32
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.size == 0
+	}
+
+	/// Size of the buffer in bytes.
+	pub fn size(&self) -> u32 {
+		self.size
+	}
+
+	pub(crate) fn pointer(&self) -> u32 {
+		self.pointer
+	}
+
+	pub(crate) fn owner(&self) -> &Owner {
+		&self.owner
+	}
+
+	/// Gives up the buffer, whoever frees it now.
+	pub(crate) fn hand_over(mut self) -> (u32, u32) {
+		self.handed_over = true;
+		(self.pointer, self.size)
+	}
+}
+
+impl Drop for GuestValue {
+	fn drop(&mut self) {
+		if !self.handed_over {
+			self.owner.kept.dropped.lock().unwrap_or_else(|e| e.into_inner()).push((self.pointer, self.size, self.owner.generation));
+		}
+	}
+}
+
+impl<'a> From<GuestValue> for Param<'a> {
+	fn from(v: GuestValue) -> Self {
+		let (ty, owner) = (v.ty, v.owner.clone());
+		let (pointer, size) = v.hand_over();
+		Param::Guest(GuestParam { ty, pointer, length: size / ty.elem_size() as u32, size, owner })
+	}
+}
+
+/// Tracks the values a `Bindgen` kept in the guest: the generation of its
+/// instance, bumped whenever their memory goes away, and those dropped since
+/// its last call, to free.
+#[derive(Debug, Default)]
+pub(crate) struct KeptValues {
+	generation: AtomicU64,
+	dropped: Mutex<Vec<(u32, u32, u64)>>,
+}
+
+impl KeptValues {
+	pub(crate) fn owner(self: &Arc<Self>) -> Owner {
+		Owner { kept: self.clone(), generation: self.generation.load(Ordering::Relaxed) }
+	}
+
+	/// Makes the values kept so far stale.
+	pub(crate) fn invalidate(&self) {
+		self.generation.fetch_add(1, Ordering::Relaxed);
+	}
+
+	/// Buffers of the values dropped since the last call and still in the instance.
+	pub(crate) fn take_dropped(&self) -> Vec<(u32, u32)> {
+		let generation = self.generation.load(Ordering::Relaxed);
+		let dropped = std::mem::take(&mut *self.dropped.lock().unwrap_or_else(|e| e.into_inner()));
+		dropped.into_iter().filter(|(_, _, g)| *g == generation).map(|(pointer, size, _)| (pointer, size)).collect()
+	}
+
+	/// Whether `owner` is this tracker, at the current generation.
+	pub(crate) fn owns(self: &Arc<Self>, owner: &Owner) -> bool {
+		Arc::ptr_eq(self, &owner.kept) && owner.generation == self.generation.load(Ordering::Relaxed)
+	}
+}
+
+/// The `Bindgen` and instance generation a value was kept by.
+#[derive(Clone, Debug)]
+pub(crate) struct Owner {
+	kept: Arc<KeptValues>,
+	generation: u64,
+}
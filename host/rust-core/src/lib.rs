@@ -0,0 +1,980 @@
+use std::any::Any;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use wasmedge_bindgen_abi as abi;
+use metrics::span;
+use prepared::{Allocator, Export, Exports};
+use record::Recorder;
+use guest_value::KeptValues;
+use snapshot::Snapshot;
+use wasmedge_bindgen_abi::{ParamEntry, ResultEntry, ReturnHeader, ENTRY_SIZE, HEADER_SIZE, PARAM_ENTRY_SIZE};
+
+mod asynchronous;
+mod backend;
+mod config;
+mod direct;
+mod error;
+mod guest_bytes;
+mod guest_value;
+mod metrics;
+mod output;
+mod param;
+mod pool;
+mod prepared;
+pub mod record;
+mod router;
+mod snapshot;
+mod value;
+
+pub use asynchronous::{AsyncBindgen, Locked, RunWasm};
+pub use backend::{Backend, FuncHandle, Val, ValType};
+pub use config::{AllocSignature, BindgenConfig, DeallocSignature};
+pub use error::Error;
+pub use guest_bytes::{GuestBytes, GuestResults};
+pub use guest_value::GuestValue;
+pub use metrics::{CallStats, Metrics};
+pub use output::Output;
+pub use param::{GuestParam, Param};
+pub use prepared::PreparedCall;
+pub use record::{Encoded, Outcome, RecordedCall, Replayed};
+pub use router::Router;
+pub use value::Value;
+pub use wasmedge_bindgen_abi::manifest::{Manifest, Signature};
+pub use wasmedge_bindgen_abi::WireType;
+
+/// Values returned by a bindgen function, or the message of its `Err(String)`.
+pub type WasmResult = Result<Vec<Box<dyn Any + Send + Sync>>, String>;
+
+/// Calls functions exported with #[wasmedge_bindgen] through a [`Backend`].
+///
+/// Calls use the packed, direct and batch exports the manifest flags, except
+/// [`run_wasm_borrowed`](Self::run_wasm_borrowed) and [`Param::Guest`]
+/// parameters, which go through the classic frame of pointers.
+///
+/// A trap poisons the instance, which is replaced by a fresh instance of the
+/// module before the next call when the backend can [reinstantiate](Backend::reinstantiate).
+/// Errors of the caller, such as a wrong number of parameters, leave it alone.
+#[derive(Clone)]
+pub struct Bindgen<B> {
+	backend: B,
+	config: BindgenConfig,
+	metrics: Option<Arc<dyn Metrics>>,
+	stats: CallStats,
+	recorder: Option<Arc<Mutex<Recorder>>>,
+	snapshot: Option<Arc<Snapshot>>,
+	poisoned: bool,
+	// Whether no guest code ran since the instance was instantiated
+	fresh: bool,
+	// Whether `_initialize` is called on every new instance
+	initialize: bool,
+	traps: u64,
+	recycles: u64,
+	trap_handler: Option<Arc<TrapHandler>>,
+	manifest: Option<Manifest>,
+	output: Output,
+	output_handler: Option<Arc<OutputHandler>>,
+	allocator: Option<Allocator>,
+	kept: Arc<KeptValues>,
+}
+
+/// Called with the function name and error of every trap.
+type TrapHandler = dyn Fn(&str, &Error) + Send + Sync;
+
+/// Called with the function name and captured output of every call that printed.
+type OutputHandler = dyn Fn(&str, &Output) + Send + Sync;
+
+impl<B: Backend> Bindgen<B> {
+	pub fn new(backend: B) -> Self {
+		Bindgen {
+			manifest: backend.manifest(),
+			backend,
+			config: BindgenConfig::default(),
+			metrics: None,
+			stats: CallStats::default(),
+			recorder: None,
+			snapshot: None,
+			poisoned: false,
+			fresh: true,
+			initialize: false,
+			traps: 0,
+			recycles: 0,
+			trap_handler: None,
+			output: Output::default(),
+			output_handler: None,
+			allocator: None,
+			kept: Arc::default(),
+		}
+	}
+
+	/// Creates a `Bindgen` for an instantiated module, checking that the
+	/// allocator and memory exports named in `config` are present with the
+	/// signatures it names, or returning [`Error::InvalidConfig`].
+	pub fn with_config(backend: B, config: BindgenConfig) -> Result<Self, Error> {
+		config.validate(&backend)?;
+		Ok(Bindgen {
+			manifest: backend.manifest(),
+			backend,
+			config,
+			metrics: None,
+			stats: CallStats::default(),
+			recorder: None,
+			snapshot: None,
+			poisoned: false,
+			fresh: true,
+			initialize: false,
+			traps: 0,
+			recycles: 0,
+			trap_handler: None,
+			output: Output::default(),
+			output_handler: None,
+			allocator: None,
+			kept: Arc::default(),
+		})
+	}
+
+	pub fn config(&self) -> &BindgenConfig {
+		&self.config
+	}
+
+	pub fn backend(&self) -> &B {
+		&self.backend
+	}
+
+	pub fn backend_mut(&mut self) -> &mut B {
+		self.fresh = false;
+		&mut self.backend
+	}
+
+	/// Reports the [`CallStats`] of every following call to `metrics`.
+	pub fn set_metrics(&mut self, metrics: Arc<dyn Metrics>) {
+		self.metrics = Some(metrics);
+	}
+
+	/// Statistics of the last call.
+	pub fn last_call_stats(&self) -> &CallStats {
+		&self.stats
+	}
+
+	/// What the guest wrote to stdout and stderr during the last call, with
+	/// backends capturing it.
+	pub fn last_call_output(&self) -> &Output {
+		&self.output
+	}
+
+	/// Calls `handler` with the output of every call that printed something,
+	/// e.g. to prefix it with the plugin name and forward it to a logger.
+	pub fn on_output(&mut self, handler: impl Fn(&str, &Output) + Send + Sync + 'static) {
+		self.output_handler = Some(Arc::new(handler));
+	}
+
+	/// Appends every following call to `writer`, in the format described in [`record`].
+	pub fn record_to(&mut self, writer: impl Write + Send + 'static) -> io::Result<()> {
+		let recorder = Recorder::new(Box::new(writer))?;
+		self.recorder = Some(Arc::new(Mutex::new(recorder)));
+		Ok(())
+	}
+
+	pub fn record_to_file(&mut self, path: impl AsRef<Path>) -> io::Result<()> {
+		self.record_to(BufWriter::new(File::create(path)?))
+	}
+
+	pub fn stop_recording(&mut self) {
+		self.recorder = None;
+	}
+
+	/// Allocates a parameter of `length` elements in the guest, to fill in place
+	/// with [`fill_param`](Self::fill_param) and pass as [`Param::Guest`]
+	/// without copying it through an intermediate buffer.
+	pub fn alloc_param(&mut self, ty: WireType, length: u32) -> Result<GuestParam, Error> {
+		let size = length.checked_mul(ty.elem_size() as u32)
+			.filter(|size| *size <= i32::MAX as u32)
+			.ok_or_else(|| Error::Unsupported(format!("a parameter of {} {:?} elements", length, ty)))?;
+		let pointer = self.allocate(size as i32)?;
+		Ok(GuestParam { ty, pointer, length, size, owner: self.kept.owner() })
+	}
+
+	/// Lends the buffer of `param` to `fill`, e.g. to read a file straight into
+	/// it. It is lent in place when the backend can lend its memory, otherwise
+	/// `fill` gets a zeroed buffer copied into the guest afterwards.
+	pub fn fill_param<R>(&mut self, param: &GuestParam, fill: impl FnOnce(&mut [u8]) -> R) -> Result<R, Error> {
+		if !self.kept.owns(&param.owner) {
+			return Err(Error::StaleValue);
+		}
+		self.check_bounds(param.pointer, param.size)?;
+		self.fresh = false;
+		if let Some(bytes) = self.backend.view_mut(&self.config.memory, param.pointer, param.size)? {
+			return Ok(fill(bytes));
+		}
+		let mut bytes = vec![0; param.size as usize];
+		let result = fill(&mut bytes);
+		self.backend.write(&self.config.memory, param.pointer, &bytes)?;
+		Ok(result)
+	}
+
+	/// Frees a parameter that won't be passed to a call.
+	pub fn free_param(&mut self, param: GuestParam) -> Result<(), Error> {
+		if !self.kept.owns(&param.owner) {
+			return Err(Error::StaleValue);
+		}
+		self.deallocate(param.pointer, param.size)
+	}
+
+	/// Saves the linear memory and exported mutable globals, for [`reset`](Self::reset)
+	/// to bring the instance back to this state, e.g. right after instantiation.
+	///
+	/// A snapshot taken before any call, with a backend able to [reinstantiate](Backend::reinstantiate),
+	/// is restored by instantiating the module again, which also resets the
+	/// globals the module doesn't export. A later one restores only the memory
+	/// and exported globals: the others, such as the stack pointer, keep their
+	/// values, which is only safe while they are back to their initial values
+	/// between calls.
+	pub fn snapshot(&mut self) -> Result<(), Error> {
+		self.snapshot = Some(Arc::new(Snapshot::capture(&self.backend, &self.config.memory, self.fresh)?));
+		Ok(())
+	}
+
+	/// Restores the last snapshot, dropping whatever the calls since left in
+	/// the guest: heap fragmentation, caches, or another tenant's data. Only
+	/// the pages that changed are written back. Without re-instantiation, pages
+	/// the memory grew by are zeroed, and stay allocated.
+	pub fn reset(&mut self) -> Result<(), Error> {
+		let snapshot = self.snapshot.as_ref().ok_or(Error::NoSnapshot)?;
+		self.kept.invalidate();
+		snapshot.restore(&mut self.backend, &self.config.memory, self.initialize)?;
+		self.fresh = snapshot.is_fresh();
+		Ok(())
+	}
+
+	pub fn has_snapshot(&self) -> bool {
+		self.snapshot.is_some()
+	}
+
+	/// Calls `handler` on every trap, e.g. to alert on a plugin that keeps crashing.
+	pub fn on_trap(&mut self, handler: impl Fn(&str, &Error) + Send + Sync + 'static) {
+		self.trap_handler = Some(Arc::new(handler));
+	}
+
+	/// Number of calls that trapped.
+	pub fn trap_count(&self) -> u64 {
+		self.traps
+	}
+
+	/// Number of times the instance was replaced after a trap.
+	pub fn recycle_count(&self) -> u64 {
+		self.recycles
+	}
+
+	/// Whether the instance trapped and will be replaced before the next call.
+	/// Only happens with backends able to [reinstantiate](Backend::reinstantiate),
+	/// others keep using the instance.
+	pub fn is_poisoned(&self) -> bool {
+		self.poisoned
+	}
+
+	/// Calls `_initialize` when the module is a reactor, as WASI requires
+	/// before any other export. From then on, every instance replacing this
+	/// one is initialized too: after [`recycle`](Self::recycle), [`reload`](Self::reload)
+	/// and a [`reset`](Self::reset) that instantiates the module again.
+	pub fn initialize(&mut self) -> Result<(), Error> {
+		self.initialize = true;
+		initialize(&mut self.backend)
+	}
+
+	/// Replaces the instance with a fresh one of the same module.
+	pub fn recycle(&mut self) -> Result<(), Error> {
+		reinstantiate(&mut self.backend, self.initialize)?;
+		self.kept.invalidate();
+		self.allocator = None;
+		self.poisoned = false;
+		self.fresh = true;
+		self.recycles += 1;
+		Ok(())
+	}
+
+	/// Manifest of the running module, when it has one and the backend was
+	/// given its bytes or it was [reloaded](Self::reload).
+	pub fn manifest(&self) -> Option<&Manifest> {
+		self.manifest.as_ref()
+	}
+
+	/// Replaces the module with `wasm` for the following calls, keeping the
+	/// host functions, config, metrics and recorder.
+	///
+	/// `wasm` must have a manifest exporting all the functions of the running
+	/// module's with the same signatures, otherwise the running module is kept.
+	/// So is it when the new one fails to instantiate or lacks the exports of
+	/// the config, with backends that can check the new instance before swapping
+	/// it in. Without the manifest of the running module there is nothing to
+	/// check against, and the reload is refused. The snapshot is dropped, as it
+	/// belongs to the old module.
+	pub fn reload(&mut self, wasm: impl AsRef<[u8]>) -> Result<(), Error> {
+		let wasm = wasm.as_ref();
+		let old = self.manifest.as_ref().ok_or_else(|| Error::Incompatible(String::from("the running module has no manifest to check the new one against")))?;
+		let manifest = Manifest::from_module(wasm).map_err(|e| Error::Incompatible(format!("Invalid manifest: {}", e)))?;
+		let new = manifest.as_ref().ok_or_else(|| Error::Incompatible(String::from("the new module has no manifest")))?;
+		old.check_compatible(new).map_err(Error::Incompatible)?;
+		let config = &self.config;
+		self.backend.reload(wasm, &|backend| config.validate(backend))?;
+		if self.initialize {
+			initialize(&mut self.backend)?;
+		}
+		self.kept.invalidate();
+		self.allocator = None;
+		self.manifest = manifest;
+		self.snapshot = None;
+		self.poisoned = false;
+		self.fresh = true;
+		Ok(())
+	}
+
+	pub fn reload_file(&mut self, path: impl AsRef<Path>) -> Result<(), Error> {
+		self.reload(std::fs::read(path).map_err(Error::Io)?)
+	}
+
+	/// Runs the calls of a recording again and compares their outcomes.
+	pub fn replay(&mut self, calls: &[RecordedCall]) -> Vec<Replayed> {
+		let mut replayed = Vec::with_capacity(calls.len());
+		for (index, call) in calls.iter().enumerate() {
+			let params: Result<Vec<Param>, _> = call.params.iter().map(|p| Param::decode(p.ty, &p.bytes)).collect();
+			let start = Instant::now();
+			let actual = match params {
+				Ok(params) => Outcome::from(self.run_encoded(&call.func_name, params)),
+				Err(e) => Outcome::HostError(format!("Invalid recorded parameter: {}", e)),
+			};
+			replayed.push(Replayed {
+				index,
+				func_name: call.func_name.clone(),
+				expected: call.outcome.clone(),
+				actual,
+				recorded_duration: call.duration,
+				duration: start.elapsed(),
+			});
+		}
+		replayed
+	}
+
+	pub fn run_wasm(&mut self, func_name: impl AsRef<str>, inputs: Vec<Param>) -> Result<WasmResult, Error> {
+		let values = match self.run_encoded(func_name, inputs)? {
+			Ok(values) => values,
+			Err(message) => return Ok(Err(message)),
+		};
+		Ok(decode_results(&values))
+	}
+
+	/// Like [`run_wasm`](Self::run_wasm), also returning what the guest wrote
+	/// to stdout and stderr during the call.
+	pub fn run_wasm_with_output(&mut self, func_name: impl AsRef<str>, inputs: Vec<Param>) -> Result<(WasmResult, Output), Error> {
+		let result = self.run_wasm(func_name, inputs)?;
+		Ok((result, std::mem::take(&mut self.output)))
+	}
+
+	/// Like [`run_wasm`](Self::run_wasm) with owned values, for types only known at runtime.
+	pub fn run_wasm_values(&mut self, func_name: impl AsRef<str>, inputs: Vec<Value>) -> Result<Result<Vec<Value>, String>, Error> {
+		let values = match self.run_encoded(func_name, inputs.into_iter().map(Param::from).collect())? {
+			Ok(values) => values,
+			Err(message) => return Ok(Err(message)),
+		};
+		Ok(values.iter().map(|v| Value::decode(v.ty, &v.bytes)).collect::<Result<_, _>>().map_err(|e| e.to_string()))
+	}
+
+	/// Looks up `func_name` once, for [`run_prepared`](Self::run_prepared) to call it repeatedly.
+	pub fn prepare(&mut self, func_name: impl AsRef<str>) -> Result<PreparedCall, Error> {
+		let func_name = func_name.as_ref();
+		if !self.backend.has_function(func_name) {
+			return Err(Error::MissingFunction(func_name.to_string()));
+		}
+		Ok(PreparedCall {
+			func_name: func_name.to_string(),
+			exports: self.exports(func_name, true)?,
+			params: self.manifest.as_ref().and_then(|m| m.function(func_name)).map(|f| f.params.clone()),
+		})
+	}
+
+	/// Like [`run_wasm`](Self::run_wasm) for a function looked up by [`prepare`](Self::prepare).
+	pub fn run_prepared(&mut self, call: &PreparedCall, inputs: Vec<Param>) -> Result<WasmResult, Error> {
+		if let Err(message) = call.check(&inputs) {
+			return Ok(Err(message));
+		}
+		let values = match self.run_exports(&call.func_name, &call.exports, inputs)? {
+			Ok(values) => values,
+			Err(message) => return Ok(Err(message)),
+		};
+		Ok(decode_results(&values))
+	}
+
+	/// Like [`run_wasm`](Self::run_wasm), leaving the results in their wire encoding.
+	pub fn run_encoded(&mut self, func_name: impl AsRef<str>, inputs: Vec<Param>) -> Result<Result<Vec<Encoded>, String>, Error> {
+		let func_name = func_name.as_ref();
+		let exports = self.exports(func_name, false)?;
+		self.run_exports(func_name, &exports, inputs)
+	}
+
+	/// Runs `func_name` once per item of `inputs`, returning the result of each.
+	///
+	/// When the manifest flags a batch export, see `#[wasmedge_bindgen(batch)]`,
+	/// all the calls cross into the guest at once. Otherwise, or when some
+	/// parameters already are in the guest, they are made one by one. A batch
+	/// the guest rejects as a whole fails every call with its message.
+	pub fn call_batch(&mut self, func_name: impl AsRef<str>, inputs: Vec<Vec<Param>>) -> Result<Vec<WasmResult>, Error> {
+		let func_name = func_name.as_ref();
+		let batch = self.manifest.as_ref().and_then(|m| m.function(func_name)).is_some_and(|f| f.batch);
+		if !batch || inputs.iter().flatten().any(|p| matches!(p, Param::Guest(_))) {
+			return inputs.into_iter().map(|inputs| self.run_wasm(func_name, inputs)).collect();
+		}
+
+		let _span = span!("call_batch", func = func_name, calls = inputs.len());
+		let start = self.begin()?;
+		let mut recorded = self.recorder.as_ref().map(|_| Vec::with_capacity(inputs.len()));
+		let mut result = self.call_batch_export(func_name, inputs, &mut recorded);
+		if self.config.isolated {
+			result = self.reset().and(result);
+		}
+
+		let failed = !matches!(&result, Ok(results) if results.iter().all(Result::is_ok));
+		self.finish(func_name, start, result.as_ref().err(), failed, None);
+		for (i, params) in recorded.into_iter().flatten().enumerate() {
+			let outcome = match &result {
+				Ok(results) => Outcome::from(Ok(results[i].clone())),
+				Err(e) => Outcome::HostError(e.to_string()),
+			};
+			self.record(func_name, params, outcome, start);
+		}
+		Ok(result?.iter().map(|r| r.as_ref().map_err(Clone::clone).and_then(|values| decode_results(values))).collect())
+	}
+
+	/// Exports of `func_name` according to the manifest, resolved when `resolve` is set.
+	fn exports(&mut self, func_name: &str, resolve: bool) -> Result<Exports, Error> {
+		let signature = self.manifest.as_ref().and_then(|m| m.function(func_name));
+		let (packed, direct) = signature.map_or((false, false), |s| (s.packed, s.direct));
+		// Only direct calls need the types, to convert the values
+		let signature = signature.filter(|_| direct).cloned();
+		let mut export = |name: String| -> Result<Export, Error> {
+			let handle = if resolve { self.backend.resolve(&name)? } else { None };
+			Ok(Export { name, handle })
+		};
+		Ok(Exports {
+			packed: packed.then(|| export(abi::packed::export_name(func_name))).transpose()?,
+			direct: match signature {
+				Some(signature) => Some((export(abi::direct::export_name(func_name))?, signature)),
+				None => None,
+			},
+			handle: if resolve { self.backend.resolve(func_name)? } else { None },
+		})
+	}
+
+	/// Calls the direct export when there is one and the parameters are the
+	/// numbers it takes, or the packed one unless parameters already are in
+	/// the guest and would have to be copied into the frame.
+	fn run_exports(&mut self, func_name: &str, exports: &Exports, inputs: Vec<Param>) -> Result<Result<Vec<Encoded>, String>, Error> {
+		let _span = span!("run_wasm", func = func_name);
+		let start = self.begin()?;
+
+		let mut recorded = self.recorder.as_ref().map(|_| Vec::with_capacity(inputs.len()));
+		let direct = exports.direct.as_ref().filter(|(_, signature)| direct::accepts(signature, &inputs));
+		let packed = exports.packed.as_ref().filter(|_| !inputs.iter().any(|p| matches!(p, Param::Guest(_))));
+		let mut result = match (direct, packed) {
+			(Some((export, signature)), _) => self.call_direct(export, signature, inputs, &mut recorded),
+			(None, Some(export)) => self.call_packed(export, inputs, &mut recorded),
+			(None, None) => match self.call(func_name, exports.handle, inputs, &mut recorded) {
+				Ok(Ok(entries)) => self.take_values(&entries).map(Ok),
+				other => other.map(|r| r.map(|_| Vec::new())),
+			},
+		};
+		if self.config.isolated {
+			result = self.reset().and(result);
+		}
+
+		let record = recorded.map(|params| (params, Outcome::from(&result)));
+		self.finish(func_name, start, result.as_ref().err(), !matches!(result, Ok(Ok(_))), record);
+		result
+	}
+
+	/// Like [`run_wasm`](Self::run_wasm), leaving the results in the guest memory
+	/// to read them without copying, until they are [released](GuestResults::release).
+	pub fn run_wasm_borrowed(&mut self, func_name: impl AsRef<str>, inputs: Vec<Param>) -> Result<Result<GuestResults<'_, B>, String>, Error> {
+		let func_name = func_name.as_ref();
+		let _span = span!("run_wasm", func = func_name);
+		let start = self.begin()?;
+
+		let mut recorded = self.recorder.as_ref().map(|_| Vec::with_capacity(inputs.len()));
+		let mut result = match self.call(func_name, None, inputs, &mut recorded) {
+			Ok(Ok(entries)) => self.check_entries(&entries).map(|_| Ok(entries)),
+			other => other,
+		};
+		// On success, the reset waits for the results to be released
+		if self.config.isolated && !matches!(result, Ok(Ok(_))) {
+			result = self.reset().and(result);
+		}
+
+		let record = recorded.map(|params| {
+			let outcome = match &result {
+				Ok(Ok(entries)) => Outcome::from(self.copy_values(entries).map(Ok)),
+				Ok(Err(message)) => Outcome::GuestError(message.clone()),
+				Err(e) => Outcome::HostError(e.to_string()),
+			};
+			(params, outcome)
+		});
+		self.finish(func_name, start, result.as_ref().err(), !matches!(result, Ok(Ok(_))), record);
+		Ok(result?.map(|entries| GuestResults::new(self, entries)))
+	}
+
+	/// Like [`run_wasm`](Self::run_wasm), keeping the results in the guest as
+	/// handles to pass to later calls, e.g. between the steps of a pipeline,
+	/// or to [take](Self::take_value) when needed on the host.
+	///
+	/// Not for isolated instances, which are reset after every call.
+	pub fn run_wasm_kept(&mut self, func_name: impl AsRef<str>, inputs: Vec<Param>) -> Result<Result<Vec<GuestValue>, String>, Error> {
+		let func_name = func_name.as_ref();
+		if self.config.isolated {
+			return Err(Error::Unsupported(String::from("values kept in an isolated instance")));
+		}
+		let _span = span!("run_wasm", func = func_name);
+		let start = self.begin()?;
+
+		let mut recorded = self.recorder.as_ref().map(|_| Vec::with_capacity(inputs.len()));
+		let result = match self.call(func_name, None, inputs, &mut recorded) {
+			Ok(Ok(entries)) => self.check_entries(&entries).map(|_| Ok(entries)),
+			other => other,
+		};
+
+		let record = recorded.map(|params| {
+			let outcome = match &result {
+				Ok(Ok(entries)) => Outcome::from(self.copy_values(entries).map(Ok)),
+				Ok(Err(message)) => Outcome::GuestError(message.clone()),
+				Err(e) => Outcome::HostError(e.to_string()),
+			};
+			(params, outcome)
+		});
+		self.finish(func_name, start, result.as_ref().err(), !matches!(result, Ok(Ok(_))), record);
+		Ok(result?.map(|entries| entries.iter().map(|entry| GuestValue::new(entry, self.kept.owner())).collect()))
+	}
+
+	/// Borrows the encoded bytes of a kept value, or a copy of them when the
+	/// backend can't lend its memory.
+	pub fn view_value(&self, value: &GuestValue) -> Result<GuestBytes<'_>, Error> {
+		if !self.kept.owns(value.owner()) {
+			return Err(Error::StaleValue);
+		}
+		self.check_bounds(value.pointer(), value.size())?;
+		let bytes = self.backend.view(&self.config.memory, value.pointer(), value.size())?;
+		Ok(GuestBytes::new(value.ty(), bytes))
+	}
+
+	/// Copies a kept value out of the guest and frees it there, decoding it
+	/// like [`run_wasm`](Self::run_wasm) does.
+	pub fn take_value(&mut self, value: GuestValue) -> Result<Box<dyn Any + Send + Sync>, Error> {
+		if !self.kept.owns(value.owner()) {
+			return Err(Error::StaleValue);
+		}
+		let ty = value.ty();
+		let (pointer, size) = value.hand_over();
+		let bytes = self.read(pointer, size);
+		let _ = self.deallocate(pointer, size);
+		abi::decode_any(ty, &bytes?).map_err(|e| Error::InvalidReturn(e.to_string()))
+	}
+
+	/// Prepares a call: replaces a poisoned instance and takes the snapshot of
+	/// an isolated one.
+	fn begin(&mut self) -> Result<Instant, Error> {
+		let start = Instant::now();
+		self.stats = CallStats::default();
+		if self.poisoned {
+			self.recycle()?;
+		}
+		if self.config.isolated && self.snapshot.is_none() {
+			self.snapshot()?;
+		}
+		self.fresh = false;
+		if self.allocator.is_none() {
+			// Exports that can't be resolved are called by name, and reported missing then
+			self.allocator = Some(Allocator {
+				allocate: self.backend.resolve(&self.config.allocate).unwrap_or(None),
+				deallocate: self.backend.resolve(&self.config.deallocate).unwrap_or(None),
+			});
+		}
+		for (pointer, size) in self.kept.take_dropped() {
+			let _ = self.deallocate(pointer, size);
+		}
+		Ok(start)
+	}
+
+	/// Accounts for a finished call: traps, output, statistics, metrics and recording.
+	fn finish(&mut self, func_name: &str, start: Instant, error: Option<&Error>, failed: bool, record: Option<(Vec<Encoded>, Outcome)>) {
+		if let Some(e) = error.filter(|e| e.is_trap()) {
+			self.traps += 1;
+			self.poisoned = self.backend.can_reinstantiate();
+			if let Some(handler) = &self.trap_handler {
+				handler(func_name, e);
+			}
+		}
+
+		self.output = self.backend.take_output();
+		if let Some(handler) = self.output_handler.as_ref().filter(|_| !self.output.is_empty()) {
+			handler(func_name, &self.output);
+		}
+
+		self.stats.failed = failed;
+		self.stats.marshal_time = start.elapsed().saturating_sub(self.stats.guest_time);
+		#[cfg(feature = "tracing")]
+		tracing::debug!(
+			bytes_in = self.stats.bytes_in,
+			bytes_out = self.stats.bytes_out,
+			allocate_calls = self.stats.allocate_calls,
+			deallocate_calls = self.stats.deallocate_calls,
+			guest_time = ?self.stats.guest_time,
+			marshal_time = ?self.stats.marshal_time,
+			failed = self.stats.failed,
+			"bindgen call finished",
+		);
+		if let Some(metrics) = &self.metrics {
+			metrics.record(func_name, &self.stats);
+		}
+		if let Some((params, outcome)) = record {
+			self.record(func_name, params, outcome, start);
+		}
+	}
+
+	fn record(&self, func_name: &str, params: Vec<Encoded>, outcome: Outcome, start: Instant) {
+		let Some(recorder) = &self.recorder else {
+			return;
+		};
+		let call = RecordedCall {
+			func_name: func_name.to_string(),
+			params,
+			outcome,
+			duration: start.elapsed(),
+		};
+		// A failing recording must not break the calls themselves
+		let _ = recorder.lock().unwrap_or_else(|e| e.into_inner()).record(&call);
+	}
+
+	/// Runs the guest function, returning the entries of its results.
+	fn call(&mut self, func_name: &str, handle: Option<FuncHandle>, inputs: Vec<Param>, recorded: &mut Option<Vec<Encoded>>) -> Result<Result<Vec<ResultEntry>, String>, Error> {
+		let inputs_count = inputs.len() as i32;
+		// Before anything is allocated for the call
+		for inp in inputs.iter() {
+			if let Param::Guest(GuestParam { owner, .. }) = inp {
+				if !self.kept.owns(owner) {
+					return Err(Error::StaleValue);
+				}
+			}
+		}
+
+		let pointer_of_pointers = {
+			let _span = span!("settle", params = inputs.len());
+
+			// allocate new frame for passing pointers
+			let pointer_of_pointers = self.allocate(inputs_count * PARAM_ENTRY_SIZE as i32)?;
+
+			let mut frame = Vec::with_capacity(inputs.len());
+			for inp in inputs.iter() {
+				if let Param::Guest(p) = inp {
+					if let Some(params) = recorded.as_mut() {
+						params.push(Encoded { ty: p.ty, bytes: self.backend.read(&self.config.memory, p.pointer, p.size)? });
+					}
+					frame.push(ParamEntry { pointer: p.pointer, length: p.length });
+					continue;
+				}
+				let (length, bytes) = inp.encode()?;
+				frame.push(self.settle(length, &bytes)?);
+				if let Some(params) = recorded.as_mut() {
+					params.push(Encoded { ty: inp.wire_type(), bytes });
+				}
+			}
+			self.write(pointer_of_pointers, &ParamEntry::encode_all(&frame))?;
+			pointer_of_pointers
+		};
+
+		let rets = {
+			let _span = span!("guest");
+			let guest_start = Instant::now();
+			let args = [Val::I32(pointer_of_pointers as i32), Val::I32(inputs_count)];
+			let rets = match handle {
+				Some(handle) => self.backend.call_handle(handle, &args),
+				None => self.backend.call(func_name, &args),
+			};
+			self.stats.guest_time = guest_start.elapsed();
+			rets?
+		};
+		// Don't need to deallocate because the memory will be loaded and free in the wasm
+
+		let header_pointer = match rets.as_slice() {
+			[Val::I32(p)] => *p as u32,
+			_ => return Ok(Err(String::from("Invalid return value"))),
+		};
+		let rvec = self.read(header_pointer, HEADER_SIZE as u32)?;
+		let _ = self.deallocate(header_pointer, HEADER_SIZE as u32);
+
+		let header = match ReturnHeader::decode(&rvec) {
+			Ok(header) => header,
+			Err(e) => return Ok(Err(e.to_string())),
+		};
+		match header.is_error {
+			false => self.parse_result(header.pointer, header.len),
+			true => self.parse_error(header.pointer, header.len),
+		}
+	}
+
+	/// Runs the direct export of a guest function, returning its result.
+	fn call_direct(&mut self, export: &Export, signature: &Signature, inputs: Vec<Param>, recorded: &mut Option<Vec<Encoded>>) -> Result<Result<Vec<Encoded>, String>, Error> {
+		let args: Vec<Val> = inputs.iter().filter_map(direct::to_val).collect();
+		self.stats.bytes_in += inputs.iter().map(|inp| inp.wire_type().elem_size() as u64).sum::<u64>();
+		if let Some(params) = recorded.as_mut() {
+			for inp in inputs.iter() {
+				params.push(Encoded { ty: inp.wire_type(), bytes: inp.encode()?.1 });
+			}
+		}
+
+		let rets = {
+			let _span = span!("guest");
+			let guest_start = Instant::now();
+			let rets = match export.handle {
+				Some(handle) => self.backend.call_handle(handle, &args),
+				None => self.backend.call(&export.name, &args),
+			};
+			self.stats.guest_time = guest_start.elapsed();
+			rets?
+		};
+
+		if rets.len() != signature.results.len() {
+			return Err(Error::InvalidReturn(format!("{} should return {} values", export.name, signature.results.len())));
+		}
+		let mut results = Vec::with_capacity(rets.len());
+		for (ty, val) in signature.results.iter().zip(rets) {
+			let bytes = direct::from_val(*ty, val).ok_or_else(|| Error::InvalidReturn(format!("{} returned {:?} for {:?}", export.name, val, ty)))?;
+			self.stats.bytes_out += bytes.len() as u64;
+			results.push(Encoded { ty: *ty, bytes });
+		}
+		Ok(Ok(results))
+	}
+
+	/// Runs the packed export of a guest function, returning its results.
+	fn call_packed(&mut self, export: &Export, inputs: Vec<Param>, recorded: &mut Option<Vec<Encoded>>) -> Result<Result<Vec<Encoded>, String>, Error> {
+		let frame = {
+			let _span = span!("settle", params = inputs.len());
+			let mut frame = abi::packed::Writer::params(inputs.len());
+			for inp in inputs.iter() {
+				let (_, bytes) = inp.encode()?;
+				frame.push(inp.wire_type(), |out| out.extend_from_slice(&bytes));
+				if let Some(params) = recorded.as_mut() {
+					params.push(Encoded { ty: inp.wire_type(), bytes });
+				}
+			}
+			frame.finish()
+		};
+		let bytes = match self.call_buffer(export, &frame)? {
+			Some(bytes) => bytes,
+			None => return Ok(Err(String::from("Invalid return value"))),
+		};
+
+		let _span = span!("take_values");
+		Ok(packed_results(&bytes))
+	}
+
+	/// Runs the batch export of a guest function, returning the results of each call.
+	fn call_batch_export(&mut self, func_name: &str, inputs: Vec<Vec<Param>>, recorded: &mut Option<Vec<Vec<Encoded>>>) -> Result<Vec<Result<Vec<Encoded>, String>>, Error> {
+		let calls = inputs.len();
+		let frame = {
+			let _span = span!("settle", calls = calls);
+			let mut frame = abi::batch::Writer::params(calls);
+			for inputs in inputs.iter() {
+				let mut packed = abi::packed::Writer::params(inputs.len());
+				let mut params = recorded.as_ref().map(|_| Vec::with_capacity(inputs.len()));
+				for inp in inputs.iter() {
+					let (_, bytes) = inp.encode()?;
+					packed.push(inp.wire_type(), |out| out.extend_from_slice(&bytes));
+					if let Some(params) = params.as_mut() {
+						params.push(Encoded { ty: inp.wire_type(), bytes });
+					}
+				}
+				frame.push(&packed.finish());
+				if let (Some(recorded), Some(params)) = (recorded.as_mut(), params) {
+					recorded.push(params);
+				}
+			}
+			frame.finish()
+		};
+		let export = Export { name: abi::batch::export_name(func_name), handle: None };
+		let bytes = self.call_buffer(&export, &frame)?.ok_or_else(|| Error::InvalidReturn(format!("{} should return a single i64", export.name)))?;
+
+		let _span = span!("take_values", calls = calls);
+		let invalid = |e: abi::DecodeError| Error::InvalidReturn(format!("{}: {}", export.name, e));
+		let mut reader = match abi::batch::Reader::results(&bytes).map_err(invalid)? {
+			Ok(reader) => reader,
+			// The guest is fine, the calls just didn't run
+			Err(message) => return Ok(vec![Err(message); calls]),
+		};
+		if reader.remaining() as usize != calls {
+			return Err(Error::InvalidReturn(format!("{} returned {} results for {} calls", export.name, reader.remaining(), calls)));
+		}
+		let mut results = Vec::with_capacity(calls);
+		while let Some(packed) = reader.next_call().map_err(invalid)? {
+			results.push(packed_results(packed));
+		}
+		Ok(results)
+	}
+
+	/// Writes `frame` into the guest and calls the packed or batch `export` with
+	/// it, returning the buffer it hands back, or `None` if it returned something else.
+	fn call_buffer(&mut self, export: &Export, frame: &[u8]) -> Result<Option<Vec<u8>>, Error> {
+		let pointer = self.allocate(frame.len() as i32)?;
+		self.write(pointer, frame)?;
+
+		let rets = {
+			let _span = span!("guest");
+			let guest_start = Instant::now();
+			let args = [Val::I32(pointer as i32), Val::I32(frame.len() as i32)];
+			let rets = match export.handle {
+				Some(handle) => self.backend.call_handle(handle, &args),
+				None => self.backend.call(&export.name, &args),
+			};
+			self.stats.guest_time = guest_start.elapsed();
+			rets?
+		};
+
+		let (pointer, len) = match rets.as_slice() {
+			[Val::I64(v)] => (*v as u32, (*v as u64 >> 32) as u32),
+			_ => return Ok(None),
+		};
+		self.check_bounds(pointer, len)?;
+		let bytes = self.read(pointer, len)?;
+		let _ = self.deallocate(pointer, len);
+		Ok(Some(bytes))
+	}
+
+	fn settle(&mut self, length: u32, bytes: &[u8]) -> Result<ParamEntry, Error> {
+		let pointer = self.allocate(bytes.len() as i32)?;
+		self.write(pointer, bytes)?;
+		Ok(ParamEntry { pointer, length })
+	}
+
+	fn parse_error(&mut self, ret_pointer: u32, ret_len: u32) -> Result<Result<Vec<ResultEntry>, String>, Error> {
+		let _span = span!("parse_error");
+		let err_bytes = self.read(ret_pointer, ret_len)?;
+		let _ = self.deallocate(ret_pointer, ret_len);
+		Ok(Err(String::from_utf8(err_bytes).unwrap_or_default()))
+	}
+
+	fn parse_result(&mut self, ret_pointer: u32, ret_len: u32) -> Result<Result<Vec<ResultEntry>, String>, Error> {
+		let _span = span!("parse_result", results = ret_len);
+		let entries_size = ret_len.saturating_mul(ENTRY_SIZE as u32);
+		let p_data = self.read(ret_pointer, entries_size)?;
+		let _ = self.deallocate(ret_pointer, entries_size);
+
+		Ok(ResultEntry::decode_all(&p_data, ret_len as usize).map_err(|e| e.to_string()))
+	}
+
+	/// Reads and frees the values of the results.
+	fn take_values(&mut self, entries: &[ResultEntry]) -> Result<Vec<Encoded>, Error> {
+		let _span = span!("take_values", results = entries.len());
+		let mut results = Vec::with_capacity(entries.len());
+		for entry in entries.iter() {
+			let bytes = self.read(entry.pointer, entry.size)?;
+			let _ = self.deallocate(entry.pointer, entry.size);
+			results.push(Encoded { ty: entry.ty, bytes });
+		}
+		Ok(results)
+	}
+
+	/// Reads the values of the results, leaving them in the guest.
+	fn copy_values(&self, entries: &[ResultEntry]) -> Result<Vec<Encoded>, Error> {
+		entries.iter()
+			.map(|entry| Ok(Encoded { ty: entry.ty, bytes: self.backend.read(&self.config.memory, entry.pointer, entry.size)? }))
+			.collect()
+	}
+
+	fn check_entries(&mut self, entries: &[ResultEntry]) -> Result<(), Error> {
+		for entry in entries.iter() {
+			self.check_bounds(entry.pointer, entry.size)?;
+			self.stats.bytes_out += entry.size as u64;
+		}
+		Ok(())
+	}
+
+	// Pointers and lengths come from the guest, check them before the
+	// backend allocates a buffer of `len` bytes
+	fn check_bounds(&self, offset: u32, len: u32) -> Result<(), Error> {
+		match offset as u64 + len as u64 > self.backend.memory_size(&self.config.memory)? {
+			true => Err(Error::MemoryAccess { offset, len }),
+			false => Ok(()),
+		}
+	}
+
+	fn allocate(&mut self, size: i32) -> Result<u32, Error> {
+		let args = match self.config.alloc_signature {
+			AllocSignature::Size => vec![Val::I32(size)],
+			AllocSignature::SizeAlign => vec![Val::I32(size), Val::I32(self.config.align)],
+		};
+		self.stats.allocate_calls += 1;
+		self.fresh = false;
+		let rets = match self.allocator.and_then(|a| a.allocate) {
+			Some(handle) => self.backend.call_handle(handle, &args)?,
+			None => self.backend.call(&self.config.allocate, &args)?,
+		};
+		match rets.as_slice() {
+			[Val::I32(pointer)] => Ok(*pointer as u32),
+			_ => Err(Error::InvalidReturn(format!("{} should return a single i32", self.config.allocate))),
+		}
+	}
+
+	fn deallocate(&mut self, pointer: u32, size: u32) -> Result<(), Error> {
+		let (pointer, size) = (Val::I32(pointer as i32), Val::I32(size as i32));
+		let args = match self.config.dealloc_signature {
+			DeallocSignature::Ptr => vec![pointer],
+			DeallocSignature::PtrSize => vec![pointer, size],
+			DeallocSignature::PtrSizeAlign => vec![pointer, size, Val::I32(self.config.align)],
+		};
+		self.stats.deallocate_calls += 1;
+		self.fresh = false;
+		match self.allocator.and_then(|a| a.deallocate) {
+			Some(handle) => self.backend.call_handle(handle, &args)?,
+			None => self.backend.call(&self.config.deallocate, &args)?,
+		};
+		Ok(())
+	}
+
+	fn read(&mut self, offset: u32, len: u32) -> Result<Vec<u8>, Error> {
+		self.check_bounds(offset, len)?;
+		self.stats.bytes_out += len as u64;
+		self.backend.read(&self.config.memory, offset, len)
+	}
+
+	fn write(&mut self, offset: u32, data: &[u8]) -> Result<(), Error> {
+		self.stats.bytes_in += data.len() as u64;
+		self.backend.write(&self.config.memory, offset, data)
+	}
+}
+
+/// Values of a packed results buffer, or the message of the call that failed.
+/// Instantiates the module again, calling `_initialize` if `initialize`.
+pub(crate) fn reinstantiate(backend: &mut impl Backend, initialize: bool) -> Result<(), Error> {
+	backend.reinstantiate()?;
+	if initialize {
+		self::initialize(backend)?;
+	}
+	Ok(())
+}
+
+fn initialize(backend: &mut impl Backend) -> Result<(), Error> {
+	if backend.has_function("_initialize") {
+		backend.call("_initialize", &[])?;
+	}
+	Ok(())
+}
+
+fn packed_results(bytes: &[u8]) -> Result<Vec<Encoded>, String> {
+	let mut reader = abi::packed::Reader::results(bytes).map_err(|e| e.to_string())??;
+	let mut results = Vec::with_capacity(reader.remaining().min(64) as usize);
+	while let Some((ty, bytes)) = reader.next_value().map_err(|e| e.to_string())? {
+		results.push(Encoded { ty, bytes: bytes.to_vec() });
+	}
+	Ok(results)
+}
+
+fn decode_results(values: &[Encoded]) -> WasmResult {
+	values.iter().map(|v| abi::decode_any(v.ty, &v.bytes).map_err(|e| e.to_string())).collect()
+}
@@ -0,0 +1,40 @@
+use std::time::Duration;
+
+/// What happened during one `run_wasm` call.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CallStats {
+	/// Bytes of parameters written to the guest, frame included.
+	pub bytes_in: u64,
+	/// Bytes of results read back, header and entries included.
+	pub bytes_out: u64,
+	pub allocate_calls: u32,
+	pub deallocate_calls: u32,
+	/// Time spent in the guest function itself.
+	pub guest_time: Duration,
+	/// Time spent on everything else: allocating, copying and decoding.
+	pub marshal_time: Duration,
+	/// Whether the call failed, either in the host or with a guest error.
+	pub failed: bool,
+}
+
+/// Receives the statistics of every call, to feed counters and histograms
+/// of whichever metrics library the application uses.
+pub trait Metrics: Send + Sync {
+	fn record(&self, func_name: &str, stats: &CallStats);
+}
+
+/// Emits a span per call and per phase when the `tracing` feature is on.
+macro_rules! span {
+	($name:literal $(, $($fields:tt)*)?) => {{
+		#[cfg(feature = "tracing")]
+		let span = tracing::debug_span!($name $(, $($fields)*)?).entered();
+		#[cfg(not(feature = "tracing"))]
+		let span = $crate::metrics::NoSpan;
+		span
+	}};
+}
+
+#[cfg(not(feature = "tracing"))]
+pub(crate) struct NoSpan;
+
+pub(crate) use span;
@@ -0,0 +1,13 @@
+/// What a guest wrote to its stdout and stderr during a call, for backends
+/// that capture it instead of letting it through to the host's.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Output {
+	pub stdout: Vec<u8>,
+	pub stderr: Vec<u8>,
+}
+
+impl Output {
+	pub fn is_empty(&self) -> bool {
+		self.stdout.is_empty() && self.stderr.is_empty()
+	}
+}
@@ -0,0 +1,292 @@
+use std::borrow::Cow;
+
+use wasmedge_bindgen_abi as abi;
+use wasmedge_bindgen_abi::{DecodeError, Wire, WireType};
+
+use crate::guest_value::Owner;
+use crate::Error;
+
+#[derive(Debug)]
+pub enum Param<'a> {
+	I8(i8),
+	U8(u8),
+	I16(i16),
+	U16(u16),
+	I32(i32),
+	U32(u32),
+	I64(i64),
+	U64(u64),
+	F32(f32),
+	F64(f64),
+	Bool(bool),
+	Char(char),
+	VecI8(&'a [i8]),
+	VecU8(&'a [u8]),
+	VecI16(&'a [i16]),
+	VecU16(&'a [u16]),
+	VecI32(&'a [i32]),
+	VecU32(&'a [u32]),
+	VecI64(&'a [i64]),
+	VecU64(&'a [u64]),
+	String(&'a str),
+	OwnedVecI8(Vec<i8>),
+	OwnedVecU8(Vec<u8>),
+	OwnedVecI16(Vec<i16>),
+	OwnedVecU16(Vec<u16>),
+	OwnedVecI32(Vec<i32>),
+	OwnedVecU32(Vec<u32>),
+	OwnedVecI64(Vec<i64>),
+	OwnedVecU64(Vec<u64>),
+	OwnedString(String),
+	/// A buffer already in the guest, from [`Bindgen::alloc_param`](crate::Bindgen::alloc_param).
+	Guest(GuestParam),
+}
+
+/// A parameter allocated in the guest memory by [`Bindgen::alloc_param`](crate::Bindgen::alloc_param),
+/// to fill in place and pass as [`Param::Guest`].
+///
+/// It belongs to the instance that allocated it, and not after a reset,
+/// recycle or reload, when using it fails with [`Error::StaleValue`](crate::Error::StaleValue).
+/// The guest frees it when it is passed to a call, otherwise
+/// [`Bindgen::free_param`](crate::Bindgen::free_param) does.
+#[derive(Debug)]
+pub struct GuestParam {
+	pub(crate) ty: WireType,
+	pub(crate) pointer: u32,
+	pub(crate) length: u32,
+	pub(crate) size: u32,
+	/// To check it still is valid.
+	pub(crate) owner: Owner,
+}
+
+impl GuestParam {
+	pub fn ty(&self) -> WireType {
+		self.ty
+	}
+
+	/// Number of elements.
+	pub fn len(&self) -> u32 {
+		self.length
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.length == 0
+	}
+
+	/// Size of the buffer in bytes.
+	pub fn size(&self) -> u32 {
+		self.size
+	}
+}
+
+impl<'a> Param<'a> {
+	/// Element count and little-endian bytes of the value. Parameters already
+	/// in the guest have none, and are only passed by classic calls.
+	pub(crate) fn encode(&self) -> Result<(u32, Vec<u8>), Error> {
+		let encoded = match self {
+			Param::I8(v) => (v.length(), v.to_bytes()),
+			Param::U8(v) => (v.length(), v.to_bytes()),
+			Param::I16(v) => (v.length(), v.to_bytes()),
+			Param::U16(v) => (v.length(), v.to_bytes()),
+			Param::I32(v) => (v.length(), v.to_bytes()),
+			Param::U32(v) => (v.length(), v.to_bytes()),
+			Param::I64(v) => (v.length(), v.to_bytes()),
+			Param::U64(v) => (v.length(), v.to_bytes()),
+			Param::F32(v) => (v.length(), v.to_bytes()),
+			Param::F64(v) => (v.length(), v.to_bytes()),
+			Param::Bool(v) => (v.length(), v.to_bytes()),
+			Param::Char(v) => (v.length(), v.to_bytes()),
+			Param::VecI8(v) => (v.len() as u32, abi::encode_slice(v)),
+			Param::VecU8(v) => (v.len() as u32, abi::encode_slice(v)),
+			Param::VecI16(v) => (v.len() as u32, abi::encode_slice(v)),
+			Param::VecU16(v) => (v.len() as u32, abi::encode_slice(v)),
+			Param::VecI32(v) => (v.len() as u32, abi::encode_slice(v)),
+			Param::VecU32(v) => (v.len() as u32, abi::encode_slice(v)),
+			Param::VecI64(v) => (v.len() as u32, abi::encode_slice(v)),
+			Param::VecU64(v) => (v.len() as u32, abi::encode_slice(v)),
+			Param::String(v) => (v.len() as u32, v.as_bytes().to_vec()),
+			Param::OwnedVecI8(v) => return Param::VecI8(v).encode(),
+			Param::OwnedVecU8(v) => return Param::VecU8(v).encode(),
+			Param::OwnedVecI16(v) => return Param::VecI16(v).encode(),
+			Param::OwnedVecU16(v) => return Param::VecU16(v).encode(),
+			Param::OwnedVecI32(v) => return Param::VecI32(v).encode(),
+			Param::OwnedVecU32(v) => return Param::VecU32(v).encode(),
+			Param::OwnedVecI64(v) => return Param::VecI64(v).encode(),
+			Param::OwnedVecU64(v) => return Param::VecU64(v).encode(),
+			Param::OwnedString(v) => return Param::String(v).encode(),
+			Param::Guest(_) => return Err(Error::Unsupported(String::from("parameters already in the guest, outside of classic calls"))),
+		};
+		Ok(encoded)
+	}
+
+	pub fn wire_type(&self) -> WireType {
+		match self {
+			Param::I8(_) => WireType::I8,
+			Param::U8(_) => WireType::U8,
+			Param::I16(_) => WireType::I16,
+			Param::U16(_) => WireType::U16,
+			Param::I32(_) => WireType::I32,
+			Param::U32(_) => WireType::U32,
+			Param::I64(_) => WireType::I64,
+			Param::U64(_) => WireType::U64,
+			Param::F32(_) => WireType::F32,
+			Param::F64(_) => WireType::F64,
+			Param::Bool(_) => WireType::Bool,
+			Param::Char(_) => WireType::Char,
+			Param::VecI8(_) | Param::OwnedVecI8(_) => WireType::I8Array,
+			Param::VecU8(_) | Param::OwnedVecU8(_) => WireType::U8Array,
+			Param::VecI16(_) | Param::OwnedVecI16(_) => WireType::I16Array,
+			Param::VecU16(_) | Param::OwnedVecU16(_) => WireType::U16Array,
+			Param::VecI32(_) | Param::OwnedVecI32(_) => WireType::I32Array,
+			Param::VecU32(_) | Param::OwnedVecU32(_) => WireType::U32Array,
+			Param::VecI64(_) | Param::OwnedVecI64(_) => WireType::I64Array,
+			Param::VecU64(_) | Param::OwnedVecU64(_) => WireType::U64Array,
+			Param::String(_) | Param::OwnedString(_) => WireType::String,
+			Param::Guest(p) => p.ty,
+		}
+	}
+
+	/// Rebuilds a parameter from its type tag and encoded bytes.
+	pub fn decode(ty: WireType, bytes: &[u8]) -> Result<Param<'static>, DecodeError> {
+		let param = match ty {
+			WireType::I8 => Param::I8(Wire::decode(bytes)?),
+			WireType::U8 => Param::U8(Wire::decode(bytes)?),
+			WireType::I16 => Param::I16(Wire::decode(bytes)?),
+			WireType::U16 => Param::U16(Wire::decode(bytes)?),
+			WireType::I32 => Param::I32(Wire::decode(bytes)?),
+			WireType::U32 => Param::U32(Wire::decode(bytes)?),
+			WireType::I64 => Param::I64(Wire::decode(bytes)?),
+			WireType::U64 => Param::U64(Wire::decode(bytes)?),
+			WireType::F32 => Param::F32(Wire::decode(bytes)?),
+			WireType::F64 => Param::F64(Wire::decode(bytes)?),
+			WireType::Bool => Param::Bool(Wire::decode(bytes)?),
+			WireType::Char => Param::Char(Wire::decode(bytes)?),
+			WireType::I8Array => Param::OwnedVecI8(Wire::decode(bytes)?),
+			WireType::U8Array => Param::OwnedVecU8(Wire::decode(bytes)?),
+			WireType::I16Array => Param::OwnedVecI16(Wire::decode(bytes)?),
+			WireType::U16Array => Param::OwnedVecU16(Wire::decode(bytes)?),
+			WireType::I32Array => Param::OwnedVecI32(Wire::decode(bytes)?),
+			WireType::U32Array => Param::OwnedVecU32(Wire::decode(bytes)?),
+			WireType::I64Array => Param::OwnedVecI64(Wire::decode(bytes)?),
+			WireType::U64Array => Param::OwnedVecU64(Wire::decode(bytes)?),
+			WireType::String => Param::OwnedString(Wire::decode(bytes)?),
+		};
+		Ok(param)
+	}
+}
+
+macro_rules! impl_from_scalar {
+	($($t:ty => $variant:ident),* $(,)?) => {
+		$(
+		impl<'a> From<$t> for Param<'a> {
+			fn from(v: $t) -> Self {
+				Param::$variant(v)
+			}
+		}
+		)*
+	};
+}
+
+macro_rules! impl_from_vec {
+	($($t:ty => $borrowed:ident, $owned:ident),* $(,)?) => {
+		$(
+		impl<'a> From<&'a [$t]> for Param<'a> {
+			fn from(v: &'a [$t]) -> Self {
+				Param::$borrowed(v)
+			}
+		}
+
+		impl<'a> From<&'a Vec<$t>> for Param<'a> {
+			fn from(v: &'a Vec<$t>) -> Self {
+				Param::$borrowed(v)
+			}
+		}
+
+		impl<'a> From<Vec<$t>> for Param<'a> {
+			fn from(v: Vec<$t>) -> Self {
+				Param::$owned(v)
+			}
+		}
+
+		impl<'a> From<Cow<'a, [$t]>> for Param<'a> {
+			fn from(v: Cow<'a, [$t]>) -> Self {
+				match v {
+					Cow::Borrowed(v) => Param::$borrowed(v),
+					Cow::Owned(v) => Param::$owned(v),
+				}
+			}
+		}
+		)*
+	};
+}
+
+impl_from_scalar! {
+	i8 => I8,
+	u8 => U8,
+	i16 => I16,
+	u16 => U16,
+	i32 => I32,
+	u32 => U32,
+	i64 => I64,
+	u64 => U64,
+	f32 => F32,
+	f64 => F64,
+	bool => Bool,
+	char => Char,
+}
+
+impl_from_vec! {
+	i8 => VecI8, OwnedVecI8,
+	u8 => VecU8, OwnedVecU8,
+	i16 => VecI16, OwnedVecI16,
+	u16 => VecU16, OwnedVecU16,
+	i32 => VecI32, OwnedVecI32,
+	u32 => VecU32, OwnedVecU32,
+	i64 => VecI64, OwnedVecI64,
+	u64 => VecU64, OwnedVecU64,
+}
+
+impl<'a> From<&'a str> for Param<'a> {
+	fn from(v: &'a str) -> Self {
+		Param::String(v)
+	}
+}
+
+impl<'a> From<&'a String> for Param<'a> {
+	fn from(v: &'a String) -> Self {
+		Param::String(v)
+	}
+}
+
+impl<'a> From<String> for Param<'a> {
+	fn from(v: String) -> Self {
+		Param::OwnedString(v)
+	}
+}
+
+impl<'a> From<Cow<'a, str>> for Param<'a> {
+	fn from(v: Cow<'a, str>) -> Self {
+		match v {
+			Cow::Borrowed(v) => Param::String(v),
+			Cow::Owned(v) => Param::OwnedString(v),
+		}
+	}
+}
+
+/// Builds a `Vec<Param>` from values convertible with `Param::from`.
+///
+/// ```ignore
+/// bg.run_wasm("f", params![1i32, "s", &bytes[..]])
+/// ```
+#[macro_export]
+macro_rules! params {
+	($($p:expr),* $(,)?) => {
+		vec![$($crate::Param::from($p)),*]
+	};
+}
+
+impl<'a> From<GuestParam> for Param<'a> {
+	fn from(v: GuestParam) -> Self {
+		Param::Guest(v)
+	}
+}
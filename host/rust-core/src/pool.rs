@@ -0,0 +1,70 @@
+use std::collections::VecDeque;
+use std::sync::{Condvar, Mutex, MutexGuard, OnceLock};
+use std::thread;
+use std::time::Duration;
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// How long a worker waits for a job before exiting.
+const KEEP_ALIVE: Duration = Duration::from_secs(10);
+
+static POOL: OnceLock<Pool> = OnceLock::new();
+
+#[derive(Default)]
+struct Pool {
+	state: Mutex<State>,
+	queued: Condvar,
+}
+
+#[derive(Default)]
+struct State {
+	jobs: VecDeque<Job>,
+	idle: usize,
+}
+
+/// Runs `job` on the threads dedicated to blocking calls into guests. A
+/// thread is started whenever all are busy, so jobs never wait for each
+/// other; [`AsyncBindgen`](crate::AsyncBindgen) only spawns jobs for idle
+/// instances, which bounds their number.
+pub(crate) fn spawn(job: impl FnOnce() + Send + 'static) {
+	let pool = POOL.get_or_init(Pool::default);
+	let mut state = pool.lock();
+	state.jobs.push_back(Box::new(job));
+	if state.jobs.len() > state.idle {
+		drop(state);
+		thread::Builder::new()
+			.name(String::from("bindgen-blocking"))
+			.spawn(move || pool.work())
+			.expect("failed to spawn a bindgen worker thread");
+	} else {
+		pool.queued.notify_one();
+	}
+}
+
+impl Pool {
+	fn lock(&self) -> MutexGuard<'_, State> {
+		self.state.lock().unwrap_or_else(|e| e.into_inner())
+	}
+
+	fn work(&self) {
+		let mut state = self.lock();
+		loop {
+			match state.jobs.pop_front() {
+				Some(job) => {
+					drop(state);
+					job();
+					state = self.lock();
+				}
+				None => {
+					state.idle += 1;
+					let (next, waited) = self.queued.wait_timeout(state, KEEP_ALIVE).unwrap_or_else(|e| e.into_inner());
+					state = next;
+					state.idle -= 1;
+					if waited.timed_out() && state.jobs.is_empty() {
+						return;
+					}
+				}
+			}
+		}
+	}
+}
@@ -0,0 +1,68 @@
+use wasmedge_bindgen_abi::DecodeError;
+
+use crate::{FuncHandle, Param, Signature, WireType};
+
+/// A bindgen function looked up once by [`Bindgen::prepare`](crate::Bindgen::prepare),
+/// for hot loops calling it over and over.
+///
+/// It holds the exports resolved by the backend, so calls skip the lookup by
+/// name, and the parameter types of the manifest, so mismatched parameters
+/// are reported before anything is written to the guest.
+#[derive(Clone, Debug)]
+pub struct PreparedCall {
+	pub(crate) func_name: String,
+	pub(crate) exports: Exports,
+	pub(crate) params: Option<Vec<WireType>>,
+}
+
+impl PreparedCall {
+	pub fn func_name(&self) -> &str {
+		&self.func_name
+	}
+
+	/// Parameter types of the function, when the module has a manifest.
+	pub fn params(&self) -> Option<&[WireType]> {
+		self.params.as_deref()
+	}
+
+	/// Checks `inputs` against the parameter types, with the message the
+	/// guest would fail with.
+	pub(crate) fn check(&self, inputs: &[Param]) -> Result<(), String> {
+		let Some(types) = &self.params else {
+			return Ok(());
+		};
+		if inputs.len() != types.len() {
+			return Err(DecodeError::InvalidParamsCount { expected: types.len(), got: inputs.len() as i32 }.to_string());
+		}
+		for (index, (input, ty)) in inputs.iter().zip(types).enumerate() {
+			if input.wire_type() != *ty {
+				return Err(DecodeError::MismatchedType { index, expected: *ty, got: input.wire_type() }.to_string());
+			}
+		}
+		Ok(())
+	}
+}
+
+/// An export of a bindgen function, called by handle once resolved.
+#[derive(Clone, Debug)]
+pub(crate) struct Export {
+	pub(crate) name: String,
+	pub(crate) handle: Option<FuncHandle>,
+}
+
+/// What a bindgen function can be called through: the export taking a frame
+/// of pointers, and the packed and direct variants the manifest advertises.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Exports {
+	pub(crate) handle: Option<FuncHandle>,
+	pub(crate) packed: Option<Export>,
+	/// With the signature, to convert the values.
+	pub(crate) direct: Option<(Export, Signature)>,
+}
+
+/// Handles of the allocator exports, resolved on the first call.
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct Allocator {
+	pub(crate) allocate: Option<FuncHandle>,
+	pub(crate) deallocate: Option<FuncHandle>,
+}
@@ -0,0 +1,266 @@
+//! Recording of calls, to replay them later against another build of a module.
+//!
+//! A recording starts with [`MAGIC`], followed by one record per call, all
+//! integers little-endian:
+//!
+//! * `name_len: u32`, the function name, `nanos: u64` spent in the call
+//! * `count: u32` parameters, each `type: i32`, `len: u32` and `len` encoded bytes
+//! * `outcome: u8`: 0 for results, stored like the parameters, 1 for a guest
+//!   error and 2 for a host error, followed by `len: u32` bytes of message
+
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::time::Duration;
+
+use wasmedge_bindgen_abi as abi;
+use wasmedge_bindgen_abi::WireType;
+
+use crate::Error;
+
+pub const MAGIC: &[u8; 8] = b"wbgrec01";
+
+/// A parameter or result in its wire encoding.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Encoded {
+	pub ty: WireType,
+	pub bytes: Vec<u8>,
+}
+
+impl fmt::Display for Encoded {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match abi::decode_any(self.ty, &self.bytes) {
+			Ok(v) => {
+				macro_rules! show {
+					($($t:ty),*) => {
+						$(if let Some(v) = v.downcast_ref::<$t>() {
+							return write!(f, "{:?}", v);
+						})*
+					};
+				}
+				show!(u8, i8, u16, i16, u32, i32, u64, i64, f32, f64, bool, char, String,
+					Vec<u8>, Vec<i8>, Vec<u16>, Vec<i16>, Vec<u32>, Vec<i32>, Vec<u64>, Vec<i64>);
+				write!(f, "{:?}({:02x?})", self.ty, self.bytes)
+			}
+			Err(_) => write!(f, "invalid {:?}({:02x?})", self.ty, self.bytes),
+		}
+	}
+}
+
+/// How a call ended.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Outcome {
+	Ok(Vec<Encoded>),
+	/// The `Err(String)` returned by the guest.
+	GuestError(String),
+	/// An [`Error`](crate::Error) of the host or runtime, e.g. a trap.
+	HostError(String),
+}
+
+impl fmt::Display for Outcome {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Outcome::Ok(values) => {
+				write!(f, "Ok(")?;
+				for (i, v) in values.iter().enumerate() {
+					if i > 0 {
+						write!(f, ", ")?;
+					}
+					write!(f, "{}", v)?;
+				}
+				write!(f, ")")
+			}
+			Outcome::GuestError(message) => write!(f, "Err({:?})", message),
+			Outcome::HostError(message) => write!(f, "host error: {}", message),
+		}
+	}
+}
+
+impl From<&Result<Result<Vec<Encoded>, String>, Error>> for Outcome {
+	fn from(result: &Result<Result<Vec<Encoded>, String>, Error>) -> Self {
+		match result {
+			Ok(Ok(values)) => Outcome::Ok(values.clone()),
+			Ok(Err(message)) => Outcome::GuestError(message.clone()),
+			Err(e) => Outcome::HostError(e.to_string()),
+		}
+	}
+}
+
+impl From<Result<Result<Vec<Encoded>, String>, Error>> for Outcome {
+	fn from(result: Result<Result<Vec<Encoded>, String>, Error>) -> Self {
+		match result {
+			Ok(Ok(values)) => Outcome::Ok(values),
+			Ok(Err(message)) => Outcome::GuestError(message),
+			Err(e) => Outcome::HostError(e.to_string()),
+		}
+	}
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RecordedCall {
+	pub func_name: String,
+	pub params: Vec<Encoded>,
+	pub outcome: Outcome,
+	pub duration: Duration,
+}
+
+/// The result of replaying one [`RecordedCall`].
+#[derive(Clone, Debug)]
+pub struct Replayed {
+	/// Position of the call in the recording.
+	pub index: usize,
+	pub func_name: String,
+	pub expected: Outcome,
+	pub actual: Outcome,
+	pub recorded_duration: Duration,
+	pub duration: Duration,
+}
+
+impl Replayed {
+	pub fn is_match(&self) -> bool {
+		self.expected == self.actual
+	}
+}
+
+impl fmt::Display for Replayed {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "#{} {} ({:?}, recorded {:?}): ", self.index, self.func_name, self.duration, self.recorded_duration)?;
+		match self.is_match() {
+			true => write!(f, "{}", self.actual),
+			false => write!(f, "expected {}, got {}", self.expected, self.actual),
+		}
+	}
+}
+
+pub(crate) struct Recorder {
+	writer: Box<dyn Write + Send>,
+}
+
+impl Recorder {
+	pub(crate) fn new(mut writer: Box<dyn Write + Send>) -> io::Result<Self> {
+		writer.write_all(MAGIC)?;
+		Ok(Recorder { writer })
+	}
+
+	pub(crate) fn record(&mut self, call: &RecordedCall) -> io::Result<()> {
+		let mut out = Vec::new();
+		put_bytes(&mut out, call.func_name.as_bytes());
+		out.extend_from_slice(&(call.duration.as_nanos() as u64).to_le_bytes());
+		put_values(&mut out, &call.params);
+		match &call.outcome {
+			Outcome::Ok(values) => {
+				out.push(0);
+				put_values(&mut out, values);
+			}
+			Outcome::GuestError(message) => {
+				out.push(1);
+				put_bytes(&mut out, message.as_bytes());
+			}
+			Outcome::HostError(message) => {
+				out.push(2);
+				put_bytes(&mut out, message.as_bytes());
+			}
+		}
+		self.writer.write_all(&out)?;
+		self.writer.flush()
+	}
+}
+
+fn put_bytes(out: &mut Vec<u8>, bytes: &[u8]) {
+	out.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+	out.extend_from_slice(bytes);
+}
+
+fn put_values(out: &mut Vec<u8>, values: &[Encoded]) {
+	out.extend_from_slice(&(values.len() as u32).to_le_bytes());
+	for v in values {
+		out.extend_from_slice(&(v.ty as i32).to_le_bytes());
+		put_bytes(out, &v.bytes);
+	}
+}
+
+fn invalid(message: impl Into<String>) -> io::Error {
+	io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
+
+struct Reader<R> {
+	inner: R,
+}
+
+impl<R: Read> Reader<R> {
+	fn array<const N: usize>(&mut self) -> io::Result<[u8; N]> {
+		let mut buf = [0; N];
+		self.inner.read_exact(&mut buf)?;
+		Ok(buf)
+	}
+
+	fn u32(&mut self) -> io::Result<u32> {
+		self.array().map(u32::from_le_bytes)
+	}
+
+	fn exact(&mut self, len: u32) -> io::Result<Vec<u8>> {
+		// Don't trust the length with a preallocation, the input may be truncated
+		let mut bytes = Vec::new();
+		(&mut self.inner).take(len as u64).read_to_end(&mut bytes)?;
+		match bytes.len() == len as usize {
+			true => Ok(bytes),
+			false => Err(io::ErrorKind::UnexpectedEof.into()),
+		}
+	}
+
+	fn bytes(&mut self) -> io::Result<Vec<u8>> {
+		let len = self.u32()?;
+		self.exact(len)
+	}
+
+	fn string(&mut self, len: u32) -> io::Result<String> {
+		String::from_utf8(self.exact(len)?).map_err(|_| invalid("invalid UTF-8 in recording"))
+	}
+
+	fn string_field(&mut self) -> io::Result<String> {
+		let len = self.u32()?;
+		self.string(len)
+	}
+
+	fn values(&mut self) -> io::Result<Vec<Encoded>> {
+		let count = self.u32()?;
+		let mut values = Vec::new();
+		for _ in 0..count {
+			let tag = i32::from_le_bytes(self.array()?);
+			let ty = WireType::from_i32(tag).ok_or_else(|| invalid(format!("unknown type {} in recording", tag)))?;
+			values.push(Encoded { ty, bytes: self.bytes()? });
+		}
+		Ok(values)
+	}
+
+	/// Reads the next call, or `None` at the end of the recording.
+	fn call(&mut self) -> io::Result<Option<RecordedCall>> {
+		let mut first = [0; 4];
+		match self.inner.read(&mut first[..1])? {
+			0 => return Ok(None),
+			_ => self.inner.read_exact(&mut first[1..])?,
+		}
+		let func_name = self.string(u32::from_le_bytes(first))?;
+		let duration = Duration::from_nanos(u64::from_le_bytes(self.array()?));
+		let params = self.values()?;
+		let outcome = match self.array::<1>()?[0] {
+			0 => Outcome::Ok(self.values()?),
+			1 => Outcome::GuestError(self.string_field()?),
+			2 => Outcome::HostError(self.string_field()?),
+			v => return Err(invalid(format!("unknown outcome {} in recording", v))),
+		};
+		Ok(Some(RecordedCall { func_name, params, outcome, duration }))
+	}
+}
+
+/// Reads a whole recording made with [`Bindgen::record_to`](crate::Bindgen::record_to).
+pub fn read_recording(reader: impl Read) -> io::Result<Vec<RecordedCall>> {
+	let mut reader = Reader { inner: reader };
+	if &reader.array::<8>()? != MAGIC {
+		return Err(invalid("not a bindgen recording"));
+	}
+	let mut calls = Vec::new();
+	while let Some(call) = reader.call()? {
+		calls.push(call);
+	}
+	Ok(calls)
+}
@@ -0,0 +1,119 @@
+use crate::{Backend, Bindgen, Error, GuestParam, GuestResults, Param, WasmResult};
+
+/// Named instances, possibly of different modules, whose bindgen functions
+/// can be chained: the results of a call are copied from its linear memory
+/// straight into the next instance as parameters, without being decoded on
+/// the host.
+pub struct Router<B> {
+	modules: Vec<(String, Bindgen<B>)>,
+}
+
+impl<B: Backend> Default for Router<B> {
+	fn default() -> Self {
+		Router { modules: Vec::new() }
+	}
+}
+
+impl<B: Backend> Router<B> {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Adds an instance under `name`, replacing the one already there.
+	pub fn add(&mut self, name: impl Into<String>, bindgen: impl Into<Bindgen<B>>) -> &mut Self {
+		let (name, bindgen) = (name.into(), bindgen.into());
+		match self.modules.iter_mut().find(|(n, _)| *n == name) {
+			Some(module) => module.1 = bindgen,
+			None => self.modules.push((name, bindgen)),
+		}
+		self
+	}
+
+	pub fn remove(&mut self, name: &str) -> Option<Bindgen<B>> {
+		let index = self.modules.iter().position(|(n, _)| n == name)?;
+		Some(self.modules.remove(index).1)
+	}
+
+	pub fn get(&self, name: &str) -> Option<&Bindgen<B>> {
+		self.modules.iter().find(|(n, _)| n == name).map(|(_, bg)| bg)
+	}
+
+	pub fn get_mut(&mut self, name: &str) -> Option<&mut Bindgen<B>> {
+		self.modules.iter_mut().find(|(n, _)| n == name).map(|(_, bg)| bg)
+	}
+
+	pub fn names(&self) -> impl Iterator<Item = &str> {
+		self.modules.iter().map(|(n, _)| n.as_str())
+	}
+
+	pub fn run_wasm(&mut self, module: &str, func_name: impl AsRef<str>, inputs: Vec<Param>) -> Result<WasmResult, Error> {
+		let index = self.index(module)?;
+		self.modules[index].1.run_wasm(func_name, inputs)
+	}
+
+	/// Calls the `(module, function)`s of `route` in turn, passing the results
+	/// of each call as the parameters of the next, and returns the results of
+	/// the last one. Stops at the first guest error.
+	///
+	/// Only the last results are decoded. The others are copied from memory to
+	/// memory, or handed over in place when the next call is in the same instance.
+	pub fn route(&mut self, route: &[(&str, &str)], inputs: Vec<Param>) -> Result<WasmResult, Error> {
+		let (&(module, func_name), hops) = route.split_last().ok_or_else(|| Error::InvalidConfig(String::from("Empty route")))?;
+		let mut inputs = inputs;
+		for (hop, &(from, func_name)) in hops.iter().enumerate() {
+			let (source, target) = (self.index(from)?, self.index(route[hop + 1].0)?);
+			inputs = if source == target {
+				match self.modules[source].1.run_wasm_borrowed(func_name, inputs)? {
+					Ok(results) => results.into_params(),
+					Err(message) => return Ok(Err(message)),
+				}
+			} else {
+				let (source, target) = self.pair(source, target);
+				match source.run_wasm_borrowed(func_name, inputs)? {
+					Ok(results) => copy_results(&results, target)?,
+					Err(message) => return Ok(Err(message)),
+				}
+			};
+		}
+		self.run_wasm(module, func_name, inputs)
+	}
+
+	fn index(&self, name: &str) -> Result<usize, Error> {
+		self.modules.iter().position(|(n, _)| n == name).ok_or_else(|| Error::MissingModule(name.to_string()))
+	}
+
+	/// Borrows two different instances at once.
+	fn pair(&mut self, a: usize, b: usize) -> (&mut Bindgen<B>, &mut Bindgen<B>) {
+		if a < b {
+			let (head, tail) = self.modules.split_at_mut(b);
+			(&mut head[a].1, &mut tail[0].1)
+		} else {
+			let (head, tail) = self.modules.split_at_mut(a);
+			(&mut tail[0].1, &mut head[b].1)
+		}
+	}
+}
+
+/// Copies `results` into buffers allocated in `target`.
+fn copy_results<B: Backend>(results: &GuestResults<'_, B>, target: &mut Bindgen<B>) -> Result<Vec<Param<'static>>, Error> {
+	let mut params: Vec<GuestParam> = Vec::with_capacity(results.len());
+	for index in 0..results.len() {
+		let copied = results.get(index).and_then(|bytes| {
+			let param = target.alloc_param(bytes.ty(), (bytes.len() / bytes.ty().elem_size()) as u32)?;
+			match target.fill_param(&param, |buf| buf.copy_from_slice(&bytes)) {
+				Ok(()) => Ok(param),
+				Err(e) => Err(target.free_param(param).err().unwrap_or(e)),
+			}
+		});
+		match copied {
+			Ok(param) => params.push(param),
+			Err(e) => {
+				for param in params {
+					let _ = target.free_param(param);
+				}
+				return Err(e);
+			}
+		}
+	}
+	Ok(params.into_iter().map(Param::Guest).collect())
+}
@@ -0,0 +1,76 @@
+use crate::{reinstantiate, Backend, Error, Val};
+
+const PAGE_SIZE: u64 = 65536;
+
+/// Linear memory and mutable globals of an instance.
+pub(crate) struct Snapshot {
+	memory: Vec<u8>,
+	globals: Vec<(String, Val)>,
+	// Whether no guest code ran in the instance since it was instantiated
+	fresh: bool,
+}
+
+impl Snapshot {
+	pub(crate) fn capture(backend: &impl Backend, memory: &str, fresh: bool) -> Result<Self, Error> {
+		let size = backend.memory_size(memory)?;
+		let mut bytes = Vec::with_capacity(size as usize);
+		for offset in (0..size).step_by(PAGE_SIZE as usize) {
+			bytes.extend_from_slice(&backend.view(memory, offset as u32, page_len(offset, size))?);
+		}
+		Ok(Snapshot {
+			memory: bytes,
+			globals: backend.globals()?,
+			fresh,
+		})
+	}
+
+	pub(crate) fn is_fresh(&self) -> bool {
+		self.fresh
+	}
+
+	/// Brings the instance back to the capture. A capture of a fresh instance
+	/// is restored by instantiating the module again, which also resets the
+	/// globals it doesn't export, such as the stack pointer. So is an instance
+	/// whose memory grew since, as a memory can't shrink, before the saved
+	/// pages are written back. Otherwise only the pages that changed are
+	/// written back, and without re-instantiation grown pages are zeroed.
+	/// New instances are initialized if `initialize`.
+	pub(crate) fn restore(&self, backend: &mut impl Backend, memory: &str, initialize: bool) -> Result<(), Error> {
+		if backend.can_reinstantiate() {
+			if self.fresh {
+				return reinstantiate(backend, initialize);
+			}
+			if backend.memory_size(memory)? > self.memory.len() as u64 {
+				reinstantiate(backend, initialize)?;
+				let size = backend.memory_size(memory)?;
+				if size < self.memory.len() as u64 {
+					backend.grow(memory, ((self.memory.len() as u64 - size) / PAGE_SIZE) as u32)?;
+				}
+			}
+		}
+		let size = backend.memory_size(memory)?;
+		for offset in (0..size).step_by(PAGE_SIZE as usize) {
+			let len = page_len(offset, size);
+			let saved = self.memory.get(offset as usize..offset as usize + len as usize);
+			let current = backend.view(memory, offset as u32, len)?;
+			let changed = match saved {
+				Some(saved) => *saved != *current,
+				None => current.iter().any(|&b| b != 0),
+			};
+			drop(current);
+			match saved {
+				Some(saved) if changed => backend.write(memory, offset as u32, saved)?,
+				None if changed => backend.write(memory, offset as u32, &vec![0; len as usize])?,
+				_ => {}
+			}
+		}
+		for (name, value) in self.globals.iter() {
+			backend.set_global(name, *value)?;
+		}
+		Ok(())
+	}
+}
+
+fn page_len(offset: u64, size: u64) -> u32 {
+	(size - offset).min(PAGE_SIZE) as u32
+}
@@ -0,0 +1,278 @@
+use wasmedge_bindgen_abi::{DecodeError, Wire, WireType};
+
+use crate::Param;
+
+/// An owned value of any wire type, for calls whose types are only known at
+/// runtime. Converts to and from `serde_json::Value` with the `json` feature.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+	I8(i8),
+	U8(u8),
+	I16(i16),
+	U16(u16),
+	I32(i32),
+	U32(u32),
+	I64(i64),
+	U64(u64),
+	F32(f32),
+	F64(f64),
+	Bool(bool),
+	Char(char),
+	VecI8(Vec<i8>),
+	VecU8(Vec<u8>),
+	VecI16(Vec<i16>),
+	VecU16(Vec<u16>),
+	VecI32(Vec<i32>),
+	VecU32(Vec<u32>),
+	VecI64(Vec<i64>),
+	VecU64(Vec<u64>),
+	String(String),
+}
+
+impl Value {
+	pub fn wire_type(&self) -> WireType {
+		self.as_param().wire_type()
+	}
+
+	/// Borrows the value as a parameter.
+	pub fn as_param(&self) -> Param<'_> {
+		match self {
+			Value::I8(v) => Param::I8(*v),
+			Value::U8(v) => Param::U8(*v),
+			Value::I16(v) => Param::I16(*v),
+			Value::U16(v) => Param::U16(*v),
+			Value::I32(v) => Param::I32(*v),
+			Value::U32(v) => Param::U32(*v),
+			Value::I64(v) => Param::I64(*v),
+			Value::U64(v) => Param::U64(*v),
+			Value::F32(v) => Param::F32(*v),
+			Value::F64(v) => Param::F64(*v),
+			Value::Bool(v) => Param::Bool(*v),
+			Value::Char(v) => Param::Char(*v),
+			Value::VecI8(v) => Param::VecI8(v),
+			Value::VecU8(v) => Param::VecU8(v),
+			Value::VecI16(v) => Param::VecI16(v),
+			Value::VecU16(v) => Param::VecU16(v),
+			Value::VecI32(v) => Param::VecI32(v),
+			Value::VecU32(v) => Param::VecU32(v),
+			Value::VecI64(v) => Param::VecI64(v),
+			Value::VecU64(v) => Param::VecU64(v),
+			Value::String(v) => Param::String(v),
+		}
+	}
+
+	pub fn decode(ty: WireType, bytes: &[u8]) -> Result<Value, DecodeError> {
+		let value = match ty {
+			WireType::I8 => Value::I8(Wire::decode(bytes)?),
+			WireType::U8 => Value::U8(Wire::decode(bytes)?),
+			WireType::I16 => Value::I16(Wire::decode(bytes)?),
+			WireType::U16 => Value::U16(Wire::decode(bytes)?),
+			WireType::I32 => Value::I32(Wire::decode(bytes)?),
+			WireType::U32 => Value::U32(Wire::decode(bytes)?),
+			WireType::I64 => Value::I64(Wire::decode(bytes)?),
+			WireType::U64 => Value::U64(Wire::decode(bytes)?),
+			WireType::F32 => Value::F32(Wire::decode(bytes)?),
+			WireType::F64 => Value::F64(Wire::decode(bytes)?),
+			WireType::Bool => Value::Bool(Wire::decode(bytes)?),
+			WireType::Char => Value::Char(Wire::decode(bytes)?),
+			WireType::I8Array => Value::VecI8(Wire::decode(bytes)?),
+			WireType::U8Array => Value::VecU8(Wire::decode(bytes)?),
+			WireType::I16Array => Value::VecI16(Wire::decode(bytes)?),
+			WireType::U16Array => Value::VecU16(Wire::decode(bytes)?),
+			WireType::I32Array => Value::VecI32(Wire::decode(bytes)?),
+			WireType::U32Array => Value::VecU32(Wire::decode(bytes)?),
+			WireType::I64Array => Value::VecI64(Wire::decode(bytes)?),
+			WireType::U64Array => Value::VecU64(Wire::decode(bytes)?),
+			WireType::String => Value::String(Wire::decode(bytes)?),
+		};
+		Ok(value)
+	}
+}
+
+impl From<Value> for Param<'static> {
+	fn from(v: Value) -> Self {
+		match v {
+			Value::VecI8(v) => Param::OwnedVecI8(v),
+			Value::VecU8(v) => Param::OwnedVecU8(v),
+			Value::VecI16(v) => Param::OwnedVecI16(v),
+			Value::VecU16(v) => Param::OwnedVecU16(v),
+			Value::VecI32(v) => Param::OwnedVecI32(v),
+			Value::VecU32(v) => Param::OwnedVecU32(v),
+			Value::VecI64(v) => Param::OwnedVecI64(v),
+			Value::VecU64(v) => Param::OwnedVecU64(v),
+			Value::String(v) => Param::OwnedString(v),
+			Value::I8(v) => Param::I8(v),
+			Value::U8(v) => Param::U8(v),
+			Value::I16(v) => Param::I16(v),
+			Value::U16(v) => Param::U16(v),
+			Value::I32(v) => Param::I32(v),
+			Value::U32(v) => Param::U32(v),
+			Value::I64(v) => Param::I64(v),
+			Value::U64(v) => Param::U64(v),
+			Value::F32(v) => Param::F32(v),
+			Value::F64(v) => Param::F64(v),
+			Value::Bool(v) => Param::Bool(v),
+			Value::Char(v) => Param::Char(v),
+		}
+	}
+}
+
+#[cfg(feature = "json")]
+mod json {
+	use serde_json::{Map, Number};
+
+	use super::Value;
+	use crate::Error;
+
+	fn float(v: f64) -> serde_json::Value {
+		match Number::from_f64(v) {
+			Some(n) => serde_json::Value::Number(n),
+			// Not representable as JSON numbers
+			None if v.is_nan() => "NaN".into(),
+			None if v > 0.0 => "inf".into(),
+			None => "-inf".into(),
+		}
+	}
+
+	fn array<T: Copy + Into<serde_json::Value>>(v: &[T]) -> serde_json::Value {
+		serde_json::Value::Array(v.iter().map(|&e| e.into()).collect())
+	}
+
+	impl From<&Value> for serde_json::Value {
+		/// A single entry object naming the wire type, so that the conversion
+		/// back restores the exact type.
+		fn from(v: &Value) -> Self {
+			let json = match v {
+				Value::I8(v) => (*v).into(),
+				Value::U8(v) => (*v).into(),
+				Value::I16(v) => (*v).into(),
+				Value::U16(v) => (*v).into(),
+				Value::I32(v) => (*v).into(),
+				Value::U32(v) => (*v).into(),
+				Value::I64(v) => (*v).into(),
+				Value::U64(v) => (*v).into(),
+				Value::F32(v) => float(*v as f64),
+				Value::F64(v) => float(*v),
+				Value::Bool(v) => (*v).into(),
+				Value::Char(v) => v.to_string().into(),
+				Value::VecI8(v) => array(v),
+				Value::VecU8(v) => array(v),
+				Value::VecI16(v) => array(v),
+				Value::VecU16(v) => array(v),
+				Value::VecI32(v) => array(v),
+				Value::VecU32(v) => array(v),
+				Value::VecI64(v) => array(v),
+				Value::VecU64(v) => array(v),
+				Value::String(v) => v.as_str().into(),
+			};
+			let mut object = Map::new();
+			object.insert(key(v).to_string(), json);
+			serde_json::Value::Object(object)
+		}
+	}
+
+	impl From<Value> for serde_json::Value {
+		fn from(v: Value) -> Self {
+			(&v).into()
+		}
+	}
+
+	/// Key naming the wire type in the JSON object, e.g. `{"u64": 18446744073709551615}`.
+	fn key(v: &Value) -> &'static str {
+		match v {
+			Value::I8(_) => "i8",
+			Value::U8(_) => "u8",
+			Value::I16(_) => "i16",
+			Value::U16(_) => "u16",
+			Value::I32(_) => "i32",
+			Value::U32(_) => "u32",
+			Value::I64(_) => "i64",
+			Value::U64(_) => "u64",
+			Value::F32(_) => "f32",
+			Value::F64(_) => "f64",
+			Value::Bool(_) => "bool",
+			Value::Char(_) => "char",
+			Value::VecI8(_) => "i8_array",
+			Value::VecU8(_) => "u8_array",
+			Value::VecI16(_) => "i16_array",
+			Value::VecU16(_) => "u16_array",
+			Value::VecI32(_) => "i32_array",
+			Value::VecU32(_) => "u32_array",
+			Value::VecI64(_) => "i64_array",
+			Value::VecU64(_) => "u64_array",
+			Value::String(_) => "string",
+		}
+	}
+
+	fn invalid(json: &serde_json::Value) -> Error {
+		Error::InvalidValue(json.to_string())
+	}
+
+	fn to_float(json: &serde_json::Value) -> Option<f64> {
+		match json.as_str() {
+			Some("NaN") => Some(f64::NAN),
+			Some("inf") => Some(f64::INFINITY),
+			Some("-inf") => Some(f64::NEG_INFINITY),
+			_ => json.as_f64(),
+		}
+	}
+
+	fn to_int<T: TryFrom<i64> + TryFrom<u64>>(json: &serde_json::Value) -> Option<T> {
+		match json.as_u64() {
+			Some(v) => T::try_from(v).ok(),
+			None => json.as_i64().and_then(|v| T::try_from(v).ok()),
+		}
+	}
+
+	fn to_array<T>(json: &serde_json::Value, elem: impl Fn(&serde_json::Value) -> Option<T>) -> Option<Vec<T>> {
+		json.as_array()?.iter().map(elem).collect()
+	}
+
+	impl TryFrom<&serde_json::Value> for Value {
+		type Error = Error;
+
+		/// Reads the objects written by the conversion to JSON.
+		fn try_from(json: &serde_json::Value) -> Result<Self, Error> {
+			let (key, v) = match json.as_object() {
+				Some(object) if object.len() == 1 => object.iter().next().unwrap(),
+				_ => return Err(invalid(json)),
+			};
+			let value = match key.as_str() {
+				"i8" => to_int(v).map(Value::I8),
+				"u8" => to_int(v).map(Value::U8),
+				"i16" => to_int(v).map(Value::I16),
+				"u16" => to_int(v).map(Value::U16),
+				"i32" => to_int(v).map(Value::I32),
+				"u32" => to_int(v).map(Value::U32),
+				"i64" => to_int(v).map(Value::I64),
+				"u64" => to_int(v).map(Value::U64),
+				"f32" => to_float(v).map(|v| Value::F32(v as f32)),
+				"f64" => to_float(v).map(Value::F64),
+				"bool" => v.as_bool().map(Value::Bool),
+				"char" => v.as_str().and_then(|s| {
+					let mut chars = s.chars();
+					chars.next().filter(|_| chars.next().is_none())
+				}).map(Value::Char),
+				"i8_array" => to_array(v, to_int).map(Value::VecI8),
+				"u8_array" => to_array(v, to_int).map(Value::VecU8),
+				"i16_array" => to_array(v, to_int).map(Value::VecI16),
+				"u16_array" => to_array(v, to_int).map(Value::VecU16),
+				"i32_array" => to_array(v, to_int).map(Value::VecI32),
+				"u32_array" => to_array(v, to_int).map(Value::VecU32),
+				"i64_array" => to_array(v, to_int).map(Value::VecI64),
+				"u64_array" => to_array(v, to_int).map(Value::VecU64),
+				"string" => v.as_str().map(|s| Value::String(s.to_string())),
+				_ => None,
+			};
+			value.ok_or_else(|| invalid(json))
+		}
+	}
+
+	impl TryFrom<serde_json::Value> for Value {
+		type Error = Error;
+
+		fn try_from(json: serde_json::Value) -> Result<Self, Error> {
+			Value::try_from(&json)
+		}
+	}
+}
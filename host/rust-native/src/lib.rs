@@ -0,0 +1,166 @@
+use std::collections::HashMap;
+
+use wasmedge_bindgen_abi::{batch, direct, guest, linear, packed};
+
+pub use wasmedge_bindgen_host_core::{params, record, AllocSignature, AsyncBindgen, Backend, BindgenConfig, CallStats, DeallocSignature, Encoded, Error, FuncHandle, GuestBytes, GuestParam, GuestResults, GuestValue, Locked, Manifest, Metrics, Outcome, Output, Param, PreparedCall, RecordedCall, Replayed, RunWasm, Signature, Val, ValType, Value, WasmResult, WireType};
+
+/// Signature of the shims generated by #[wasmedge_bindgen].
+pub type Export = unsafe extern "C" fn(*mut u32, i32) -> i32;
+
+/// Signature of the [packed](packed) and [batch](batch) shims generated by #[wasmedge_bindgen].
+pub type PackedExport = unsafe extern "C" fn(u32, u32) -> u64;
+
+/// Adapter calling a [direct](direct) shim with the values of a call, as the
+/// shims don't share a signature.
+pub type DirectExport = fn(&[Val]) -> Vec<Val>;
+
+/// Calls bindgen functions of a guest crate linked into the host.
+pub type Bindgen = wasmedge_bindgen_host_core::Bindgen<NativeBackend>;
+
+/// Chains bindgen calls between guest crates linked into the host.
+pub type Router = wasmedge_bindgen_host_core::Router<NativeBackend>;
+
+/// [`Backend`] calling the exports of a guest crate compiled for the host target.
+///
+/// Guest buffers live in the linear memory simulated by the abi crate, exposed
+/// as `memory`, and `allocate`/`deallocate` are always available. Exports are
+/// registered by name:
+///
+/// ```ignore
+/// let mut bg = Bindgen::new(NativeBackend::new().export("say", say));
+/// ```
+///
+/// A panic in a guest function aborts the process, as it can't unwind out of
+/// the `extern "C"` shim.
+///
+/// The shims are only exported under their plain names on wasm32. Elsewhere
+/// their symbols are mangled, so a guest function named like a libc one, such
+/// as `write` or `free`, doesn't replace it in the host process.
+#[derive(Clone, Debug, Default)]
+pub struct NativeBackend {
+	exports: HashMap<String, Export>,
+	// Packed and batch shims, by export name
+	packed: HashMap<String, PackedExport>,
+	direct: HashMap<String, DirectExport>,
+	manifest: Option<Manifest>,
+}
+
+impl NativeBackend {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	pub fn export(mut self, name: impl Into<String>, func: Export) -> Self {
+		self.exports.insert(name.into(), func);
+		self
+	}
+
+	/// Registers the packed shim of `name`, e.g. `__wasmedge_bindgen_packed_say`.
+	/// It is only called when the manifest flags `name` as packed.
+	pub fn export_packed(mut self, name: &str, func: PackedExport) -> Self {
+		self.packed.insert(packed::export_name(name), func);
+		self
+	}
+
+	/// Registers the batch shim of `name`, e.g. `__wasmedge_bindgen_batch_say`.
+	/// It is only called when the manifest flags `name` as batch.
+	pub fn export_batch(mut self, name: &str, func: PackedExport) -> Self {
+		self.packed.insert(batch::export_name(name), func);
+		self
+	}
+
+	/// Registers an adapter for the direct shim of `name`, e.g.
+	/// `|args| vec![Val::I32(__wasmedge_bindgen_direct_add(args[0].to_i32().unwrap(), args[1].to_i32().unwrap()))]`.
+	/// It is only called when the manifest flags `name` as direct.
+	pub fn export_direct(mut self, name: &str, func: DirectExport) -> Self {
+		self.direct.insert(direct::export_name(name), func);
+		self
+	}
+
+	/// Sets the manifest, e.g. decoded from the `__wasmedge_bindgen_manifest_*`
+	/// records of the guest, which a module would carry in its custom section.
+	pub fn with_manifest(mut self, manifest: Manifest) -> Self {
+		self.manifest = Some(manifest);
+		self
+	}
+}
+
+fn args_i32<const N: usize>(name: &str, args: &[Val]) -> Result<[i32; N], Error> {
+	let values: Option<Vec<i32>> = args.iter().map(Val::to_i32).collect();
+	values.and_then(|v| v.try_into().ok())
+		.ok_or_else(|| Error::Runtime(format!("{} expects {} i32 arguments, got {:?}", name, N, args).into()))
+}
+
+impl Backend for NativeBackend {
+	fn call(&mut self, name: &str, args: &[Val]) -> Result<Vec<Val>, Error> {
+		match name {
+			"allocate" => {
+				let [size] = args_i32(name, args)?;
+				Ok(vec![Val::I32(guest::allocate(size as u32) as i32)])
+			}
+			"deallocate" => {
+				let [pointer, size] = args_i32(name, args)?;
+				unsafe { guest::deallocate(pointer as u32, size as u32) };
+				Ok(vec![])
+			}
+			_ if self.packed.contains_key(name) => {
+				let [pointer, len] = args_i32(name, args)?;
+				let ret = unsafe { self.packed[name](pointer as u32, len as u32) };
+				Ok(vec![Val::I64(ret as i64)])
+			}
+			_ if self.direct.contains_key(name) => Ok(self.direct[name](args)),
+			_ => {
+				let func = self.exports.get(name).ok_or_else(|| Error::MissingFunction(name.to_string()))?;
+				let [pointer, count] = args_i32(name, args)?;
+				let ret = unsafe { func(pointer as u32 as usize as *mut u32, count) };
+				Ok(vec![Val::I32(ret)])
+			}
+		}
+	}
+
+	fn read(&self, memory: &str, offset: u32, len: u32) -> Result<Vec<u8>, Error> {
+		self.memory_size(memory)?;
+		linear::read(offset, len).ok_or(Error::MemoryAccess { offset, len })
+	}
+
+	fn write(&mut self, memory: &str, offset: u32, data: &[u8]) -> Result<(), Error> {
+		self.memory_size(memory)?;
+		match linear::write(offset, data) {
+			true => Ok(()),
+			false => Err(Error::MemoryAccess { offset, len: data.len() as u32 }),
+		}
+	}
+
+	fn memory_size(&self, memory: &str) -> Result<u64, Error> {
+		match memory {
+			"memory" => Ok(linear::size()),
+			_ => Err(Error::MissingMemory(memory.to_string())),
+		}
+	}
+
+	fn has_function(&self, name: &str) -> bool {
+		matches!(name, "allocate" | "deallocate") || self.exports.contains_key(name) || self.packed.contains_key(name) || self.direct.contains_key(name)
+	}
+
+	fn signature(&self, name: &str) -> Option<(Vec<ValType>, Vec<ValType>)> {
+		match name {
+			"allocate" => Some((vec![ValType::I32], vec![ValType::I32])),
+			"deallocate" => Some((vec![ValType::I32, ValType::I32], vec![])),
+			_ if self.packed.contains_key(name) => Some((vec![ValType::I32, ValType::I32], vec![ValType::I64])),
+			// Depends on the function
+			_ if self.direct.contains_key(name) => None,
+			_ if self.exports.contains_key(name) => Some((vec![ValType::I32, ValType::I32], vec![ValType::I32])),
+			_ => None,
+		}
+	}
+
+	fn manifest(&self) -> Option<Manifest> {
+		self.manifest.clone()
+	}
+
+	// The simulated memory is shared by the whole process and its allocator
+	// state lives outside of it, so it can't be restored from a copy
+	fn globals(&self) -> Result<Vec<(String, Val)>, Error> {
+		Err(Error::Unsupported(String::from("snapshots of the simulated linear memory")))
+	}
+}
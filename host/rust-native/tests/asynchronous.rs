@@ -0,0 +1,182 @@
+use std::future::Future;
+use std::pin::pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::task::{Context, Poll, Wake};
+use std::thread::{self, Thread};
+use std::time::Duration;
+
+use wasmedge_bindgen_host_native::*;
+use wasmedge_bindgen_macro::*;
+
+#[wasmedge_bindgen]
+pub fn slow_square(ms: u64, x: i64) -> i64 {
+	thread::sleep(Duration::from_millis(ms));
+	x * x
+}
+
+/// Held closed by a test while calls wait on it. Waiting gives up after a
+/// while, so that a broken scheduler fails the test instead of hanging it.
+struct Gate {
+	open: Mutex<bool>,
+	opened: Condvar,
+	passed: AtomicU64,
+}
+
+impl Gate {
+	const fn new() -> Self {
+		Gate { open: Mutex::new(false), opened: Condvar::new(), passed: AtomicU64::new(0) }
+	}
+
+	fn wait(&self) {
+		let open = self.open.lock().unwrap();
+		drop(self.opened.wait_timeout_while(open, Duration::from_secs(10), |open| !*open).unwrap());
+		self.passed.fetch_add(1, Ordering::SeqCst);
+	}
+
+	fn open(&self) {
+		*self.open.lock().unwrap() = true;
+		self.opened.notify_all();
+	}
+
+	fn passed(&self) -> u64 {
+		self.passed.load(Ordering::SeqCst)
+	}
+}
+
+static GATES: [Gate; 2] = [Gate::new(), Gate::new()];
+
+/// Waits for the test to open gate `gate`.
+#[wasmedge_bindgen]
+pub fn wait(gate: u64) -> u64 {
+	GATES[gate as usize].wait();
+	gate
+}
+
+static CALLED: AtomicU64 = AtomicU64::new(0);
+
+#[wasmedge_bindgen]
+pub fn count() -> u64 {
+	CALLED.fetch_add(1, Ordering::SeqCst) + 1
+}
+
+static MEETING: (Mutex<u64>, Condvar) = (Mutex::new(0), Condvar::new());
+
+/// Returns whether `n` calls were running together, giving up after a while.
+#[wasmedge_bindgen]
+pub fn meet(n: u64) -> bool {
+	let (arrived, all) = &MEETING;
+	let mut arrived = arrived.lock().unwrap();
+	*arrived += 1;
+	all.notify_all();
+	let (arrived, _) = all.wait_timeout_while(arrived, Duration::from_secs(10), |arrived| *arrived < n).unwrap();
+	*arrived >= n
+}
+
+struct Unpark(Thread);
+
+impl Wake for Unpark {
+	fn wake(self: Arc<Self>) {
+		self.0.unpark();
+	}
+}
+
+fn block_on<F: Future>(future: F) -> F::Output {
+	let waker = Arc::new(Unpark(thread::current())).into();
+	let mut cx = Context::from_waker(&waker);
+	let mut future = pin!(future);
+	loop {
+		match future.as_mut().poll(&mut cx) {
+			Poll::Ready(output) => return output,
+			Poll::Pending => thread::park(),
+		}
+	}
+}
+
+fn instance() -> Bindgen {
+	let backend = NativeBackend::new().export("slow_square", slow_square).export("wait", wait).export("count", count).export("meet", meet);
+	Bindgen::new(backend)
+}
+
+fn bindgen() -> AsyncBindgen<NativeBackend> {
+	instance().into_async()
+}
+
+#[test]
+fn resolves_with_results() {
+	let bg = bindgen();
+	let mut rv = block_on(bg.run_wasm_async("slow_square", params![10u64, -12i64])).unwrap().unwrap();
+	assert_eq!(*rv.pop().unwrap().downcast::<i64>().unwrap(), 144);
+}
+
+#[test]
+fn calls_queue_on_one_instance() {
+	let bg = bindgen();
+	let calls: Vec<_> = (0..4i64).map(|x| bg.run_wasm_async("slow_square", params![5u64, x])).collect();
+	for (x, call) in calls.into_iter().enumerate() {
+		let mut rv = block_on(call).unwrap().unwrap();
+		assert_eq!(*rv.pop().unwrap().downcast::<i64>().unwrap(), (x * x) as i64);
+	}
+}
+
+#[test]
+fn dropped_calls_are_cancelled() {
+	let bg = bindgen();
+	// Holds the instance while a call is queued behind it and dropped
+	let running = bg.run_wasm_async("wait", params![0u64]);
+	drop(bg.run_wasm_async("count", vec![]));
+	GATES[0].open();
+	block_on(running).unwrap().unwrap();
+
+	// Queued after the dropped call, so it would have run by now
+	let mut rv = block_on(bg.run_wasm_async("slow_square", params![0u64, 3i64])).unwrap().unwrap();
+	assert_eq!(*rv.pop().unwrap().downcast::<i64>().unwrap(), 9);
+	assert_eq!(CALLED.load(Ordering::SeqCst), 0);
+}
+
+#[test]
+fn errors_are_returned() {
+	let rv = block_on(bindgen().run_wasm_async("missing", vec![]));
+	assert!(matches!(rv, Err(Error::MissingFunction(_))));
+}
+
+#[test]
+fn pooled_instances_run_calls_in_parallel() {
+	let bg = AsyncBindgen::pooled((0..4).map(|_| instance()));
+	let calls: Vec<_> = (0..4).map(|_| bg.run_wasm_async("meet", params![4u64])).collect();
+	for call in calls {
+		let mut rv = block_on(call).unwrap().unwrap();
+		assert!(*rv.pop().unwrap().downcast::<bool>().unwrap());
+	}
+}
+
+#[test]
+fn busy_instances_dont_hold_up_others() {
+	// Queued on an instance held by a call, without taking up blocking threads
+	let busy = bindgen();
+	let queued: Vec<_> = (0..16).map(|_| busy.run_wasm_async("wait", params![1u64])).collect();
+
+	let mut rv = block_on(bindgen().run_wasm_async("slow_square", params![0u64, 5i64])).unwrap().unwrap();
+	assert_eq!(*rv.pop().unwrap().downcast::<i64>().unwrap(), 25);
+	assert_eq!(GATES[1].passed(), 0);
+
+	GATES[1].open();
+	for call in queued {
+		block_on(call).unwrap().unwrap();
+	}
+	assert_eq!(GATES[1].passed(), 16);
+}
+
+#[test]
+fn locked_instances_leave_the_pool() {
+	let bg = AsyncBindgen::pooled([instance(), instance()]);
+	let mut locked = bg.lock();
+	let mut rv = locked.run_wasm("slow_square", params![0u64, 6i64]).unwrap().unwrap();
+	assert_eq!(*rv.pop().unwrap().downcast::<i64>().unwrap(), 36);
+
+	// Served by the other instance meanwhile
+	let mut rv = block_on(bg.run_wasm_async("slow_square", params![0u64, 7i64])).unwrap().unwrap();
+	assert_eq!(*rv.pop().unwrap().downcast::<i64>().unwrap(), 49);
+	drop(locked);
+	assert_eq!(bg.instance_count(), 2);
+}
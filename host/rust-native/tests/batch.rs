@@ -0,0 +1,116 @@
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex};
+
+use wasmedge_bindgen_host_native::*;
+use wasmedge_bindgen_host_native::record::read_recording;
+use wasmedge_bindgen_macro::*;
+
+#[wasmedge_bindgen(batch)]
+pub fn divide(a: i64, b: i64) -> Result<i64, String> {
+	a.checked_div(b).ok_or_else(|| format!("can't divide {} by {}", a, b))
+}
+
+#[wasmedge_bindgen(batch)]
+pub fn greet(name: String, times: u8) -> (String, u32) {
+	(format!("hello {}", name).repeat(times as usize), times as u32 * 2)
+}
+
+#[wasmedge_bindgen]
+pub fn square(v: i32) -> i32 {
+	v * v
+}
+
+fn bindgen() -> Bindgen {
+	let records = [&__wasmedge_bindgen_manifest_divide[..], &__wasmedge_bindgen_manifest_greet[..], &__wasmedge_bindgen_manifest_square[..]];
+	Bindgen::new(NativeBackend::new()
+		.export("divide", divide)
+		.export_packed("divide", __wasmedge_bindgen_packed_divide)
+		.export_batch("divide", __wasmedge_bindgen_batch_divide)
+		.export("greet", greet)
+		.export_packed("greet", __wasmedge_bindgen_packed_greet)
+		.export_batch("greet", __wasmedge_bindgen_batch_greet)
+		.export("square", square)
+		.export_packed("square", __wasmedge_bindgen_packed_square)
+		.export_direct("square", |args| match args {
+			[Val::I32(v)] => vec![Val::I32(__wasmedge_bindgen_direct_square(*v))],
+			_ => vec![],
+		})
+		.with_manifest(Manifest::decode(&records.concat()).unwrap()))
+}
+
+#[test]
+fn flagged_in_manifest() {
+	let bg = bindgen();
+	let manifest = bg.manifest().unwrap();
+	assert!(manifest.function("divide").unwrap().batch);
+	assert!(!manifest.function("square").unwrap().batch);
+}
+
+#[test]
+fn one_guest_entry() {
+	let mut bg = bindgen();
+	let inputs = (1..=1000).map(|i| params![i as i64 * 6, 3i64]).collect();
+	let results = bg.call_batch("divide", inputs).unwrap();
+	assert_eq!(results.len(), 1000);
+	for (i, result) in results.into_iter().enumerate() {
+		assert_eq!(*result.unwrap()[0].downcast_ref::<i64>().unwrap(), (i as i64 + 1) * 2);
+	}
+	let stats = bg.last_call_stats();
+	assert_eq!((stats.allocate_calls, stats.deallocate_calls), (1, 1));
+
+	let mut results = bg.call_batch("greet", vec![params!["a", 2u8], params!["b", 0u8]]).unwrap().into_iter();
+	let first = results.next().unwrap().unwrap();
+	assert_eq!(*first[0].downcast_ref::<String>().unwrap(), "hello ahello a");
+	assert_eq!(*first[1].downcast_ref::<u32>().unwrap(), 4);
+	assert_eq!(*results.next().unwrap().unwrap()[0].downcast_ref::<String>().unwrap(), "");
+
+	assert!(bg.call_batch("divide", vec![]).unwrap().is_empty());
+}
+
+#[test]
+fn per_call_errors() {
+	let mut bg = bindgen();
+	let results = bg.call_batch("divide", vec![params![4i64, 2i64], params![1i64, 0i64], params![1i64], params![1i64, 2i32]]).unwrap();
+	assert_eq!(*results[0].as_ref().unwrap()[0].downcast_ref::<i64>().unwrap(), 2);
+	assert_eq!(results[1].as_ref().unwrap_err(), "can't divide 1 by 0");
+	assert_eq!(results[2].as_ref().unwrap_err(), "Invalid params count, expect 2, got 1");
+	assert_eq!(results[3].as_ref().unwrap_err(), "Parameter 1 is I32, expect I64");
+	assert!(bg.last_call_stats().failed);
+}
+
+#[test]
+fn without_batch_export() {
+	let mut bg = bindgen();
+	let results = bg.call_batch("square", vec![params![3], params![4]]).unwrap();
+	let squares: Vec<i32> = results.into_iter().map(|r| *r.unwrap()[0].downcast_ref::<i32>().unwrap()).collect();
+	assert_eq!(squares, [9, 16]);
+}
+
+#[derive(Clone, Default)]
+struct Shared(Arc<Mutex<Vec<u8>>>);
+
+impl Write for Shared {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		self.0.lock().unwrap().write(buf)
+	}
+
+	fn flush(&mut self) -> io::Result<()> {
+		Ok(())
+	}
+}
+
+#[test]
+fn recorded_per_call() {
+	let out = Shared::default();
+	let mut bg = bindgen();
+	bg.record_to(out.clone()).unwrap();
+	bg.call_batch("divide", vec![params![4i64, 2i64], params![1i64, 0i64]]).unwrap();
+	bg.stop_recording();
+
+	let bytes = out.0.lock().unwrap().clone();
+	let calls = read_recording(&bytes[..]).unwrap();
+	assert_eq!(calls.len(), 2);
+	assert_eq!(calls[1].params, vec![Encoded { ty: WireType::I64, bytes: 1i64.to_le_bytes().to_vec() }, Encoded { ty: WireType::I64, bytes: vec![0; 8] }]);
+	assert_eq!(calls[1].outcome, Outcome::GuestError(String::from("can't divide 1 by 0")));
+	assert!(bg.replay(&calls).iter().all(|r| r.is_match()));
+}
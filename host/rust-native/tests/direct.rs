@@ -0,0 +1,100 @@
+use wasmedge_bindgen_host_native::*;
+use wasmedge_bindgen_macro::*;
+
+#[wasmedge_bindgen(direct)]
+pub fn lowest_common_multiple(a: i32, b: i32) -> i32 {
+	let (mut x, mut y) = (a, b);
+	while y != 0 {
+		(x, y) = (y, x % y);
+	}
+	a / x * b
+}
+
+#[wasmedge_bindgen]
+pub fn halve(v: u8) -> f64 {
+	v as f64 / 2.0
+}
+
+#[wasmedge_bindgen]
+pub fn shout(s: String) -> String {
+	s.to_uppercase()
+}
+
+// Not direct: Rust can't export the multi-value function it would take
+#[wasmedge_bindgen]
+pub fn divmod(a: i32, b: i32) -> (i32, i32) {
+	(a / b, a % b)
+}
+
+fn bindgen() -> Bindgen {
+	let records = [&__wasmedge_bindgen_manifest_lowest_common_multiple[..], &__wasmedge_bindgen_manifest_halve[..], &__wasmedge_bindgen_manifest_shout[..], &__wasmedge_bindgen_manifest_divmod[..]];
+	Bindgen::new(NativeBackend::new()
+		.export("lowest_common_multiple", lowest_common_multiple)
+		.export_packed("lowest_common_multiple", __wasmedge_bindgen_packed_lowest_common_multiple)
+		.export_direct("lowest_common_multiple", |args| match args {
+			[Val::I32(a), Val::I32(b)] => vec![Val::I32(__wasmedge_bindgen_direct_lowest_common_multiple(*a, *b))],
+			_ => vec![],
+		})
+		.export("halve", halve)
+		.export_packed("halve", __wasmedge_bindgen_packed_halve)
+		.export_direct("halve", |args| match args {
+			[Val::I32(v)] => vec![Val::F64(__wasmedge_bindgen_direct_halve(*v as u8))],
+			_ => vec![],
+		})
+		.export("shout", shout)
+		.export_packed("shout", __wasmedge_bindgen_packed_shout)
+		.export("divmod", divmod)
+		.export_packed("divmod", __wasmedge_bindgen_packed_divmod)
+		.with_manifest(Manifest::decode(&records.concat()).unwrap()))
+}
+
+#[test]
+fn flagged_in_manifest() {
+	let bg = bindgen();
+	let manifest = bg.manifest().unwrap();
+	assert!(manifest.function("lowest_common_multiple").unwrap().direct);
+	assert!(manifest.function("halve").unwrap().direct);
+	assert!(!manifest.function("shout").unwrap().direct);
+	assert!(!manifest.function("divmod").unwrap().direct);
+}
+
+#[test]
+fn no_linear_memory() {
+	let mut bg = bindgen();
+	let rv = bg.run_wasm("lowest_common_multiple", params![4, 6]).unwrap().unwrap();
+	assert_eq!(*rv[0].downcast_ref::<i32>().unwrap(), 12);
+	let stats = bg.last_call_stats();
+	assert_eq!((stats.allocate_calls, stats.deallocate_calls), (0, 0));
+	// Counted like the values other calls copy in and out
+	assert_eq!((stats.bytes_in, stats.bytes_out), (8, 4));
+
+	let rv = bg.run_wasm("halve", params![7u8]).unwrap().unwrap();
+	assert_eq!(*rv[0].downcast_ref::<f64>().unwrap(), 3.5);
+	assert_eq!(bg.last_call_stats().allocate_calls, 0);
+
+	let rv = bg.run_wasm("shout", params!["hey"]).unwrap().unwrap();
+	assert_eq!(*rv[0].downcast_ref::<String>().unwrap(), "HEY");
+	assert_eq!(bg.last_call_stats().allocate_calls, 1);
+
+	let rv = bg.run_wasm("divmod", params![17, 5]).unwrap().unwrap();
+	assert_eq!((*rv[0].downcast_ref::<i32>().unwrap(), *rv[1].downcast_ref::<i32>().unwrap()), (3, 2));
+	assert_eq!(bg.last_call_stats().allocate_calls, 1);
+}
+
+#[test]
+fn mismatched_params_fall_back() {
+	let mut bg = bindgen();
+	assert_eq!(bg.run_wasm("lowest_common_multiple", params![4, 6u8]).unwrap().unwrap_err(), "Parameter 1 is U8, expect I32");
+	assert_eq!(bg.run_wasm("lowest_common_multiple", params![4]).unwrap().unwrap_err(), "Invalid params count, expect 2, got 1");
+}
+
+#[test]
+fn prepared() {
+	let mut bg = bindgen();
+	let lcm = bg.prepare("lowest_common_multiple").unwrap();
+	for (a, b, m) in [(3, 5, 15), (12, 18, 36)] {
+		let rv = bg.run_prepared(&lcm, params![a, b]).unwrap().unwrap();
+		assert_eq!(*rv[0].downcast_ref::<i32>().unwrap(), m);
+	}
+	assert_eq!(bg.last_call_stats().allocate_calls, 0);
+}
@@ -0,0 +1,197 @@
+use std::sync::{Arc, Mutex};
+
+use wasmedge_bindgen_abi::{linear, ParamEntry, ReturnHeader, HEADER_SIZE, PARAM_ENTRY_SIZE};
+use wasmedge_bindgen_host_native::*;
+use wasmedge_bindgen_macro::*;
+
+#[wasmedge_bindgen]
+pub fn say(s: String) -> Result<(u16, String), String> {
+	Ok((5, String::from("hello ") + s.as_str()))
+}
+
+#[wasmedge_bindgen]
+pub fn lowest_common_multiple(a: i32, b: i32) -> i32 {
+	let (mut x, mut y) = (a, b);
+	while y != 0 {
+		(x, y) = (y, x % y);
+	}
+	a / x * b
+}
+
+#[wasmedge_bindgen]
+pub fn reverse(v: Vec<u8>) -> Vec<u8> {
+	v.into_iter().rev().collect()
+}
+
+#[wasmedge_bindgen]
+pub fn scalars(a: i8, b: u16, c: i64, d: f32, e: f64, f: bool, g: char) -> (i8, u16, i64, f32, f64, bool, char) {
+	(a, b, c, d, e, f, g)
+}
+
+#[wasmedge_bindgen]
+pub fn arrays(a: Vec<i16>, b: Vec<u32>, c: Vec<i64>) -> (Vec<i16>, Vec<u32>, Vec<i64>, u64) {
+	let count = a.len() + b.len() + c.len();
+	(a, b, c, count as u64)
+}
+
+#[wasmedge_bindgen]
+pub fn fail(s: String) -> Result<String, String> {
+	Err(format!("failed on {}", s))
+}
+
+#[wasmedge_bindgen]
+pub fn nothing() {}
+
+fn bindgen() -> Bindgen {
+	Bindgen::new(NativeBackend::new()
+		.export("say", say)
+		.export("lowest_common_multiple", lowest_common_multiple)
+		.export("reverse", reverse)
+		.export("scalars", scalars)
+		.export("arrays", arrays)
+		.export("fail", fail)
+		.export("nothing", nothing))
+}
+
+#[test]
+fn string_and_tuple() {
+	let mut rv = bindgen().run_wasm("say", params!["bindgen funcs test"]).unwrap().unwrap();
+	assert_eq!(*rv.pop().unwrap().downcast::<String>().unwrap(), "hello bindgen funcs test");
+	assert_eq!(*rv.pop().unwrap().downcast::<u16>().unwrap(), 5);
+}
+
+#[test]
+fn integers() {
+	let mut rv = bindgen().run_wasm("lowest_common_multiple", params![123, 2]).unwrap().unwrap();
+	assert_eq!(*rv.pop().unwrap().downcast::<i32>().unwrap(), 246);
+}
+
+#[test]
+fn bytes() {
+	let bytes = b"This is an important message".to_vec();
+	let mut rv = bindgen().run_wasm("reverse", params![&bytes[..]]).unwrap().unwrap();
+	let mut expected = bytes.clone();
+	expected.reverse();
+	assert_eq!(*rv.pop().unwrap().downcast::<Vec<u8>>().unwrap(), expected);
+}
+
+#[test]
+fn every_scalar() {
+	let rv = bindgen().run_wasm("scalars", params![-3i8, 60000u16, i64::MIN, 1.5f32, -2.25f64, true, '🦀']).unwrap().unwrap();
+	let mut rv = rv.into_iter();
+	assert_eq!(*rv.next().unwrap().downcast::<i8>().unwrap(), -3);
+	assert_eq!(*rv.next().unwrap().downcast::<u16>().unwrap(), 60000);
+	assert_eq!(*rv.next().unwrap().downcast::<i64>().unwrap(), i64::MIN);
+	assert_eq!(*rv.next().unwrap().downcast::<f32>().unwrap(), 1.5);
+	assert_eq!(*rv.next().unwrap().downcast::<f64>().unwrap(), -2.25);
+	assert!(*rv.next().unwrap().downcast::<bool>().unwrap());
+	assert_eq!(*rv.next().unwrap().downcast::<char>().unwrap(), '🦀');
+}
+
+#[test]
+fn every_array() {
+	let a = vec![i16::MIN, 0, i16::MAX];
+	let b = vec![7u32; 1000];
+	let rv = bindgen().run_wasm("arrays", params![&a, &b, Vec::<i64>::new()]).unwrap().unwrap();
+	let mut rv = rv.into_iter();
+	assert_eq!(*rv.next().unwrap().downcast::<Vec<i16>>().unwrap(), a);
+	assert_eq!(*rv.next().unwrap().downcast::<Vec<u32>>().unwrap(), b);
+	assert_eq!(*rv.next().unwrap().downcast::<Vec<i64>>().unwrap(), Vec::<i64>::new());
+	assert_eq!(*rv.next().unwrap().downcast::<u64>().unwrap(), 1003);
+}
+
+#[test]
+fn guest_error() {
+	let rv = bindgen().run_wasm("fail", params!["purpose"]).unwrap();
+	assert_eq!(rv.unwrap_err(), "failed on purpose");
+}
+
+#[test]
+fn no_params_no_returns() {
+	let rv = bindgen().run_wasm("nothing", vec![]).unwrap().unwrap();
+	assert!(rv.is_empty());
+}
+
+#[test]
+fn wrong_params_count() {
+	let rv = bindgen().run_wasm("lowest_common_multiple", params![1]).unwrap();
+	assert!(rv.unwrap_err().contains("params count"));
+}
+
+#[test]
+fn param_out_of_memory() {
+	let entry = ParamEntry { pointer: u32::MAX - 4, length: 16 };
+	let frame = linear::allocate(PARAM_ENTRY_SIZE as u32);
+	linear::write(frame, &ParamEntry::encode_all(&[entry]));
+
+	let header = unsafe { reverse(frame as usize as *mut u32, 1) } as u32;
+	let header = ReturnHeader::decode(&linear::read(header, HEADER_SIZE as u32).unwrap()).unwrap();
+	assert!(header.is_error);
+	let message = String::from_utf8(linear::read(header.pointer, header.len).unwrap()).unwrap();
+	assert!(message.contains("out of the linear memory"));
+}
+
+#[test]
+fn missing_function() {
+	assert!(matches!(bindgen().run_wasm("missing", vec![]), Err(Error::MissingFunction(_))));
+	let config = BindgenConfig::malloc();
+	assert!(matches!(Bindgen::with_config(NativeBackend::new(), config), Err(Error::MissingFunction(_))));
+}
+
+#[test]
+fn allocator_signatures() {
+	let config = BindgenConfig::new().deallocator("deallocate", DeallocSignature::PtrSizeAlign);
+	assert!(matches!(Bindgen::with_config(NativeBackend::new(), config), Err(Error::InvalidConfig(_))));
+	let config = BindgenConfig::new().allocator("allocate", AllocSignature::SizeAlign);
+	assert!(matches!(Bindgen::with_config(NativeBackend::new(), config), Err(Error::InvalidConfig(_))));
+	assert!(Bindgen::with_config(NativeBackend::new(), BindgenConfig::new()).is_ok());
+}
+
+#[test]
+fn no_snapshots() {
+	assert!(matches!(bindgen().snapshot(), Err(Error::Unsupported(_))));
+}
+
+#[derive(Default)]
+struct Recorder(Mutex<Vec<(String, CallStats)>>);
+
+impl Metrics for Recorder {
+	fn record(&self, func_name: &str, stats: &CallStats) {
+		self.0.lock().unwrap().push((func_name.to_string(), stats.clone()));
+	}
+}
+
+#[test]
+fn call_stats() {
+	let recorder = Arc::new(Recorder::default());
+	let mut bg = bindgen();
+	bg.set_metrics(recorder.clone());
+
+	bg.run_wasm("say", params!["stats"]).unwrap().unwrap();
+	let stats = bg.last_call_stats().clone();
+	// frame and string in, header, 2 entries, u16 and string out
+	assert_eq!(stats.bytes_in, 8 + 5);
+	assert_eq!(stats.bytes_out, 9 + 2 * 12 + 2 + 11);
+	assert_eq!(stats.allocate_calls, 2);
+	assert_eq!(stats.deallocate_calls, 4);
+	assert!(!stats.failed);
+
+	bg.run_wasm("fail", params!["purpose"]).unwrap().unwrap_err();
+	assert!(bg.last_call_stats().failed);
+
+	let recorded = recorder.0.lock().unwrap();
+	assert_eq!(recorded.len(), 2);
+	assert_eq!(recorded[0], (String::from("say"), stats));
+	assert_eq!(recorded[1].0, "fail");
+}
+
+#[test]
+fn manifest_records() {
+	let say = Manifest::decode(&__wasmedge_bindgen_manifest_say).unwrap();
+	assert_eq!(say.functions, [Signature { name: String::from("say"), params: vec![WireType::String], results: vec![WireType::U16, WireType::String], fallible: true, packed: true, direct: false, batch: false }]);
+
+	let arrays = Manifest::decode(&__wasmedge_bindgen_manifest_arrays).unwrap();
+	assert_eq!(arrays.functions[0].params, [WireType::I16Array, WireType::U32Array, WireType::I64Array]);
+	assert_eq!(arrays.functions[0].results, [WireType::I16Array, WireType::U32Array, WireType::I64Array, WireType::U64]);
+	assert!(!arrays.functions[0].fallible);
+}
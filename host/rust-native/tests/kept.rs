@@ -0,0 +1,58 @@
+use wasmedge_bindgen_host_native::*;
+use wasmedge_bindgen_macro::*;
+
+#[wasmedge_bindgen]
+pub fn decode(s: String) -> Result<Vec<u8>, String> {
+	s.split(',').map(|n| n.trim().parse::<u8>().map_err(|e| e.to_string())).collect()
+}
+
+#[wasmedge_bindgen]
+pub fn double(v: Vec<u8>) -> (Vec<u8>, u32) {
+	(v.iter().map(|b| b.wrapping_mul(2)).collect(), v.len() as u32)
+}
+
+#[wasmedge_bindgen]
+pub fn encode(v: Vec<u8>) -> String {
+	v.iter().map(|b| b.to_string()).collect::<Vec<_>>().join(",")
+}
+
+fn bindgen() -> Bindgen {
+	Bindgen::new(NativeBackend::new().export("decode", decode).export("double", double).export("encode", encode))
+}
+
+#[test]
+fn pipeline() {
+	let mut bg = bindgen();
+	let mut decoded = bg.run_wasm_kept("decode", params!["1, 2, 3"]).unwrap().unwrap();
+	assert_eq!((decoded[0].ty(), decoded[0].len()), (WireType::U8Array, 3));
+
+	let mut doubled = bg.run_wasm_kept("double", vec![decoded.pop().unwrap().into()]).unwrap().unwrap();
+	assert_eq!(&bg.view_value(&doubled[0]).unwrap()[..], [2, 4, 6]);
+	assert_eq!(*bg.take_value(doubled.pop().unwrap()).unwrap().downcast::<u32>().unwrap(), 3);
+
+	let mut rv = bg.run_wasm("encode", vec![doubled.pop().unwrap().into()]).unwrap().unwrap();
+	assert_eq!(*rv.pop().unwrap().downcast::<String>().unwrap(), "2,4,6");
+	// The values were handed over as they were, not copied back in
+	assert_eq!(bg.last_call_stats().bytes_in, 8);
+
+	assert_eq!(bg.run_wasm_kept("decode", params!["1, x"]).unwrap().unwrap_err(), "invalid digit found in string");
+}
+
+#[test]
+fn dropped_values_are_freed_by_the_next_call() {
+	let mut bg = bindgen();
+	drop(bg.run_wasm_kept("decode", params!["1, 2"]).unwrap().unwrap());
+	bg.run_wasm("encode", params![&[1u8][..]]).unwrap().unwrap();
+	// The frame, the parameter and the result, then the dropped value
+	assert_eq!(bg.last_call_stats().deallocate_calls, 4);
+}
+
+#[test]
+fn stale_values() {
+	let mut bg = bindgen();
+	let mut other = bindgen();
+	let mut kept = bg.run_wasm_kept("decode", params!["7"]).unwrap().unwrap();
+	assert!(matches!(other.view_value(&kept[0]), Err(Error::StaleValue)));
+	assert!(matches!(other.run_wasm("encode", vec![kept.pop().unwrap().into()]), Err(Error::StaleValue)));
+	assert_eq!(other.last_call_stats().allocate_calls, 0);
+}
@@ -0,0 +1,61 @@
+use wasmedge_bindgen_abi::linear;
+use wasmedge_bindgen_host_native::*;
+use wasmedge_bindgen_macro::*;
+
+#[wasmedge_bindgen]
+pub fn split(s: String) -> Result<(Vec<u8>, String), String> {
+	match s.split_once(' ') {
+		Some((a, b)) => Ok((a.as_bytes().to_vec(), b.to_string())),
+		None => Err(format!("no space in {}", s)),
+	}
+}
+
+#[wasmedge_bindgen]
+pub fn join(a: Vec<u8>, b: String) -> String {
+	String::from_utf8(a).unwrap() + "+" + b.as_str()
+}
+
+// The only test of this binary, so that nothing else allocates meanwhile.
+#[test]
+fn every_buffer_is_freed() {
+	let mut bg = Bindgen::new(NativeBackend::new().export("split", split));
+	let mut router = Router::new();
+	for name in ["a", "b"] {
+		router.add(name, Bindgen::new(NativeBackend::new().export("split", split).export("join", join)));
+	}
+	let before = linear::in_use();
+
+	for _ in 0..100 {
+		assert!(bg.run_wasm("split", params!["hello world"]).unwrap().is_ok());
+		assert!(bg.run_wasm("split", params!["hello"]).unwrap().is_err());
+
+		let results = bg.run_wasm_borrowed("split", params!["hello world"]).unwrap().unwrap();
+		assert_eq!(&results.get(1).unwrap()[..], b"world");
+		results.release().unwrap();
+		// Dropping releases too
+		bg.run_wasm_borrowed("split", params!["hello world"]).unwrap().unwrap();
+
+		let param = bg.alloc_param(WireType::String, 5).unwrap();
+		bg.fill_param(&param, |buf| buf.copy_from_slice(b"a b c")).unwrap();
+		let mut rv = bg.run_wasm("split", vec![param.into()]).unwrap().unwrap();
+		assert_eq!(*rv.pop().unwrap().downcast::<String>().unwrap(), "b c");
+		let unused = bg.alloc_param(WireType::U8Array, 100).unwrap();
+		bg.free_param(unused).unwrap();
+
+		let mut kept = bg.run_wasm_kept("split", params!["a b c"]).unwrap().unwrap();
+		let mut rv = bg.run_wasm("split", vec![kept.pop().unwrap().into()]).unwrap().unwrap();
+		assert_eq!(*rv.pop().unwrap().downcast::<String>().unwrap(), "c");
+		assert_eq!(*bg.take_value(kept.pop().unwrap()).unwrap().downcast::<Vec<u8>>().unwrap(), b"a");
+		// Dropped, freed by the next call
+		bg.run_wasm_kept("split", params!["a b"]).unwrap().unwrap();
+
+		for route in [[("a", "split"), ("b", "join")], [("a", "split"), ("a", "join")]] {
+			let mut rv = router.route(&route, params!["hello world"]).unwrap().unwrap();
+			assert_eq!(*rv.pop().unwrap().downcast::<String>().unwrap(), "hello+world");
+		}
+		assert!(router.route(&[("a", "split"), ("b", "join")], params!["hello"]).unwrap().is_err());
+	}
+	bg.run_wasm("split", params!["last call"]).unwrap().unwrap();
+
+	assert_eq!(linear::in_use(), before);
+}
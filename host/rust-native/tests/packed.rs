@@ -0,0 +1,92 @@
+use wasmedge_bindgen_host_native::*;
+use wasmedge_bindgen_macro::*;
+
+#[wasmedge_bindgen]
+pub fn say(s: String) -> Result<(u16, String), String> {
+	match s.is_empty() {
+		true => Err(String::from("nothing to say")),
+		false => Ok((5, String::from("hello ") + s.as_str())),
+	}
+}
+
+#[wasmedge_bindgen]
+pub fn scale(v: Vec<i32>, by: f64, offset: i64, label: char) -> (Vec<i32>, i64, char, bool) {
+	(v.iter().map(|x| (*x as f64 * by) as i32).collect(), offset + 1, label, v.is_empty())
+}
+
+#[wasmedge_bindgen]
+pub fn nothing() {}
+
+fn bindgen() -> Bindgen {
+	let records = [&__wasmedge_bindgen_manifest_say[..], &__wasmedge_bindgen_manifest_scale[..], &__wasmedge_bindgen_manifest_nothing[..]];
+	Bindgen::new(NativeBackend::new()
+		.export("say", say)
+		.export_packed("say", __wasmedge_bindgen_packed_say)
+		.export("scale", scale)
+		.export_packed("scale", __wasmedge_bindgen_packed_scale)
+		.export("nothing", nothing)
+		.export_packed("nothing", __wasmedge_bindgen_packed_nothing)
+		.export_direct("nothing", |_| {
+			__wasmedge_bindgen_direct_nothing();
+			vec![]
+		})
+		.with_manifest(Manifest::decode(&records.concat()).unwrap()))
+}
+
+#[test]
+fn flagged_in_manifest() {
+	let bg = bindgen();
+	assert!(bg.manifest().unwrap().functions.iter().all(|f| f.packed));
+	assert!(bg.manifest().unwrap().function("nothing").unwrap().direct);
+}
+
+#[test]
+fn constant_crossings() {
+	let mut bg = bindgen();
+	let mut rv = bg.run_wasm("say", params!["packed"]).unwrap().unwrap();
+	assert_eq!(*rv.pop().unwrap().downcast::<String>().unwrap(), "hello packed");
+	assert_eq!(*rv.pop().unwrap().downcast::<u16>().unwrap(), 5);
+
+	// count, then a record of type, size and 6 bytes in; flag, count and 2 records out
+	let stats = bg.last_call_stats();
+	assert_eq!(stats.bytes_in, 4 + 5 + 6);
+	assert_eq!(stats.bytes_out, 5 + 5 + 2 + 5 + 12);
+	assert_eq!((stats.allocate_calls, stats.deallocate_calls), (1, 1));
+
+	let rv = bg.run_wasm("scale", params![&[1, 3][..], 2.5, 41i64, 'x']).unwrap().unwrap();
+	assert_eq!((bg.last_call_stats().allocate_calls, bg.last_call_stats().deallocate_calls), (1, 1));
+	let mut rv = rv.into_iter();
+	assert_eq!(*rv.next().unwrap().downcast::<Vec<i32>>().unwrap(), [2, 7]);
+	assert_eq!(*rv.next().unwrap().downcast::<i64>().unwrap(), 42);
+	assert_eq!(*rv.next().unwrap().downcast::<char>().unwrap(), 'x');
+	assert!(!*rv.next().unwrap().downcast::<bool>().unwrap());
+
+	assert!(bg.run_wasm("nothing", vec![]).unwrap().unwrap().is_empty());
+}
+
+#[test]
+fn errors() {
+	let mut bg = bindgen();
+	assert_eq!(bg.run_wasm("say", params![""]).unwrap().unwrap_err(), "nothing to say");
+	assert_eq!(bg.run_wasm("say", params![]).unwrap().unwrap_err(), "Invalid params count, expect 1, got 0");
+	assert_eq!(bg.run_wasm("say", params![7u8]).unwrap().unwrap_err(), "Parameter 0 is U8, expect String");
+}
+
+#[test]
+fn guest_params_use_the_classic_export() {
+	let mut bg = bindgen();
+	let param = bg.alloc_param(WireType::String, 5).unwrap();
+	bg.fill_param(&param, |buf| buf.copy_from_slice(b"there")).unwrap();
+	let mut rv = bg.run_wasm("say", vec![Param::Guest(param)]).unwrap().unwrap();
+	assert_eq!(*rv.pop().unwrap().downcast::<String>().unwrap(), "hello there");
+	assert_eq!(bg.last_call_stats().deallocate_calls, 4);
+}
+
+#[test]
+fn prepared() {
+	let mut bg = bindgen();
+	let say = bg.prepare("say").unwrap();
+	let mut rv = bg.run_prepared(&say, params!["again"]).unwrap().unwrap();
+	assert_eq!(*rv.pop().unwrap().downcast::<String>().unwrap(), "hello again");
+	assert_eq!(bg.last_call_stats().deallocate_calls, 1);
+}
@@ -0,0 +1,106 @@
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex};
+
+use wasmedge_bindgen_host_native::*;
+use wasmedge_bindgen_host_native::record::read_recording;
+use wasmedge_bindgen_macro::*;
+
+#[wasmedge_bindgen]
+pub fn greet(name: String, times: u8) -> Result<(String, Vec<u16>), String> {
+	match times {
+		0 => Err(String::from("nobody to greet")),
+		_ => Ok((format!("hello {}", name).repeat(times as usize), vec![times as u16; 2])),
+	}
+}
+
+#[wasmedge_bindgen]
+pub fn greet_v2(name: String, times: u8) -> Result<(String, Vec<u16>), String> {
+	Ok((format!("hi {}", name), vec![times as u16; 2]))
+}
+
+#[derive(Clone, Default)]
+struct Shared(Arc<Mutex<Vec<u8>>>);
+
+impl Write for Shared {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		self.0.lock().unwrap().write(buf)
+	}
+
+	fn flush(&mut self) -> io::Result<()> {
+		Ok(())
+	}
+}
+
+fn record() -> Vec<RecordedCall> {
+	let out = Shared::default();
+	let mut bg = Bindgen::new(NativeBackend::new().export("greet", greet));
+	bg.record_to(out.clone()).unwrap();
+
+	bg.run_wasm("greet", params!["you", 2u8]).unwrap().unwrap();
+	bg.run_wasm("greet", params!["nobody", 0u8]).unwrap().unwrap_err();
+	bg.run_wasm("missing", vec![]).unwrap_err();
+	bg.stop_recording();
+	bg.run_wasm("greet", params!["unrecorded", 1u8]).unwrap().unwrap();
+
+	let bytes = out.0.lock().unwrap().clone();
+	read_recording(&bytes[..]).unwrap()
+}
+
+#[test]
+fn records_calls() {
+	let calls = record();
+	assert_eq!(calls.len(), 3);
+
+	assert_eq!(calls[0].func_name, "greet");
+	assert_eq!(calls[0].params, vec![
+		Encoded { ty: WireType::String, bytes: b"you".to_vec() },
+		Encoded { ty: WireType::U8, bytes: vec![2] },
+	]);
+	assert_eq!(calls[0].outcome, Outcome::Ok(vec![
+		Encoded { ty: WireType::String, bytes: b"hello youhello you".to_vec() },
+		Encoded { ty: WireType::U16Array, bytes: vec![2, 0, 2, 0] },
+	]));
+	assert_eq!(calls[1].outcome, Outcome::GuestError(String::from("nobody to greet")));
+	assert!(matches!(calls[2].outcome, Outcome::HostError(_)));
+}
+
+#[test]
+fn replays_against_the_same_module() {
+	let calls = record();
+	let mut bg = Bindgen::new(NativeBackend::new().export("greet", greet));
+	let replayed = bg.replay(&calls);
+	assert_eq!(replayed.len(), 3);
+	assert!(replayed.iter().all(|r| r.is_match()), "{:?}", replayed);
+}
+
+#[test]
+fn replays_against_a_newer_module() {
+	let calls = record();
+	let mut bg = Bindgen::new(NativeBackend::new().export("greet", greet_v2));
+	let replayed = bg.replay(&calls);
+	let mismatches: Vec<_> = replayed.iter().filter(|r| !r.is_match()).collect();
+	assert_eq!(mismatches.len(), 2);
+	assert_eq!(mismatches[0].to_string().split_once(": ").unwrap().1,
+		"expected Ok(\"hello youhello you\", [2, 2]), got Ok(\"hi you\", [2, 2])");
+	assert_eq!(mismatches[1].actual, Outcome::Ok(vec![
+		Encoded { ty: WireType::String, bytes: b"hi nobody".to_vec() },
+		Encoded { ty: WireType::U16Array, bytes: vec![0, 0, 0, 0] },
+	]));
+}
+
+#[test]
+fn rejects_other_files() {
+	assert!(read_recording(&b"not a recording"[..]).is_err());
+	let mut truncated = record_bytes();
+	truncated.truncate(truncated.len() - 1);
+	assert!(read_recording(&truncated[..]).is_err());
+}
+
+fn record_bytes() -> Vec<u8> {
+	let out = Shared::default();
+	let mut bg = Bindgen::new(NativeBackend::new().export("greet", greet));
+	bg.record_to(out.clone()).unwrap();
+	bg.run_wasm("greet", params!["you", 1u8]).unwrap().unwrap();
+	let bytes = out.0.lock().unwrap();
+	bytes.clone()
+}
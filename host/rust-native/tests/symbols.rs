@@ -0,0 +1,23 @@
+use wasmedge_bindgen_host_native::*;
+use wasmedge_bindgen_macro::*;
+
+// Named like libc functions, which the guest's shims must not replace when
+// linked into the host
+#[wasmedge_bindgen]
+pub fn write(s: String) -> String {
+	s.to_uppercase()
+}
+
+#[wasmedge_bindgen]
+pub fn free(v: Vec<u8>) -> u32 {
+	v.len() as u32
+}
+
+#[test]
+fn libc_names() {
+	let mut bg = Bindgen::new(NativeBackend::new().export("write", write).export("free", free));
+	let mut rv = bg.run_wasm("write", params!["loud"]).unwrap().unwrap();
+	assert_eq!(*rv.pop().unwrap().downcast::<String>().unwrap(), "LOUD");
+	let mut rv = bg.run_wasm("free", params![vec![1u8, 2, 3]]).unwrap().unwrap();
+	assert_eq!(*rv.pop().unwrap().downcast::<u32>().unwrap(), 3);
+}
@@ -0,0 +1,82 @@
+use serde_json::json;
+use wasmedge_bindgen_host_native::*;
+use wasmedge_bindgen_macro::*;
+
+#[wasmedge_bindgen]
+pub fn describe(name: String, scores: Vec<u64>, initial: char) -> Result<(String, f32, Vec<i16>), String> {
+	if scores.is_empty() {
+		return Err(format!("no scores for {}", name));
+	}
+	let average = scores.iter().sum::<u64>() as f32 / scores.len() as f32;
+	Ok((format!("{}. {}", initial, name), average, vec![-1, i16::MAX]))
+}
+
+fn bindgen() -> Bindgen {
+	Bindgen::new(NativeBackend::new().export("describe", describe))
+}
+
+#[test]
+fn run_with_values() {
+	let inputs = vec![Value::String(String::from("Ada")), Value::VecU64(vec![3, 4]), Value::Char('A')];
+	let rv = bindgen().run_wasm_values("describe", inputs).unwrap().unwrap();
+	assert_eq!(rv, [Value::String(String::from("A. Ada")), Value::F32(3.5), Value::VecI16(vec![-1, i16::MAX])]);
+	assert_eq!(rv[1].wire_type(), WireType::F32);
+
+	let inputs = vec![Value::String(String::from("Ada")), Value::VecU64(vec![]), Value::Char('A')];
+	assert_eq!(bindgen().run_wasm_values("describe", inputs).unwrap().unwrap_err(), "no scores for Ada");
+}
+
+#[test]
+fn run_with_json() {
+	let inputs = json!([{"string": "Ada"}, {"u64_array": [3, 4]}, {"char": "A"}]);
+	let inputs: Vec<Value> = inputs.as_array().unwrap().iter().map(|v| Value::try_from(v).unwrap()).collect();
+	let rv = bindgen().run_wasm_values("describe", inputs).unwrap().unwrap();
+	let rv: Vec<serde_json::Value> = rv.into_iter().map(serde_json::Value::from).collect();
+	assert_eq!(serde_json::Value::Array(rv), json!([{"string": "A. Ada"}, {"f32": 3.5}, {"i16_array": [-1, 32767]}]));
+}
+
+#[test]
+fn json_round_trip() {
+	let values = [
+		Value::I8(i8::MIN),
+		Value::U8(u8::MAX),
+		Value::I16(i16::MIN),
+		Value::U16(u16::MAX),
+		Value::I32(i32::MIN),
+		Value::U32(u32::MAX),
+		Value::I64(i64::MIN),
+		Value::U64(u64::MAX),
+		Value::F32(0.1),
+		Value::F32(f32::INFINITY),
+		Value::F64(-0.0),
+		Value::F64(f64::NEG_INFINITY),
+		Value::Bool(true),
+		Value::Char('é'),
+		Value::VecI8(vec![-1, 1]),
+		Value::VecU8(vec![]),
+		Value::VecI16(vec![i16::MIN]),
+		Value::VecU16(vec![u16::MAX]),
+		Value::VecI32(vec![i32::MIN]),
+		Value::VecU32(vec![u32::MAX]),
+		Value::VecI64(vec![i64::MIN]),
+		Value::VecU64(vec![u64::MAX]),
+		Value::String(String::from("wasmedge")),
+	];
+	for v in values {
+		let json = serde_json::Value::from(&v);
+		assert_eq!(Value::try_from(&json).unwrap(), v, "{}", json);
+		// Survives printing and parsing too
+		let parsed: serde_json::Value = serde_json::from_str(&json.to_string()).unwrap();
+		assert_eq!(Value::try_from(parsed).unwrap(), v);
+	}
+
+	let nan = Value::try_from(serde_json::Value::from(Value::F64(f64::NAN))).unwrap();
+	assert!(matches!(nan, Value::F64(v) if v.is_nan()));
+}
+
+#[test]
+fn invalid_json() {
+	for json in [json!(5), json!({"u8": 256}), json!({"i8_array": [1, "2"]}), json!({"char": "ab"}), json!({"u8": 1, "u16": 2}), json!({"f16": 1.0})] {
+		assert!(matches!(Value::try_from(&json), Err(Error::InvalidValue(_))), "{}", json);
+	}
+}
@@ -1,507 +1,363 @@
-use num_derive::FromPrimitive;
-use num_traits::FromPrimitive;
-use std::any::Any;
+use core::ops::{Deref, DerefMut};
+use std::borrow::{Borrow, Cow};
 use wasmedge_sdk::*;
+use wasmedge_types::error::{CoreError, CoreExecutionError, WasmEdgeError};
+use wasmedge_types::{Mutability, ValType as WasmType};
 
-#[derive(Debug)]
-pub enum Param<'a> {
-    I8(i8),
-    U8(u8),
-    I16(i16),
-    U16(u16),
-    I32(i32),
-    U32(u32),
-    I64(i64),
-    U64(u64),
-    F32(f32),
-    F64(f64),
-    Bool(bool),
-    VecI8(&'a Vec<i8>),
-    VecU8(&'a Vec<u8>),
-    VecI16(&'a Vec<i16>),
-    VecU16(&'a Vec<u16>),
-    VecI32(&'a Vec<i32>),
-    VecU32(&'a Vec<u32>),
-    VecI64(&'a Vec<i64>),
-    VecU64(&'a Vec<u64>),
-    String(&'a str),
+pub use wasmedge_bindgen_host_core::{
+    params, record, AllocSignature, AsyncBindgen, Backend, BindgenConfig, CallStats,
+    DeallocSignature, Encoded, Error, FuncHandle, GuestBytes, GuestParam, GuestResults, GuestValue,
+    Locked, Manifest, Metrics, Outcome, Output, Param, PreparedCall, RecordedCall, Replayed,
+    RunWasm, Signature, Val, ValType, Value, WasmResult, WireType,
+};
+
+fn to_wasm_value(v: &Val) -> WasmValue {
+    match *v {
+        Val::I32(v) => WasmValue::from_i32(v),
+        Val::I64(v) => WasmValue::from_i64(v),
+        Val::F32(v) => WasmValue::from_f32(v),
+        Val::F64(v) => WasmValue::from_f64(v),
+    }
 }
 
-impl<'a> Param<'a> {
-    fn settle(&self, vm: &Vm, mem: &mut Memory) -> WasmEdgeResult<(i32, i32)> {
-        match self {
-            Param::I8(v) => {
-                let length = 1;
-                let pointer = allocate(vm, length)?;
-                mem.write(vec![*v as u8], pointer as u32)?;
-                Ok((pointer, length))
-            }
-            Param::U8(v) => {
-                let length = 1;
-                let pointer = allocate(vm, length)?;
-                mem.write(vec![*v], pointer as u32)?;
-                Ok((pointer, length))
-            }
-            Param::I16(v) => {
-                let length = 1;
-                let pointer = allocate(vm, length * 2)?;
-                let bytes = v.to_le_bytes();
-                mem.write(bytes, pointer as u32)?;
-                Ok((pointer, length))
-            }
-            Param::U16(v) => {
-                let length = 2;
-                let pointer = allocate(vm, length * 2)?;
-                let bytes = v.to_le_bytes();
-                mem.write(bytes, pointer as u32)?;
-                Ok((pointer, length))
-            }
-            Param::I32(v) => {
-                let length = 1;
-                let pointer = allocate(vm, length * 4)?;
-                let bytes = v.to_le_bytes();
-                mem.write(bytes, pointer as u32)?;
-                Ok((pointer, length))
-            }
-            Param::U32(v) => {
-                let length = 1;
-                let pointer = allocate(vm, length * 4)?;
-                let bytes = v.to_le_bytes();
-                mem.write(bytes, pointer as u32)?;
-                Ok((pointer, length))
-            }
-            Param::I64(v) => {
-                let length = 1;
-                let pointer = allocate(vm, length * 8)?;
-                let bytes = v.to_le_bytes();
-                mem.write(bytes, pointer as u32)?;
-                Ok((pointer, length))
-            }
-            Param::U64(v) => {
-                let length = 1;
-                let pointer = allocate(vm, length * 8)?;
-                let bytes = v.to_le_bytes();
-                mem.write(bytes, pointer as u32)?;
-                Ok((pointer, length))
-            }
-            Param::F32(v) => {
-                let length = 1;
-                let pointer = allocate(vm, length * 4)?;
-                let bytes = v.to_le_bytes();
-                mem.write(bytes, pointer as u32)?;
-                Ok((pointer, length))
-            }
-            Param::F64(v) => {
-                let length = 1;
-                let pointer = allocate(vm, length * 8)?;
-                let bytes = v.to_le_bytes();
-                mem.write(bytes, pointer as u32)?;
-                Ok((pointer, length))
-            }
-            Param::Bool(v) => {
-                let length = 1;
-                let pointer = allocate(vm, length)?;
-                let byte: u8 = match v {
-                    true => 1,
-                    false => 0,
-                };
-                mem.write(vec![byte], pointer as u32)?;
-                Ok((pointer, length))
-            }
-            Param::VecI8(v) => {
-                let length = v.len() as i32;
-                let pointer = allocate(vm, length)?;
-                let mut bytes = vec![0; length as usize];
-                for (pos, iv) in v.iter().enumerate() {
-                    bytes[pos] = *iv as u8;
-                }
-                mem.write(bytes, pointer as u32)?;
-                Ok((pointer, length))
-            }
-            Param::VecU8(v) => {
-                let length = v.len() as i32;
-                let pointer = allocate(vm, length)?;
-                let mut bytes = vec![0; length as usize];
-                for (pos, iv) in v.iter().enumerate() {
-                    bytes[pos] = *iv;
-                }
-                mem.write(bytes, pointer as u32)?;
-                Ok((pointer, length))
-            }
-            Param::VecI16(v) => {
-                let length = v.len() as i32;
-                let pointer = allocate(vm, length * 2)?;
-                let mut bytes = vec![0; length as usize * 2];
-                for (pos, iv) in v.iter().enumerate() {
-                    let b = iv.to_le_bytes();
-                    for i in 0..2 {
-                        bytes[pos * 2 + i] = b[i];
-                    }
-                }
-                mem.write(bytes, pointer as u32)?;
-                Ok((pointer, length))
-            }
-            Param::VecU16(v) => {
-                let length = v.len() as i32;
-                let pointer = allocate(vm, length * 2)?;
-                let mut bytes = vec![0; length as usize * 2];
-                for (pos, iv) in v.iter().enumerate() {
-                    let b = iv.to_le_bytes();
-                    for i in 0..2 {
-                        bytes[pos * 2 + i] = b[i];
-                    }
-                }
-                mem.write(bytes, pointer as u32)?;
-                Ok((pointer, length))
-            }
-            Param::VecI32(v) => {
-                let length = v.len() as i32;
-                let pointer = allocate(vm, length * 4)?;
-                let mut bytes = vec![0; length as usize * 4];
-                for (pos, iv) in v.iter().enumerate() {
-                    let b = iv.to_le_bytes();
-                    for i in 0..4 {
-                        bytes[pos * 4 + i] = b[i];
-                    }
-                }
-                mem.write(bytes, pointer as u32)?;
-                Ok((pointer, length))
-            }
-            Param::VecU32(v) => {
-                let length = v.len() as i32;
-                let pointer = allocate(vm, length * 4)?;
-                let mut bytes = vec![0; length as usize * 4];
-                for (pos, iv) in v.iter().enumerate() {
-                    let b = iv.to_le_bytes();
-                    for i in 0..4 {
-                        bytes[pos * 4 + i] = b[i];
-                    }
-                }
-                mem.write(bytes, pointer as u32)?;
-                Ok((pointer, length))
-            }
-            Param::VecI64(v) => {
-                let length = v.len() as i32;
-                let pointer = allocate(vm, length * 8)?;
-                let mut bytes = vec![0; length as usize * 8];
-                for (pos, iv) in v.iter().enumerate() {
-                    let b = iv.to_le_bytes();
-                    for i in 0..8 {
-                        bytes[pos * 8 + i] = b[i];
-                    }
-                }
-                mem.write(bytes, pointer as u32)?;
-                Ok((pointer, length))
-            }
-            Param::VecU64(v) => {
-                let length = v.len() as i32;
-                let pointer = allocate(vm, length * 8)?;
-                let mut bytes = vec![0; length as usize * 8];
-                for (pos, iv) in v.iter().enumerate() {
-                    let b = iv.to_le_bytes();
-                    for i in 0..8 {
-                        bytes[pos * 8 + i] = b[i];
-                    }
-                }
-                mem.write(bytes, pointer as u32)?;
-                Ok((pointer, length))
-            }
-            Param::String(v) => {
-                let bytes = v.as_bytes().to_vec();
-                let length = bytes.len() as i32;
-                let pointer = allocate(vm, length)?;
-                mem.write(bytes, pointer as u32)?;
-                Ok((pointer, length))
-            }
-        }
+fn from_wasm_value(v: &WasmValue) -> Result<Val, Error> {
+    match v.ty() {
+        WasmType::I32 => Ok(Val::I32(v.to_i32())),
+        WasmType::I64 => Ok(Val::I64(v.to_i64())),
+        WasmType::F32 => Ok(Val::F32(v.to_f32())),
+        WasmType::F64 => Ok(Val::F64(v.to_f64())),
+        ty => Err(Error::InvalidReturn(format!(
+            "Unsupported value type {:?}",
+            ty
+        ))),
+    }
+}
+
+fn from_wasm_type(ty: &WasmType) -> ValType {
+    match ty {
+        WasmType::I32 => ValType::I32,
+        WasmType::I64 => ValType::I64,
+        WasmType::F32 => ValType::F32,
+        WasmType::F64 => ValType::F64,
+        WasmType::V128 => ValType::V128,
+        WasmType::FuncRef => ValType::FuncRef,
+        WasmType::ExternRef => ValType::ExternRef,
     }
 }
 
-fn allocate(vm: &Vm, size: i32) -> WasmEdgeResult<i32> {
-    match vm.run_func(None, "allocate", vec![WasmValue::from_i32(size)]) {
-        Ok(rv) => Ok(rv[0].to_i32()),
-        Err(e) => Err(e),
+/// A trap when the engine stopped the guest midway. Calls rejected before
+/// running, e.g. for mismatched arguments, are runtime errors.
+fn call_error<E>(e: E) -> Error
+where
+    E: Borrow<WasmEdgeError> + std::error::Error + Send + Sync + 'static,
+{
+    match e.borrow() {
+        WasmEdgeError::Core(CoreError::Execution(CoreExecutionError::FuncSigMismatch)) => {
+            Error::runtime(e)
+        }
+        WasmEdgeError::Core(CoreError::Execution(_)) => Error::trap(e),
+        _ => Error::runtime(e),
     }
 }
 
-#[derive(FromPrimitive)]
-enum RetTypes {
-    U8 = 1,
-    I8 = 2,
-    U16 = 3,
-    I16 = 4,
-    U32 = 5,
-    I32 = 6,
-    U64 = 7,
-    I64 = 8,
-    F32 = 9,
-    F64 = 10,
-    Bool = 11,
-    Char = 12,
-    U8Array = 21,
-    I8Array = 22,
-    U16Array = 23,
-    I16Array = 24,
-    U32Array = 25,
-    I32Array = 26,
-    U64Array = 27,
-    I64Array = 28,
-    String = 31,
+/// [`Backend`] running guests in the active module of a wasmedge-sdk `Vm`.
+pub struct WasmEdgeBackend {
+    vm: Box<Vm>,
+    manifest: Option<Manifest>,
+    funcs: Vec<Resolved>,
+    memory: Option<Memory>,
+    // Whether the Vm was lent out since the handles were looked up
+    stale: bool,
 }
 
-pub struct Bindgen {
-    vm: Box<Vm>, // Can't use Arc because vm can be get_mut after cloned for hostfunc
+unsafe impl Send for WasmEdgeBackend {}
+unsafe impl Sync for WasmEdgeBackend {}
+
+/// A function resolved for calls by handle.
+struct Resolved {
+    name: String,
+    func: Option<Func>,
 }
 
-unsafe impl Send for Bindgen {}
-unsafe impl Sync for Bindgen {}
+impl WasmEdgeBackend {
+    fn new(vm: Vm, manifest: Option<Manifest>) -> Self {
+        let mut backend = WasmEdgeBackend {
+            vm: Box::new(vm),
+            manifest,
+            funcs: Vec::new(),
+            memory: None,
+            stale: true,
+        };
+        backend.refresh();
+        backend
+    }
 
-impl Bindgen {
-    pub fn new(vm: Vm) -> Self {
-        Bindgen { vm: Box::new(vm) }
+    fn instance(&self) -> Result<Instance, Error> {
+        self.vm.active_module().map_err(Error::runtime)
+    }
+
+    // Looks the handles up again if the active module may have changed
+    fn refresh(&mut self) {
+        if !self.stale {
+            return;
+        }
+        let instance = self.instance().ok();
+        for resolved in self.funcs.iter_mut() {
+            resolved.func = instance
+                .as_ref()
+                .and_then(|instance| instance.func(&resolved.name));
+        }
+        self.memory = instance.and_then(|instance| instance.memory("memory"));
+        self.stale = false;
+    }
+
+    fn lookup_memory(&self, name: &str) -> Result<Memory, Error> {
+        self.instance()?
+            .memory(name)
+            .ok_or_else(|| Error::MissingMemory(name.to_string()))
+    }
+
+    fn with_memory<R>(
+        &self,
+        name: &str,
+        f: impl FnOnce(&Memory) -> WasmEdgeResult<R>,
+    ) -> Result<R, Error> {
+        match &self.memory {
+            Some(memory) if name == "memory" && !self.stale => f(memory),
+            _ => f(&self.lookup_memory(name)?),
+        }
+        .map_err(Error::runtime)
     }
 
-    pub fn run_wasm(
+    fn with_memory_mut<R>(
         &mut self,
-        func_name: impl AsRef<str>,
-        inputs: Vec<Param>,
-    ) -> WasmEdgeResult<Result<Vec<Box<dyn Any + Send + Sync>>, String>> {
-        let inputs_count = inputs.len() as i32;
-
-        // allocate new frame for passing pointers
-        let pointer_of_pointers = match self.vm.run_func(
-            None,
-            "allocate",
-            vec![WasmValue::from_i32(inputs_count * 4 * 2)],
-        ) {
-            Ok(rv) => rv[0].to_i32(),
-            Err(e) => {
-                println!("allocate error: {:?}", e);
-                return Err(e);
-            }
-        };
+        name: &str,
+        f: impl FnOnce(&mut Memory) -> WasmEdgeResult<R>,
+    ) -> Result<R, Error> {
+        self.refresh();
+        match self.memory.as_mut().filter(|_| name == "memory") {
+            Some(memory) => f(memory),
+            None => f(&mut self.lookup_memory(name)?),
+        }
+        .map_err(Error::runtime)
+    }
 
-        let mut memory = self.vm.active_module().unwrap().memory("memory").unwrap();
+    fn call_func(&self, func: &Func, args: &[Val]) -> Result<Vec<Val>, Error> {
+        let args: Vec<WasmValue> = args.iter().map(to_wasm_value).collect();
+        let rets = func.call(&*self.vm, args).map_err(call_error)?;
+        rets.iter().map(from_wasm_value).collect()
+    }
+}
 
-        for (pos, inp) in inputs.iter().enumerate() {
-            let sr = inp.settle(&self.vm, &mut memory);
-            let (pointer, length_of_input) = match sr {
-                Ok(r) => (r.0, r.1),
-                Err(e) => {
-                    println!("run_wasm error: {}", func_name.as_ref());
-                    return Err(e);
-                }
-            };
-
-            memory.write(
-                pointer.to_le_bytes(),
-                pointer_of_pointers as u32 + pos as u32 * 4 * 2,
-            )?;
-            memory.write(
-                length_of_input.to_le_bytes(),
-                pointer_of_pointers as u32 + pos as u32 * 4 * 2 + 4,
-            )?;
+impl Backend for WasmEdgeBackend {
+    fn call(&mut self, name: &str, args: &[Val]) -> Result<Vec<Val>, Error> {
+        let func = self
+            .instance()?
+            .func(name)
+            .ok_or_else(|| Error::MissingFunction(name.to_string()))?;
+        self.call_func(&func, args)
+    }
+
+    fn resolve(&mut self, name: &str) -> Result<Option<FuncHandle>, Error> {
+        if let Some(index) = self.funcs.iter().position(|resolved| resolved.name == name) {
+            return Ok(Some(FuncHandle(index as u32)));
         }
+        self.refresh();
+        let func = self
+            .instance()?
+            .func(name)
+            .ok_or_else(|| Error::MissingFunction(name.to_string()))?;
+        self.funcs.push(Resolved {
+            name: name.to_string(),
+            func: Some(func),
+        });
+        Ok(Some(FuncHandle(self.funcs.len() as u32 - 1)))
+    }
 
-        let rets = self.vm.run_func(
-            None,
-            func_name,
-            vec![
-                WasmValue::from_i32(pointer_of_pointers),
-                WasmValue::from_i32(inputs_count),
-            ],
-        )?;
-        // Don't need to deallocate because the memory will be loaded and free in the wasm
-        // self.vm.run_function("deallocate", vec![WasmValue::from_i32(pointer_of_pointers), WasmValue::from_i32(inputs_count * 4 * 2)])?;
-
-        if rets.len() != 1 {
-            return Ok(Err(String::from("Invalid return value")));
+    fn call_handle(&mut self, handle: FuncHandle, args: &[Val]) -> Result<Vec<Val>, Error> {
+        self.refresh();
+        match self.funcs.get(handle.0 as usize) {
+            Some(Resolved {
+                func: Some(func), ..
+            }) => self.call_func(func, args),
+            Some(Resolved { name, func: None }) => Err(Error::MissingFunction(name.clone())),
+            None => Err(Error::Unsupported(format!(
+                "unknown function handle {}",
+                handle.0
+            ))),
         }
-        let rvec = memory.read(rets[0].to_i32() as u32, 9)?;
-        let _ = self.vm.run_func(
-            None,
-            "deallocate",
-            vec![
-                WasmValue::from_i32(rets[0].to_i32()),
-                WasmValue::from_i32(9),
-            ],
-        );
-
-        let flag = rvec[0];
-        let ret_pointer = i32::from_le_bytes(rvec[1..5].try_into().unwrap());
-        let ret_len = i32::from_le_bytes(rvec[5..9].try_into().unwrap());
-        match flag {
-            0 => Ok(Ok(self.parse_result(ret_pointer, ret_len))),
-            _ => Ok(Err(self.parse_error(ret_pointer, ret_len))),
+    }
+
+    fn read(&self, memory: &str, offset: u32, len: u32) -> Result<Vec<u8>, Error> {
+        self.with_memory(memory, |memory| memory.read(offset, len))
+    }
+
+    fn write(&mut self, memory: &str, offset: u32, data: &[u8]) -> Result<(), Error> {
+        self.with_memory_mut(memory, |memory| memory.write(data, offset))
+    }
+
+    fn view(&self, memory: &str, offset: u32, len: u32) -> Result<Cow<'_, [u8]>, Error> {
+        if len == 0 {
+            return Ok(Cow::Borrowed(&[]));
         }
+        let pointer = self.with_memory(memory, |memory| memory.data_pointer(offset, len))?;
+        // The memory can't grow or be written while the backend is borrowed
+        Ok(Cow::Borrowed(unsafe {
+            std::slice::from_raw_parts(pointer, len as usize)
+        }))
     }
 
-    fn parse_error(&self, ret_pointer: i32, ret_len: i32) -> String {
-        let memory = self.vm.active_module().unwrap().memory("memory").unwrap();
-        let err_bytes = memory.read(ret_pointer as u32, ret_len as u32).unwrap();
-        let _ = self.vm.run_func(
-            None,
-            "deallocate",
-            vec![
-                WasmValue::from_i32(ret_pointer),
-                WasmValue::from_i32(ret_len),
-            ],
-        );
-        String::from_utf8(err_bytes).unwrap_or_default()
-    }
-
-    fn parse_result(&self, ret_pointer: i32, ret_len: i32) -> Vec<Box<dyn Any + Send + Sync>> {
-        let size = ret_len as usize;
-        let memory = self.vm.active_module().unwrap().memory("memory").unwrap();
-        let p_data = memory
-            .read(ret_pointer as u32, size as u32 * 3 * 4)
-            .unwrap();
-        let _ = self.vm.run_func(
-            None,
-            "deallocate",
-            vec![
-                WasmValue::from_i32(ret_pointer),
-                WasmValue::from_i32(size as i32 * 3 * 4),
-            ],
-        );
-
-        let mut p_values = vec![0; size * 3];
-
-        for i in 0..size * 3 {
-            p_values[i] = i32::from_le_bytes(p_data[i * 4..(i + 1) * 4].try_into().unwrap());
+    fn view_mut(
+        &mut self,
+        memory: &str,
+        offset: u32,
+        len: u32,
+    ) -> Result<Option<&mut [u8]>, Error> {
+        if len == 0 {
+            return Ok(Some(&mut []));
         }
+        let pointer =
+            self.with_memory_mut(memory, |memory| memory.data_pointer_mut(offset, len))?;
+        // The memory can't be used otherwise while the backend is borrowed
+        Ok(Some(unsafe {
+            std::slice::from_raw_parts_mut(pointer, len as usize)
+        }))
+    }
 
-        let mut results: Vec<Box<dyn Any + Send + Sync>> = Vec::with_capacity(size);
-
-        for i in 0..size {
-            let bytes = memory
-                .read(p_values[i * 3] as u32, p_values[i * 3 + 2] as u32)
-                .unwrap();
-            let _ = self.vm.run_func(
-                None,
-                "deallocate",
-                vec![
-                    WasmValue::from_i32(p_values[i * 3]),
-                    WasmValue::from_i32(p_values[i * 3 + 2]),
-                ],
-            );
-            match FromPrimitive::from_i32(p_values[i * 3 + 1]) {
-                Some(RetTypes::U8) => {
-                    results.push(Box::new(bytes[0]));
-                }
-                Some(RetTypes::I8) => {
-                    results.push(Box::new(bytes[0] as i8));
-                }
-                Some(RetTypes::U16) => {
-                    let v = u16::from_le_bytes(bytes.try_into().unwrap());
-                    results.push(Box::new(v));
-                }
-                Some(RetTypes::I16) => {
-                    let v = i16::from_le_bytes(bytes.try_into().unwrap());
-                    results.push(Box::new(v));
-                }
-                Some(RetTypes::U32) => {
-                    let v = u32::from_le_bytes(bytes.try_into().unwrap());
-                    results.push(Box::new(v));
-                }
-                Some(RetTypes::I32) => {
-                    let v = i32::from_le_bytes(bytes.try_into().unwrap());
-                    results.push(Box::new(v));
-                }
-                Some(RetTypes::U64) => {
-                    let v = u64::from_le_bytes(bytes.try_into().unwrap());
-                    results.push(Box::new(v));
-                }
-                Some(RetTypes::I64) => {
-                    let v = i64::from_le_bytes(bytes.try_into().unwrap());
-                    results.push(Box::new(v));
-                }
-                Some(RetTypes::F32) => {
-                    let v = f32::from_le_bytes(bytes.try_into().unwrap());
-                    results.push(Box::new(v));
-                }
-                Some(RetTypes::F64) => {
-                    let v = f64::from_le_bytes(bytes.try_into().unwrap());
-                    results.push(Box::new(v));
-                }
-                Some(RetTypes::Bool) => {
-                    results.push(Box::new(bytes[0] == 1 as u8));
-                }
-                Some(RetTypes::Char) => {
-                    let v = u32::from_le_bytes(bytes.try_into().unwrap());
-                    results.push(Box::new(char::from_u32(v)));
-                }
-                Some(RetTypes::U8Array) => {
-                    let len = bytes.len();
-                    let mut v = vec![0; len];
-                    for i in 0..len {
-                        v[i] = bytes[i] as u8;
-                    }
-                    results.push(Box::new(v));
-                }
-                Some(RetTypes::I8Array) => {
-                    let len = bytes.len();
-                    let mut v = vec![0; len];
-                    for i in 0..len {
-                        v[i] = bytes[i] as i8;
-                    }
-                    results.push(Box::new(v));
-                }
-                Some(RetTypes::U16Array) => {
-                    let len = bytes.len() / 2;
-                    let mut v = vec![0; len];
-                    for i in 0..len {
-                        v[i] = u16::from_le_bytes(bytes[i * 2..(i + 1) * 2].try_into().unwrap());
-                    }
-                    results.push(Box::new(v));
-                }
-                Some(RetTypes::I16Array) => {
-                    let len = bytes.len() / 2;
-                    let mut v = vec![0; len];
-                    for i in 0..len {
-                        v[i] = i16::from_le_bytes(bytes[i * 2..(i + 1) * 2].try_into().unwrap());
-                    }
-                    results.push(Box::new(v));
-                }
-                Some(RetTypes::U32Array) => {
-                    let len = bytes.len() / 4;
-                    let mut v = vec![0; len];
-                    for i in 0..len {
-                        v[i] = u32::from_le_bytes(bytes[i * 4..(i + 1) * 4].try_into().unwrap());
-                    }
-                    results.push(Box::new(v));
-                }
-                Some(RetTypes::I32Array) => {
-                    let len = bytes.len() / 4;
-                    let mut v = vec![0; len];
-                    for i in 0..len {
-                        v[i] = i32::from_le_bytes(bytes[i * 4..(i + 1) * 4].try_into().unwrap());
-                    }
-                    results.push(Box::new(v));
-                }
-                Some(RetTypes::U64Array) => {
-                    let len = bytes.len() / 8;
-                    let mut v = vec![0; len];
-                    for i in 0..len {
-                        v[i] = u64::from_le_bytes(bytes[i * 8..(i + 1) * 8].try_into().unwrap());
-                    }
-                    results.push(Box::new(v));
-                }
-                Some(RetTypes::I64Array) => {
-                    let len = bytes.len() / 8;
-                    let mut v = vec![0; len];
-                    for i in 0..len {
-                        v[i] = i64::from_le_bytes(bytes[i * 8..(i + 1) * 8].try_into().unwrap());
-                    }
-                    results.push(Box::new(v));
-                }
-                Some(RetTypes::String) => {
-                    results.push(Box::new(String::from_utf8(bytes).unwrap()));
+    fn memory_size(&self, memory: &str) -> Result<u64, Error> {
+        self.with_memory(memory, |memory| Ok(memory.page() as u64 * 65536))
+    }
+
+    fn grow(&mut self, memory: &str, pages: u32) -> Result<(), Error> {
+        self.with_memory_mut(memory, |memory| memory.grow(pages))
+    }
+
+    fn has_function(&self, name: &str) -> bool {
+        match self.instance() {
+            Ok(instance) => instance.func(name).is_some(),
+            Err(_) => false,
+        }
+    }
+
+    fn signature(&self, name: &str) -> Option<(Vec<ValType>, Vec<ValType>)> {
+        let ty = self.instance().ok()?.func(name)?.ty().ok()?;
+        let types = |types: Option<&[WasmType]>| -> Vec<ValType> {
+            types
+                .unwrap_or_default()
+                .iter()
+                .map(from_wasm_type)
+                .collect()
+        };
+        Some((types(ty.args()), types(ty.returns())))
+    }
+
+    fn globals(&self) -> Result<Vec<(String, Val)>, Error> {
+        let instance = self.instance()?;
+        let mut globals = Vec::new();
+        for name in instance.global_names().unwrap_or_default() {
+            if let Some(global) = instance.global(&name) {
+                if global.ty().map_err(Error::runtime)?.mutability() == Mutability::Var {
+                    globals.push((name, from_wasm_value(&global.get_value())?));
                 }
-                None => {}
             }
         }
+        Ok(globals)
+    }
+
+    fn set_global(&mut self, name: &str, value: Val) -> Result<(), Error> {
+        let mut global = self
+            .instance()?
+            .global(name)
+            .ok_or_else(|| Error::Runtime(format!("Global {} is not exported", name).into()))?;
+        global
+            .set_value(to_wasm_value(&value))
+            .map_err(Error::runtime)
+    }
+
+    fn manifest(&self) -> Option<Manifest> {
+        self.manifest.clone()
+    }
+}
+
+/// Chains bindgen calls between the active modules of wasmedge-sdk `Vm`s.
+pub type Router = wasmedge_bindgen_host_core::Router<WasmEdgeBackend>;
+
+/// Calls bindgen functions of the active module of a wasmedge-sdk `Vm`.
+///
+/// Dereferences to the runtime independent [`wasmedge_bindgen_host_core::Bindgen`],
+/// which provides `run_wasm` and `config`. Its `reload` isn't supported with
+/// this backend and returns [`Error::Unsupported`].
+pub struct Bindgen {
+    inner: wasmedge_bindgen_host_core::Bindgen<WasmEdgeBackend>,
+}
+
+impl Deref for Bindgen {
+    type Target = wasmedge_bindgen_host_core::Bindgen<WasmEdgeBackend>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
+
+impl DerefMut for Bindgen {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.inner
+    }
+}
+
+impl From<Bindgen> for wasmedge_bindgen_host_core::Bindgen<WasmEdgeBackend> {
+    fn from(bindgen: Bindgen) -> Self {
+        bindgen.inner
+    }
+}
+
+impl Bindgen {
+    pub fn new(vm: Vm) -> Self {
+        Bindgen {
+            inner: wasmedge_bindgen_host_core::Bindgen::new(WasmEdgeBackend::new(vm, None)),
+        }
+    }
+
+    /// Creates a `Bindgen` for a `vm` with a registered active module,
+    /// checking that the allocator and memory exports named in `config` are present.
+    pub fn with_config(vm: Vm, config: BindgenConfig) -> Result<Self, Error> {
+        Ok(Bindgen {
+            inner: wasmedge_bindgen_host_core::Bindgen::with_config(
+                WasmEdgeBackend::new(vm, None),
+                config,
+            )?,
+        })
+    }
+
+    /// Like [`with_config`](Self::with_config), for a module whose manifest is
+    /// known, e.g. read with [`Manifest::from_module`] from the bytes it was
+    /// registered from. Calls then use the packed, direct and batch exports it flags.
+    pub fn with_manifest(vm: Vm, config: BindgenConfig, manifest: Manifest) -> Result<Self, Error> {
+        Ok(Bindgen {
+            inner: wasmedge_bindgen_host_core::Bindgen::with_config(
+                WasmEdgeBackend::new(vm, Some(manifest)),
+                config,
+            )?,
+        })
+    }
+
+    /// Shares the instance between tasks, running calls off the executor threads.
+    pub fn into_async(self) -> AsyncBindgen<WasmEdgeBackend> {
+        self.inner.into_async()
+    }
+
+    /// Spreads calls over `bindgens`, like [`AsyncBindgen::pooled`].
+    pub fn into_async_pool(
+        bindgens: impl IntoIterator<Item = Bindgen>,
+    ) -> AsyncBindgen<WasmEdgeBackend> {
+        AsyncBindgen::pooled(bindgens.into_iter().map(|bindgen| bindgen.inner))
+    }
 
-        results
+    /// The `Vm` running the module. Handles looked up in its active module
+    /// are looked up again on the next call, in case it was changed.
+    pub fn vm(&mut self) -> &mut Vm {
+        let backend = self.inner.backend_mut();
+        backend.stale = true;
+        &mut backend.vm
     }
 }
@@ -0,0 +1,436 @@
+use std::borrow::Cow;
+use std::io::Read;
+use std::sync::{Arc, Mutex};
+
+use wasmi::errors::ErrorKind;
+use wasmi::{Caller, Engine, ExternType, Func, Instance, Linker, Memory, Module, Store};
+
+pub use wasmedge_bindgen_host_core::{params, record, AllocSignature, AsyncBindgen, Backend, BindgenConfig, CallStats, DeallocSignature, Encoded, Error, FuncHandle, GuestBytes, GuestParam, GuestResults, GuestValue, Locked, Manifest, Metrics, Outcome, Output, Param, PreparedCall, RecordedCall, Replayed, RunWasm, Signature, Val, ValType, Value, WasmResult, WireType};
+
+/// Calls bindgen functions of a module instantiated by wasmi.
+pub type Bindgen<T = ()> = wasmedge_bindgen_host_core::Bindgen<WasmiBackend<T>>;
+
+/// Chains bindgen calls between modules instantiated by wasmi.
+pub type Router<T = ()> = wasmedge_bindgen_host_core::Router<WasmiBackend<T>>;
+
+fn to_wasmi_value(v: &Val) -> wasmi::Val {
+	match *v {
+		Val::I32(v) => wasmi::Val::I32(v),
+		Val::I64(v) => wasmi::Val::I64(v),
+		Val::F32(v) => wasmi::Val::F32(v.into()),
+		Val::F64(v) => wasmi::Val::F64(v.into()),
+	}
+}
+
+fn from_wasmi_value(v: &wasmi::Val) -> Result<Val, Error> {
+	match v {
+		wasmi::Val::I32(v) => Ok(Val::I32(*v)),
+		wasmi::Val::I64(v) => Ok(Val::I64(*v)),
+		wasmi::Val::F32(v) => Ok(Val::F32((*v).into())),
+		wasmi::Val::F64(v) => Ok(Val::F64((*v).into())),
+		v => Err(Error::InvalidReturn(format!("Unsupported value type {:?}", v.ty()))),
+	}
+}
+
+/// Collects what guests write to stdout and stderr with WASI `fd_write`,
+/// instead of letting it through to the host's.
+#[derive(Clone, Default)]
+pub struct OutputCapture(Arc<Mutex<Output>>);
+
+// WASI errnos
+const ERRNO_BADF: i32 = 8;
+const ERRNO_FAULT: i32 = 21;
+const ERRNO_IO: i32 = 29;
+const ERRNO_NOSYS: i32 = 52;
+
+impl OutputCapture {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Defines `wasi_snapshot_preview1::fd_write` in `linker`, capturing writes
+	/// to stdout and stderr. Other descriptors are rejected.
+	pub fn link<T>(&self, linker: &mut Linker<T>) -> Result<(), Error> {
+		let output = self.0.clone();
+		linker.func_wrap("wasi_snapshot_preview1", "fd_write", move |mut caller: Caller<'_, T>, fd: i32, iovs: u32, iovs_len: u32, nwritten: u32| -> i32 {
+			if fd != 1 && fd != 2 {
+				return ERRNO_BADF;
+			}
+			let memory = match caller.get_export("memory").and_then(|e| e.into_memory()) {
+				Some(memory) => memory,
+				None => return ERRNO_FAULT,
+			};
+			let mut written = Vec::new();
+			for i in 0..iovs_len {
+				let mut iov = [0u8; 8];
+				if memory.read(&caller, iovs as usize + i as usize * 8, &mut iov).is_err() {
+					return ERRNO_FAULT;
+				}
+				let (pointer, len) = (u32::from_le_bytes(iov[..4].try_into().unwrap()), u32::from_le_bytes(iov[4..].try_into().unwrap()));
+				let start = written.len();
+				written.resize(start + len as usize, 0);
+				if memory.read(&caller, pointer as usize, &mut written[start..]).is_err() {
+					return ERRNO_FAULT;
+				}
+			}
+			if memory.write(&mut caller, nwritten as usize, &(written.len() as u32).to_le_bytes()).is_err() {
+				return ERRNO_FAULT;
+			}
+			let mut output = output.lock().unwrap_or_else(|e| e.into_inner());
+			match fd {
+				1 => output.stdout.extend_from_slice(&written),
+				_ => output.stderr.extend_from_slice(&written),
+			}
+			0
+		}).map_err(Error::runtime)?;
+		Ok(())
+	}
+
+	/// Takes what was captured so far.
+	pub fn take(&self) -> Output {
+		std::mem::take(&mut *self.0.lock().unwrap_or_else(|e| e.into_inner()))
+	}
+}
+
+/// Defines the `wasi_snapshot_preview1` imports of `module` missing from
+/// `linker`, so ordinary wasm32-wasi guests instantiate without a WASI
+/// implementation. The guest sees no arguments nor environment variables,
+/// `clock_time_get` reads the host's clock, `random_get` the host's
+/// `/dev/urandom`, `sched_yield` returns, and `proc_exit` ends the call with
+/// a trap. All others, including every file and socket function, fail with
+/// `ENOSYS`.
+pub fn link_wasi_stubs<T>(linker: &mut Linker<T>, module: &Module) {
+	for import in module.imports().filter(|i| i.module() == "wasi_snapshot_preview1") {
+		let ExternType::Func(ty) = import.ty() else {
+			continue;
+		};
+		let name = import.name().to_string();
+		// Fails for those already defined, e.g. `fd_write` by an `OutputCapture`, which are kept
+		let _ = linker.func_new("wasi_snapshot_preview1", import.name(), ty.clone(), move |caller, params, results| {
+			if name == "proc_exit" {
+				return Err(wasmi::Error::i32_exit(params.first().and_then(|p| p.i32()).unwrap_or(0)));
+			}
+			let errno = wasi_stub(caller, &name, params);
+			if let [wasmi::Val::I32(result)] = results {
+				*result = errno;
+			}
+			Ok(())
+		});
+	}
+}
+
+fn wasi_stub<T>(mut caller: Caller<'_, T>, name: &str, params: &[wasmi::Val]) -> i32 {
+	let arg = |i: usize| params.get(i).and_then(|p| p.i32()).unwrap_or(0) as u32;
+	let Some(memory) = caller.get_export("memory").and_then(|e| e.into_memory()) else {
+		return ERRNO_FAULT;
+	};
+	let written = match name {
+		"args_get" | "environ_get" | "sched_yield" => Ok(()),
+		"args_sizes_get" | "environ_sizes_get" => {
+			memory.write(&mut caller, arg(0) as usize, &[0; 4]).and_then(|_| memory.write(&mut caller, arg(1) as usize, &[0; 4]))
+		}
+		"clock_time_get" => {
+			let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap_or_default();
+			memory.write(&mut caller, arg(2) as usize, &(now.as_nanos() as u64).to_le_bytes())
+		}
+		"random_get" => {
+			// Filled in place, whatever length the guest asks for allocates nothing
+			let (buffer, len) = (arg(0) as usize, arg(1) as usize);
+			let Some(buffer) = memory.data_mut(&mut caller).get_mut(buffer..buffer + len) else {
+				return ERRNO_FAULT;
+			};
+			if std::fs::File::open("/dev/urandom").and_then(|mut f| f.read_exact(buffer)).is_err() {
+				return ERRNO_IO;
+			}
+			Ok(())
+		}
+		_ => return ERRNO_NOSYS,
+	};
+	match written {
+		Ok(()) => 0,
+		Err(_) => ERRNO_FAULT,
+	}
+}
+
+fn from_wasmi_type(ty: &wasmi::core::ValType) -> ValType {
+	match ty {
+		wasmi::core::ValType::I32 => ValType::I32,
+		wasmi::core::ValType::I64 => ValType::I64,
+		wasmi::core::ValType::F32 => ValType::F32,
+		wasmi::core::ValType::F64 => ValType::F64,
+		wasmi::core::ValType::FuncRef => ValType::FuncRef,
+		wasmi::core::ValType::ExternRef => ValType::ExternRef,
+	}
+}
+
+fn lookup<T>(store: &Store<T>, instance: Instance, name: &str) -> Option<(Func, Vec<wasmi::Val>)> {
+	let func = instance.get_func(store, name)?;
+	let rets = func.ty(store).results().iter().map(|ty| wasmi::Val::default(*ty)).collect();
+	Some((func, rets))
+}
+
+fn call_func<T>(store: &mut Store<T>, func: Func, args: &[Val], rets: &mut [wasmi::Val]) -> Result<Vec<Val>, Error> {
+	// Bindgen exports take at most a few arguments, spare allocating for them
+	let mut buffer = [const { wasmi::Val::I32(0) }; 4];
+	let spilled: Vec<wasmi::Val>;
+	let args = match buffer.get_mut(..args.len()) {
+		Some(buffer) => {
+			for (to, from) in buffer.iter_mut().zip(args) {
+				*to = to_wasmi_value(from);
+			}
+			&*buffer
+		}
+		None => {
+			spilled = args.iter().map(to_wasmi_value).collect();
+			&spilled
+		}
+	};
+	func.call(store, args, rets).map_err(call_error)?;
+	rets.iter().map(from_wasmi_value).collect()
+}
+
+/// A trap when the guest was stopped midway: by a trap, or a host function
+/// failing or exiting. Calls rejected before running, e.g. for mismatched
+/// arguments, are runtime errors.
+fn call_error(e: wasmi::Error) -> Error {
+	match e.kind() {
+		ErrorKind::TrapCode(_) | ErrorKind::I32ExitStatus(_) | ErrorKind::Host(_) | ErrorKind::Message(_) => Error::trap(e),
+		_ => Error::runtime(e),
+	}
+}
+
+/// [`Backend`] running guests in the pure Rust wasmi interpreter, for targets
+/// that can't ship the WasmEdge shared library.
+pub struct WasmiBackend<T = ()> {
+	store: Store<T>,
+	instance: Instance,
+	module: Option<(Module, Box<Instantiate<T>>)>,
+	manifest: Option<Manifest>,
+	output: Option<OutputCapture>,
+	/// Functions resolved for calls by handle, looked up again in new instances
+	funcs: Vec<Resolved>,
+	/// The export named `memory`, used by the default config
+	memory: Option<Memory>,
+}
+
+/// A function resolved for calls by handle, with its result types so they
+/// aren't looked up on every call.
+struct Resolved {
+	name: String,
+	func: Option<(Func, Vec<wasmi::Val>)>,
+}
+
+type Instantiate<T> = dyn Fn(&Module) -> Result<(Store<T>, Instance), Error> + Send + Sync;
+
+impl WasmiBackend<()> {
+	/// Compiles and instantiates a module that has no imports but WASI ones,
+	/// capturing its writes to stdout and stderr. The rest of WASI is stubbed
+	/// out by [`link_wasi_stubs`].
+	pub fn new(wasm: impl AsRef<[u8]>) -> Result<Self, Error> {
+		let output = OutputCapture::new();
+		let capture = output.clone();
+		let backend = Self::with_instantiate(&Engine::default(), wasm, move |module| {
+			let mut store = Store::new(module.engine(), ());
+			let mut linker = Linker::new(module.engine());
+			capture.link(&mut linker)?;
+			link_wasi_stubs(&mut linker, module);
+			let instance = linker
+				.instantiate(&mut store, module)
+				.and_then(|pre| pre.start(&mut store))
+				.map_err(Error::runtime)?;
+			Ok((store, instance))
+		})?;
+		Ok(backend.with_output(output))
+	}
+}
+
+impl<T> WasmiBackend<T> {
+	/// Wraps a module instantiated by the caller, e.g. with WASI or other
+	/// host functions linked in. It can't be replaced after a trap, nor reloaded.
+	pub fn from_instance(store: Store<T>, instance: Instance) -> Self {
+		let memory = instance.get_memory(&store, "memory");
+		WasmiBackend { store, instance, module: None, manifest: None, output: None, funcs: Vec::new(), memory }
+	}
+
+	/// Reports the output collected by `output`, linked into the instance with
+	/// [`OutputCapture::link`], as the output of each call.
+	pub fn with_output(mut self, output: OutputCapture) -> Self {
+		self.output = Some(output);
+		self
+	}
+
+	/// Compiles `wasm` and instantiates it with `instantiate`, e.g. with a
+	/// `Linker` holding host functions. `instantiate` is called again to get a
+	/// fresh instance after a trap, and to instantiate reloaded modules.
+	pub fn with_instantiate(
+		engine: &Engine,
+		wasm: impl AsRef<[u8]>,
+		instantiate: impl Fn(&Module) -> Result<(Store<T>, Instance), Error> + Send + Sync + 'static,
+	) -> Result<Self, Error> {
+		let wasm = wasm.as_ref();
+		let module = Module::new(engine, wasm).map_err(Error::runtime)?;
+		let (store, instance) = instantiate(&module)?;
+		Ok(WasmiBackend {
+			module: Some((module, Box::new(instantiate))),
+			manifest: Manifest::from_module(wasm).ok().flatten(),
+			..Self::from_instance(store, instance)
+		})
+	}
+
+	pub fn store(&self) -> &Store<T> {
+		&self.store
+	}
+
+	pub fn store_mut(&mut self) -> &mut Store<T> {
+		&mut self.store
+	}
+
+	pub fn instance(&self) -> Instance {
+		self.instance
+	}
+
+	/// Replaces the instance, looking up the cached exports in the new one.
+	fn swap(&mut self, store: Store<T>, instance: Instance) {
+		(self.store, self.instance) = (store, instance);
+		for resolved in self.funcs.iter_mut() {
+			resolved.func = lookup(&self.store, instance, &resolved.name);
+		}
+		self.memory = instance.get_memory(&self.store, "memory");
+	}
+
+	fn memory(&self, name: &str) -> Result<Memory, Error> {
+		if let Some(memory) = self.memory.filter(|_| name == "memory") {
+			return Ok(memory);
+		}
+		self.instance.get_memory(&self.store, name).ok_or_else(|| Error::MissingMemory(name.to_string()))
+	}
+}
+
+impl<T> Backend for WasmiBackend<T> {
+	fn call(&mut self, name: &str, args: &[Val]) -> Result<Vec<Val>, Error> {
+		let func = self.instance.get_func(&self.store, name).ok_or_else(|| Error::MissingFunction(name.to_string()))?;
+		let mut rets: Vec<wasmi::Val> = func.ty(&self.store).results().iter().map(|ty| wasmi::Val::default(*ty)).collect();
+		call_func(&mut self.store, func, args, &mut rets)
+	}
+
+	fn resolve(&mut self, name: &str) -> Result<Option<FuncHandle>, Error> {
+		if let Some(index) = self.funcs.iter().position(|f| f.name == name) {
+			return Ok(Some(FuncHandle(index as u32)));
+		}
+		let func = lookup(&self.store, self.instance, name).ok_or_else(|| Error::MissingFunction(name.to_string()))?;
+		self.funcs.push(Resolved { name: name.to_string(), func: Some(func) });
+		Ok(Some(FuncHandle(self.funcs.len() as u32 - 1)))
+	}
+
+	fn call_handle(&mut self, handle: FuncHandle, args: &[Val]) -> Result<Vec<Val>, Error> {
+		match self.funcs.get_mut(handle.0 as usize) {
+			Some(Resolved { func: Some((func, rets)), .. }) => call_func(&mut self.store, *func, args, rets),
+			Some(Resolved { name, func: None }) => Err(Error::MissingFunction(name.clone())),
+			None => Err(Error::Unsupported(format!("unknown function handle {}", handle.0))),
+		}
+	}
+
+	fn read(&self, memory: &str, offset: u32, len: u32) -> Result<Vec<u8>, Error> {
+		let mut buffer = vec![0; len as usize];
+		self.memory(memory)?
+			.read(&self.store, offset as usize, &mut buffer)
+			.map_err(|_| Error::MemoryAccess { offset, len })?;
+		Ok(buffer)
+	}
+
+	fn write(&mut self, memory: &str, offset: u32, data: &[u8]) -> Result<(), Error> {
+		self.memory(memory)?
+			.write(&mut self.store, offset as usize, data)
+			.map_err(|_| Error::MemoryAccess { offset, len: data.len() as u32 })
+	}
+
+	fn view(&self, memory: &str, offset: u32, len: u32) -> Result<Cow<'_, [u8]>, Error> {
+		let data = self.memory(memory)?.data(&self.store);
+		let range = offset as usize..offset as usize + len as usize;
+		data.get(range).map(Cow::Borrowed).ok_or(Error::MemoryAccess { offset, len })
+	}
+
+	fn view_mut(&mut self, memory: &str, offset: u32, len: u32) -> Result<Option<&mut [u8]>, Error> {
+		let data = self.memory(memory)?.data_mut(&mut self.store);
+		let range = offset as usize..offset as usize + len as usize;
+		data.get_mut(range).map(Some).ok_or(Error::MemoryAccess { offset, len })
+	}
+
+	fn memory_size(&self, memory: &str) -> Result<u64, Error> {
+		let pages: u32 = self.memory(memory)?.current_pages(&self.store).into();
+		Ok(pages as u64 * 65536)
+	}
+
+	fn grow(&mut self, memory: &str, pages: u32) -> Result<(), Error> {
+		let pages = wasmi::core::Pages::new(pages).ok_or_else(|| Error::Runtime(format!("can't grow memory {} by {} pages", memory, pages).into()))?;
+		self.memory(memory)?.grow(&mut self.store, pages).map(drop).map_err(|e| Error::runtime(wasmi::Error::from(e)))
+	}
+
+	fn has_function(&self, name: &str) -> bool {
+		self.instance.get_func(&self.store, name).is_some()
+	}
+
+	fn signature(&self, name: &str) -> Option<(Vec<ValType>, Vec<ValType>)> {
+		let ty = self.instance.get_func(&self.store, name)?.ty(&self.store);
+		Some((ty.params().iter().map(from_wasmi_type).collect(), ty.results().iter().map(from_wasmi_type).collect()))
+	}
+
+	fn globals(&self) -> Result<Vec<(String, Val)>, Error> {
+		let mut globals = Vec::new();
+		for export in self.instance.exports(&self.store) {
+			let name = export.name().to_string();
+			if let Some(global) = export.into_global() {
+				if global.ty(&self.store).mutability().is_mut() {
+					globals.push((name, from_wasmi_value(&global.get(&self.store))?));
+				}
+			}
+		}
+		Ok(globals)
+	}
+
+	fn set_global(&mut self, name: &str, value: Val) -> Result<(), Error> {
+		let global = self.instance.get_global(&self.store, name).ok_or_else(|| Error::Runtime(format!("Global {} is not exported", name).into()))?;
+		global.set(&mut self.store, to_wasmi_value(&value)).map_err(|e| Error::Runtime(e.to_string().into()))
+	}
+
+	fn can_reinstantiate(&self) -> bool {
+		self.module.is_some()
+	}
+
+	fn reinstantiate(&mut self) -> Result<(), Error> {
+		let (module, instantiate) = self.module.as_ref().ok_or_else(|| Error::Unsupported(String::from("re-instantiation of an instance created outside of WasmiBackend")))?;
+		let (store, instance) = instantiate(module)?;
+		self.swap(store, instance);
+		Ok(())
+	}
+
+	fn take_output(&mut self) -> Output {
+		self.output.as_ref().map(OutputCapture::take).unwrap_or_default()
+	}
+
+	fn manifest(&self) -> Option<Manifest> {
+		self.manifest.clone()
+	}
+
+	fn reload(&mut self, wasm: &[u8], validate: &dyn Fn(&Self) -> Result<(), Error>) -> Result<(), Error> {
+		let (module, instantiate) = self.module.take().ok_or_else(|| Error::Unsupported(String::from("reloading an instance created outside of WasmiBackend")))?;
+		let loaded = Module::new(module.engine(), wasm).map_err(Error::runtime).and_then(|loaded| {
+			let (store, instance) = instantiate(&loaded)?;
+			let backend = WasmiBackend::from_instance(store, instance);
+			validate(&backend)?;
+			Ok((loaded, backend))
+		});
+		match loaded {
+			Ok((loaded, backend)) => {
+				self.swap(backend.store, backend.instance);
+				self.module = Some((loaded, instantiate));
+				self.manifest = Manifest::from_module(wasm).ok().flatten();
+				Ok(())
+			}
+			Err(e) => {
+				self.module = Some((module, instantiate));
+				Err(e)
+			}
+		}
+	}
+}
@@ -0,0 +1,627 @@
+use std::sync::{Arc, Mutex};
+
+use wasmedge_bindgen_host_wasmi::*;
+
+// A guest following the bindgen ABI by hand: a bump allocator and exports
+// returning the header, entries and values the macro would generate.
+const GUEST: &str = r#"
+(module
+	(memory (export "memory") 1)
+	(global $top (export "top") (mut i32) (i32.const 1024))
+	(data (i32.const 16) "expect 2 params")
+
+	;; Manifest records: add(i32, i32) -> i32, echo(String) -> String
+	(@custom "wasmedge_bindgen" "\01\03\00add\00\02\06\06\01\06")
+	(@custom "wasmedge_bindgen" "\01\04\00echo\00\01\1f\01\1f")
+
+	(func $allocate (export "allocate") (param $size i32) (result i32)
+		(local $p i32)
+		(local.set $p (global.get $top))
+		(global.set $top (i32.and (i32.add (i32.add (global.get $top) (local.get $size)) (i32.const 7)) (i32.const -8)))
+		(local.get $p))
+
+	(func (export "deallocate") (param i32 i32))
+
+	(func $header (param $flag i32) (param $pointer i32) (param $len i32) (result i32)
+		(local $h i32)
+		(local.set $h (call $allocate (i32.const 9)))
+		(i32.store8 (local.get $h) (local.get $flag))
+		(i32.store offset=1 (local.get $h) (local.get $pointer))
+		(i32.store offset=5 (local.get $h) (local.get $len))
+		(local.get $h))
+
+	(func $entry (param $pointer i32) (param $ty i32) (param $size i32) (result i32)
+		(local $e i32)
+		(local.set $e (call $allocate (i32.const 12)))
+		(i32.store (local.get $e) (local.get $pointer))
+		(i32.store offset=4 (local.get $e) (local.get $ty))
+		(i32.store offset=8 (local.get $e) (local.get $size))
+		(local.get $e))
+
+	;; add(a: i32, b: i32) -> i32
+	(func (export "add") (param $params i32) (param $count i32) (result i32)
+		(local $out i32)
+		(if (i32.ne (local.get $count) (i32.const 2))
+			(then (return (call $header (i32.const 1) (i32.const 16) (i32.const 15)))))
+		(local.set $out (call $allocate (i32.const 4)))
+		(i32.store (local.get $out)
+			(i32.add (i32.load (i32.load (local.get $params))) (i32.load (i32.load offset=8 (local.get $params)))))
+		(call $header (i32.const 0) (call $entry (local.get $out) (i32.const 6) (i32.const 4)) (i32.const 1)))
+
+	;; echo(s: String) -> String, handing the parameter buffer back
+	(func (export "echo") (param $params i32) (param $count i32) (result i32)
+		(call $header (i32.const 0)
+			(call $entry (i32.load (local.get $params)) (i32.const 31) (i32.load offset=4 (local.get $params)))
+			(i32.const 1)))
+
+	(func (export "trap") (param i32 i32) (result i32)
+		unreachable)
+)
+"#;
+
+/// `GUEST` with `replace` applied, checking that it matched.
+fn guest_with(replace: &[(&str, &str)]) -> Vec<u8> {
+	let mut guest = GUEST.to_string();
+	for (from, to) in replace {
+		assert!(guest.contains(from));
+		guest = guest.replace(from, to);
+	}
+	wat::parse_str(guest).unwrap()
+}
+
+fn bindgen() -> Bindgen {
+	let wasm = wat::parse_str(GUEST).unwrap();
+	Bindgen::with_config(WasmiBackend::new(wasm).unwrap(), BindgenConfig::default()).unwrap()
+}
+
+#[test]
+fn scalars() {
+	let mut rv = bindgen().run_wasm("add", params![40, 2]).unwrap().unwrap();
+	assert_eq!(*rv.pop().unwrap().downcast::<i32>().unwrap(), 42);
+}
+
+#[test]
+fn strings() {
+	let mut rv = bindgen().run_wasm("echo", params!["hello wasmi"]).unwrap().unwrap();
+	assert_eq!(*rv.pop().unwrap().downcast::<String>().unwrap(), "hello wasmi");
+}
+
+#[test]
+fn guest_error() {
+	let rv = bindgen().run_wasm("add", params![1]).unwrap();
+	assert_eq!(rv.unwrap_err(), "expect 2 params");
+}
+
+#[test]
+fn trap() {
+	assert!(matches!(bindgen().run_wasm("trap", vec![]), Err(Error::Trap(_))));
+}
+
+#[test]
+fn missing_exports() {
+	assert!(matches!(bindgen().run_wasm("missing", vec![]), Err(Error::MissingFunction(_))));
+	let backend = WasmiBackend::new(wat::parse_str(GUEST).unwrap()).unwrap();
+	assert!(matches!(Bindgen::with_config(backend, BindgenConfig::new().memory("mem")), Err(Error::MissingMemory(_))));
+}
+
+#[test]
+fn allocator_signatures() {
+	let config = BindgenConfig::new().allocator("allocate", AllocSignature::SizeAlign);
+	let error = Bindgen::with_config(WasmiBackend::new(wat::parse_str(GUEST).unwrap()).unwrap(), config).err().unwrap();
+	assert_eq!(error.to_string(), "Invalid config: allocate has signature [I32] -> [I32], expect [I32, I32] -> [I32]");
+	// `add` has the right arity for a `free`, but returns a value
+	let config = BindgenConfig::new().deallocator("add", DeallocSignature::PtrSize);
+	assert!(matches!(Bindgen::with_config(WasmiBackend::new(wat::parse_str(GUEST).unwrap()).unwrap(), config), Err(Error::InvalidConfig(_))));
+}
+
+fn top(bg: &Bindgen) -> Val {
+	bg.backend().globals().unwrap().into_iter().find(|(name, _)| name == "top").unwrap().1
+}
+
+#[test]
+fn snapshot_and_reset() {
+	let mut bg = bindgen();
+	bg.snapshot().unwrap();
+	bg.run_wasm("echo", params!["tenant secret"]).unwrap().unwrap();
+	assert_ne!(top(&bg), Val::I32(1024));
+
+	bg.reset().unwrap();
+	assert_eq!(top(&bg), Val::I32(1024));
+	let memory = bg.backend().read("memory", 0, 65536).unwrap();
+	assert!(!memory.windows(6).any(|w| w == b"secret"));
+	assert!(memory.windows(15).any(|w| w == b"expect 2 params"));
+}
+
+#[test]
+fn isolated_calls() {
+	let backend = WasmiBackend::new(wat::parse_str(GUEST).unwrap()).unwrap();
+	let mut bg = Bindgen::with_config(backend, BindgenConfig::new().isolated(true)).unwrap();
+	for _ in 0..3 {
+		let mut rv = bg.run_wasm("add", params![40, 2]).unwrap().unwrap();
+		assert_eq!(*rv.pop().unwrap().downcast::<i32>().unwrap(), 42);
+		assert_eq!(top(&bg), Val::I32(1024));
+	}
+	assert!(bg.run_wasm("trap", vec![]).is_err());
+	assert_eq!(top(&bg), Val::I32(1024));
+}
+
+#[test]
+fn reset_without_snapshot() {
+	assert!(matches!(bindgen().reset(), Err(Error::NoSnapshot)));
+}
+
+/// `GUEST` with a global it doesn't export, standing for the stack pointer.
+fn stack_guest() -> Bindgen {
+	let wasm = guest_with(&[(
+		"(func (export \"deallocate\") (param i32 i32))",
+		r#"(func (export "deallocate") (param i32 i32))
+	(global $sp (mut i32) (i32.const 4096))
+	(func (export "sp") (result i32) (global.get $sp))
+	(func (export "push") (global.set $sp (i32.sub (global.get $sp) (i32.const 16))))"#,
+	)]);
+	Bindgen::with_config(WasmiBackend::new(wasm).unwrap(), BindgenConfig::default()).unwrap()
+}
+
+#[test]
+fn fresh_snapshots_restore_hidden_globals() {
+	let mut bg = stack_guest();
+	bg.snapshot().unwrap();
+	bg.run_wasm("echo", params!["tenant secret"]).unwrap().unwrap();
+	bg.backend_mut().call("push", &[]).unwrap();
+
+	bg.reset().unwrap();
+	assert_eq!(bg.backend_mut().call("sp", &[]).unwrap(), [Val::I32(4096)]);
+	assert_eq!(top(&bg), Val::I32(1024));
+	// Still fresh, so the next reset instantiates again too
+	bg.backend_mut().call("push", &[]).unwrap();
+	bg.reset().unwrap();
+	assert_eq!(bg.backend_mut().call("sp", &[]).unwrap(), [Val::I32(4096)]);
+}
+
+#[test]
+fn reset_after_memory_growth() {
+	let mut bg = bindgen();
+	bg.run_wasm("echo", params!["before the snapshot"]).unwrap().unwrap();
+	let before = top(&bg);
+	bg.snapshot().unwrap();
+	bg.backend_mut().grow("memory", 2).unwrap();
+	bg.backend_mut().write("memory", 65536, b"tenant secret").unwrap();
+
+	bg.reset().unwrap();
+	assert_eq!(bg.backend().memory_size("memory").unwrap(), 65536);
+	assert_eq!(top(&bg), before);
+	let memory = bg.backend().read("memory", 0, 65536).unwrap();
+	assert!(memory.windows(19).any(|w| w == b"before the snapshot"));
+}
+
+#[test]
+fn reset_to_grown_memory() {
+	let mut bg = bindgen();
+	bg.backend_mut().grow("memory", 1).unwrap();
+	bg.backend_mut().write("memory", 65536, b"on the second page").unwrap();
+	bg.snapshot().unwrap();
+	bg.backend_mut().grow("memory", 1).unwrap();
+
+	bg.reset().unwrap();
+	assert_eq!(bg.backend().memory_size("memory").unwrap(), 2 * 65536);
+	assert_eq!(bg.backend().read("memory", 65536, 18).unwrap(), b"on the second page");
+}
+
+#[test]
+fn recycle_after_trap() {
+	let traps = Arc::new(Mutex::new(Vec::new()));
+	let mut bg = bindgen();
+	let seen = traps.clone();
+	bg.on_trap(move |func_name, _| seen.lock().unwrap().push(func_name.to_string()));
+
+	bg.run_wasm("echo", params!["before the trap"]).unwrap().unwrap();
+	assert!(matches!(bg.run_wasm("trap", vec![]), Err(Error::Trap(_))));
+	assert!(bg.is_poisoned());
+	assert_eq!(bg.trap_count(), 1);
+
+	// The next call runs in a fresh instance
+	let mut rv = bg.run_wasm("add", params![40, 2]).unwrap().unwrap();
+	assert_eq!(*rv.pop().unwrap().downcast::<i32>().unwrap(), 42);
+	assert!(!bg.is_poisoned());
+	assert_eq!(bg.recycle_count(), 1);
+	let memory = bg.backend().read("memory", 0, 65536).unwrap();
+	assert!(!memory.windows(15).any(|w| w == b"before the trap"));
+	assert_eq!(*traps.lock().unwrap(), ["trap"]);
+}
+
+/// `GUEST` as a reactor, whose `_initialize` sets a global it doesn't export.
+fn reactor_guest() -> Bindgen {
+	let wasm = guest_with(&[(
+		"(func (export \"deallocate\") (param i32 i32))",
+		r#"(func (export "deallocate") (param i32 i32))
+	(global $ready (mut i32) (i32.const 0))
+	(func (export "_initialize") (global.set $ready (i32.const 1)))
+	(func (export "ready") (result i32) (global.get $ready))"#,
+	)]);
+	Bindgen::with_config(WasmiBackend::new(wasm).unwrap(), BindgenConfig::default()).unwrap()
+}
+
+#[test]
+fn reactors_initialized_after_recycling() {
+	let mut bg = reactor_guest();
+	bg.initialize().unwrap();
+	bg.snapshot().unwrap();
+	assert!(matches!(bg.run_wasm("trap", vec![]), Err(Error::Trap(_))));
+	assert_eq!(add(&mut bg), 42);
+	assert_eq!(bg.recycle_count(), 1);
+	assert_eq!(bg.backend_mut().call("ready", &[]).unwrap(), [Val::I32(1)]);
+
+	// So is the instance a fresh snapshot is restored with
+	bg.reset().unwrap();
+	assert_eq!(bg.backend_mut().call("ready", &[]).unwrap(), [Val::I32(1)]);
+}
+
+#[test]
+fn caller_errors_dont_poison() {
+	let mut bg = bindgen();
+	// A misspelled export, and one called with the wrong arity
+	assert!(matches!(bg.run_wasm("ad", params![1]), Err(Error::MissingFunction(_))));
+	assert!(matches!(bg.run_wasm("allocate", params![1]), Err(Error::Runtime(_))));
+	assert!(!bg.is_poisoned());
+	assert_eq!(bg.trap_count(), 0);
+}
+
+#[test]
+fn borrowed_results() {
+	let mut bg = bindgen();
+	let message = "a large result".repeat(1000);
+	let results = bg.run_wasm_borrowed("echo", params![message.as_str()]).unwrap().unwrap();
+	assert_eq!(results.len(), 1);
+	let bytes = results.get(0).unwrap();
+	assert!(bytes.is_borrowed());
+	assert_eq!(bytes.ty(), WireType::String);
+	assert_eq!(&bytes[..], message.as_bytes());
+	assert_eq!(*bytes.decode().unwrap().downcast::<String>().unwrap(), message);
+	drop(bytes);
+	results.release().unwrap();
+
+	let rv = bg.run_wasm_borrowed("add", params![1]).unwrap();
+	assert_eq!(rv.err().unwrap(), "expect 2 params");
+}
+
+#[test]
+fn params_filled_in_place() {
+	let mut bg = bindgen();
+	let param = bg.alloc_param(WireType::String, 11).unwrap();
+	bg.fill_param(&param, |buf| buf.copy_from_slice(b"in the gues")).unwrap();
+	let mut rv = bg.run_wasm("echo", vec![Param::Guest(param)]).unwrap().unwrap();
+	assert_eq!(*rv.pop().unwrap().downcast::<String>().unwrap(), "in the gues");
+}
+
+#[test]
+fn params_stale_after_reset() {
+	let mut bg = bindgen();
+	bg.snapshot().unwrap();
+	let param = bg.alloc_param(WireType::String, 4).unwrap();
+	bg.reset().unwrap();
+	assert!(matches!(bg.fill_param(&param, |buf| buf.fill(b'x')), Err(Error::StaleValue)));
+	assert!(matches!(bg.run_wasm("echo", vec![Param::Guest(param)]), Err(Error::StaleValue)));
+
+	let param = bg.alloc_param(WireType::String, 4).unwrap();
+	bg.recycle().unwrap();
+	assert!(matches!(bg.free_param(param), Err(Error::StaleValue)));
+}
+
+fn add(bg: &mut Bindgen<impl Sized>) -> i32 {
+	let mut rv = bg.run_wasm("add", params![40, 2]).unwrap().unwrap();
+	*rv.pop().unwrap().downcast::<i32>().unwrap()
+}
+
+#[test]
+fn manifest() {
+	let bg = bindgen();
+	let add = bg.manifest().unwrap().function("add").unwrap();
+	assert_eq!(add.params, [WireType::I32, WireType::I32]);
+	assert_eq!(add.results, [WireType::I32]);
+	assert!(!add.fallible);
+}
+
+#[test]
+fn reload_keeps_host_functions() {
+	let linker = |module: &wasmi::Module| {
+		let mut store = wasmi::Store::new(module.engine(), ());
+		let mut linker = wasmi::Linker::new(module.engine());
+		linker.func_wrap("env", "bonus", || 1000).unwrap();
+		let instance = linker.instantiate(&mut store, module).and_then(|pre| pre.start(&mut store)).map_err(Error::runtime)?;
+		Ok((store, instance))
+	};
+	let backend = WasmiBackend::with_instantiate(&wasmi::Engine::default(), guest_with(&[]), linker).unwrap();
+	let mut bg = Bindgen::new(backend);
+	bg.snapshot().unwrap();
+	assert_eq!(add(&mut bg), 42);
+
+	// A new version calling a host function, and exporting one more bindgen function
+	bg.reload(guest_with(&[
+		("(module", "(module (import \"env\" \"bonus\" (func $bonus (result i32)))"),
+		("(i32.add (i32.load (i32.load", "(i32.add (call $bonus) (i32.add (i32.load (i32.load"),
+		("(local.get $params)))))", "(local.get $params))))))"),
+		("\\01\\1f\\01\\1f\")", "\\01\\1f\\01\\1f\\01\\04\\00trap\\00\\00\\00\")"),
+	])).unwrap();
+	assert_eq!(add(&mut bg), 1042);
+	assert!(bg.manifest().unwrap().function("trap").is_some());
+	assert!(!bg.has_snapshot());
+}
+
+#[test]
+fn reload_rejects_incompatible_modules() {
+	let mut bg = bindgen();
+	let changed = guest_with(&[("add\\00\\02\\06\\06", "add\\00\\02\\06\\07")]);
+	let err = bg.reload(changed).unwrap_err();
+	assert_eq!(err.to_string(), "Incompatible module: add changed from add[I32, I32] -> [I32] to add[I32, U64] -> [I32]");
+
+	let removed = guest_with(&[("(@custom \"wasmedge_bindgen\" \"\\01\\04\\00echo\\00\\01\\1f\\01\\1f\")", "")]);
+	assert!(matches!(bg.reload(removed), Err(Error::Incompatible(_))));
+
+	let unmarked = wat::parse_str("(module (memory (export \"memory\") 1))").unwrap();
+	assert!(matches!(bg.reload(unmarked), Err(Error::Incompatible(_))));
+
+	let no_allocator = guest_with(&[("(func $allocate (export \"allocate\")", "(func $allocate")]);
+	assert!(matches!(bg.reload(no_allocator), Err(Error::MissingFunction(_))));
+
+	assert!(matches!(bg.reload(b"not wasm"), Err(Error::Incompatible(_))));
+	assert_eq!(add(&mut bg), 42);
+}
+
+#[test]
+fn reload_needs_the_running_manifest() {
+	let unmarked = guest_with(&[
+		("(@custom \"wasmedge_bindgen\" \"\\01\\03\\00add\\00\\02\\06\\06\\01\\06\")", ""),
+		("(@custom \"wasmedge_bindgen\" \"\\01\\04\\00echo\\00\\01\\1f\\01\\1f\")", ""),
+	]);
+	let mut bg = Bindgen::new(WasmiBackend::new(unmarked).unwrap());
+	assert!(bg.manifest().is_none());
+	let err = bg.reload(wat::parse_str(GUEST).unwrap()).unwrap_err();
+	assert_eq!(err.to_string(), "Incompatible module: the running module has no manifest to check the new one against");
+	assert_eq!(add(&mut bg), 42);
+}
+
+#[test]
+fn route_between_instances() {
+	let mut router = Router::new();
+	router.add("a", bindgen()).add("b", bindgen());
+
+	let route = [("a", "echo"), ("a", "echo"), ("b", "echo")];
+	let mut rv = router.route(&route, params!["passed along"]).unwrap().unwrap();
+	assert_eq!(*rv.pop().unwrap().downcast::<String>().unwrap(), "passed along");
+	let memory = router.get("b").unwrap().backend().read("memory", 0, 65536).unwrap();
+	assert!(memory.windows(12).any(|w| w == b"passed along"));
+
+	let rv = router.route(&[("a", "add"), ("b", "echo")], params![1]).unwrap();
+	assert_eq!(rv.unwrap_err(), "expect 2 params");
+	assert!(matches!(router.route(&[("a", "echo"), ("c", "echo")], params!["x"]), Err(Error::MissingModule(_))));
+	assert!(matches!(router.route(&[], vec![]), Err(Error::InvalidConfig(_))));
+}
+
+/// `GUEST` with a `log` function writing its string parameter to stdout and
+/// "logged" to stderr with WASI `fd_write`, then returning it like `echo`.
+fn printing_guest() -> Vec<u8> {
+	guest_with(&[
+		("(module", r#"(module (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))"#),
+		("(data (i32.const 16) \"expect 2 params\")", r#"(data (i32.const 16) "expect 2 params") (data (i32.const 32) "logged")"#),
+		(";; echo(s: String)", r#"(func (export "log") (param $params i32) (param $count i32) (result i32)
+		(local $iov i32)
+		(local.set $iov (call $allocate (i32.const 12)))
+		(i32.store (local.get $iov) (i32.load (local.get $params)))
+		(i32.store offset=4 (local.get $iov) (i32.load offset=4 (local.get $params)))
+		(drop (call $fd_write (i32.const 1) (local.get $iov) (i32.const 1) (i32.add (local.get $iov) (i32.const 8))))
+		(i32.store (local.get $iov) (i32.const 32))
+		(i32.store offset=4 (local.get $iov) (i32.const 6))
+		(drop (call $fd_write (i32.const 2) (local.get $iov) (i32.const 1) (i32.add (local.get $iov) (i32.const 8))))
+		(call $header (i32.const 0)
+			(call $entry (i32.load (local.get $params)) (i32.const 31) (i32.load offset=4 (local.get $params)))
+			(i32.const 1)))
+
+	;; echo(s: String)"#),
+	])
+}
+
+/// `GUEST` importing WASI functions that `WasmiBackend::new` stubs out.
+fn wasi_guest() -> Vec<u8> {
+	guest_with(&[
+		("(module", r#"(module
+	(import "wasi_snapshot_preview1" "environ_sizes_get" (func $environ_sizes_get (param i32 i32) (result i32)))
+	(import "wasi_snapshot_preview1" "random_get" (func $random_get (param i32 i32) (result i32)))
+	(import "wasi_snapshot_preview1" "fd_close" (func $fd_close (param i32) (result i32)))
+	(import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))"#),
+		("(func (export \"trap\")", r#"(func (export "environ_count") (result i32 i32)
+		(i32.store (i32.const 8) (i32.const 7))
+		(call $environ_sizes_get (i32.const 8) (i32.const 12))
+		(i32.load (i32.const 8)))
+
+	(func (export "random") (result i32)
+		(call $random_get (i32.const 100) (i32.const 16)))
+
+	(func (export "random_all") (result i32)
+		(call $random_get (i32.const 100) (i32.const -1)))
+
+	(func (export "close") (result i32)
+		(call $fd_close (i32.const 3)))
+
+	(func (export "exit")
+		(call $proc_exit (i32.const 3)))
+
+	(func (export "trap")"#),
+	])
+}
+
+/// `GUEST` with `divmod(a: i32, b: i32) -> (i32, i32)` flagged direct, and a
+/// direct export returning both values, as guests in languages with
+/// multi-value functions can.
+fn multi_value_guest() -> Vec<u8> {
+	guest_with(&[
+		(r#"(@custom "wasmedge_bindgen" "\01\04\00echo"#, r#"(@custom "wasmedge_bindgen" "\01\06\00divmod\04\02\06\06\02\06\06")
+	(@custom "wasmedge_bindgen" "\01\04\00echo"#),
+		("(func (export \"trap\")", r#"(func (export "divmod") (param i32 i32) (result i32)
+		unreachable)
+
+	(func (export "__wasmedge_bindgen_direct_divmod") (param $a i32) (param $b i32) (result i32 i32)
+		(i32.div_s (local.get $a) (local.get $b))
+		(i32.rem_s (local.get $a) (local.get $b)))
+
+	(func (export "trap")"#),
+	])
+}
+
+#[test]
+fn multi_value_direct_exports() {
+	let mut bg = Bindgen::new(WasmiBackend::new(multi_value_guest()).unwrap());
+	assert!(bg.manifest().unwrap().function("divmod").unwrap().direct);
+	let rv = bg.run_wasm("divmod", params![17, 5]).unwrap().unwrap();
+	assert_eq!((*rv[0].downcast_ref::<i32>().unwrap(), *rv[1].downcast_ref::<i32>().unwrap()), (3, 2));
+	assert_eq!(bg.last_call_stats().allocate_calls, 0);
+}
+
+/// `GUEST` with batch exports: one for `add`, with every call taking two
+/// four-byte parameters, and one for `echo` rejecting every batch.
+fn batch_guest() -> Vec<u8> {
+	guest_with(&[
+		(r#"\01\03\00add\00"#, r#"\01\03\00add\08"#),
+		(r#"\01\04\00echo\00"#, r#"\01\04\00echo\08"#),
+		("(func (export \"trap\")", r#"(func (export "__wasmedge_bindgen_batch_add") (param $params i32) (param $len i32) (result i64)
+		(local $count i32) (local $i i32) (local $in i32) (local $out i32) (local $size i32) (local $o i32)
+		(local.set $count (i32.load (local.get $params)))
+		(local.set $size (i32.add (i32.const 5) (i32.mul (local.get $count) (i32.const 18))))
+		(local.set $out (call $allocate (local.get $size)))
+		(i32.store8 (local.get $out) (i32.const 0))
+		(i32.store offset=1 (local.get $out) (local.get $count))
+		(local.set $in (i32.add (local.get $params) (i32.const 4)))
+		(local.set $o (i32.add (local.get $out) (i32.const 5)))
+		(block $done
+			(loop $next
+				(br_if $done (i32.ge_u (local.get $i) (local.get $count)))
+				;; The size and packed results of a call, read from its size and packed params
+				(i32.store (local.get $o) (i32.const 14))
+				(i32.store8 offset=4 (local.get $o) (i32.const 0))
+				(i32.store offset=5 (local.get $o) (i32.const 1))
+				(i32.store8 offset=9 (local.get $o) (i32.const 6))
+				(i32.store offset=10 (local.get $o) (i32.const 4))
+				(i32.store offset=14 (local.get $o)
+					(i32.add (i32.load offset=13 (local.get $in)) (i32.load offset=22 (local.get $in))))
+				(local.set $in (i32.add (local.get $in) (i32.const 26)))
+				(local.set $o (i32.add (local.get $o) (i32.const 18)))
+				(local.set $i (i32.add (local.get $i) (i32.const 1)))
+				(br $next)))
+		(i64.or (i64.extend_i32_u (local.get $out)) (i64.shl (i64.extend_i32_u (local.get $size)) (i64.const 32))))
+
+	(func (export "__wasmedge_bindgen_batch_echo") (param i32 i32) (result i64)
+		(local $out i32)
+		(local.set $out (call $allocate (i32.const 5)))
+		(i32.store8 (local.get $out) (i32.const 1))
+		(i32.store offset=1 (local.get $out) (i32.const 0x73706f6f)) ;; "oops"
+		(i64.or (i64.extend_i32_u (local.get $out)) (i64.shl (i64.const 5) (i64.const 32))))
+
+	(func (export "trap")"#),
+	])
+}
+
+#[test]
+fn batches() {
+	let mut bg = Bindgen::new(WasmiBackend::new(batch_guest()).unwrap());
+	let results = bg.call_batch("add", (0..100).map(|i| params![i, 2]).collect()).unwrap();
+	assert_eq!(results.len(), 100);
+	for (i, result) in results.into_iter().enumerate() {
+		assert_eq!(*result.unwrap()[0].downcast_ref::<i32>().unwrap(), i as i32 + 2);
+	}
+	assert_eq!(bg.last_call_stats().allocate_calls, 1);
+
+	// A batch rejected as a whole is a guest error, which doesn't poison the instance
+	let results = bg.call_batch("echo", vec![params!["a"], params!["b"]]).unwrap();
+	assert!(results.iter().all(|result| result.as_ref().err().map(String::as_str) == Some("oops")));
+	assert!(!bg.is_poisoned());
+
+	// Parameters already in the guest can't be packed, so the calls are made one by one
+	let param = bg.alloc_param(WireType::String, 1).unwrap();
+	bg.fill_param(&param, |buf| buf.copy_from_slice(b"x")).unwrap();
+	let mut results = bg.call_batch("echo", vec![vec![Param::Guest(param)]]).unwrap();
+	assert_eq!(*results.pop().unwrap().unwrap().pop().unwrap().downcast::<String>().unwrap(), "x");
+}
+
+#[test]
+fn wasi_stubs() {
+	let mut bg = Bindgen::new(WasmiBackend::new(wasi_guest()).unwrap());
+	let backend = bg.backend_mut();
+	assert_eq!(backend.call("environ_count", &[]).unwrap(), [Val::I32(0), Val::I32(0)]);
+	assert_eq!(backend.call("random", &[]).unwrap(), [Val::I32(0)]);
+	assert_ne!(backend.read("memory", 100, 16).unwrap(), [0; 16]);
+	// Past the end of the memory, without allocating the 4 GiB asked for
+	assert_eq!(backend.call("random_all", &[]).unwrap(), [Val::I32(21)]);
+	assert_eq!(backend.call("close", &[]).unwrap(), [Val::I32(52)]);
+	assert!(backend.call("exit", &[]).is_err());
+
+	assert_eq!(add(&mut bg), 42);
+}
+
+#[test]
+fn captured_output() {
+	let printed = Arc::new(Mutex::new(Vec::new()));
+	let mut bg = Bindgen::new(WasmiBackend::new(printing_guest()).unwrap());
+	let seen = printed.clone();
+	bg.on_output(move |func_name, output| seen.lock().unwrap().push((func_name.to_string(), output.clone())));
+
+	let (rv, output) = bg.run_wasm_with_output("log", params!["hello stdout"]).unwrap();
+	assert!(rv.is_ok());
+	assert_eq!(output.stdout, b"hello stdout");
+	assert_eq!(output.stderr, b"logged");
+
+	bg.run_wasm("log", params!["again"]).unwrap().unwrap();
+	assert_eq!(bg.last_call_output().stdout, b"again");
+	bg.run_wasm("echo", params!["quiet"]).unwrap().unwrap();
+	assert!(bg.last_call_output().is_empty());
+
+	let printed = printed.lock().unwrap();
+	assert_eq!(printed.len(), 2);
+	assert_eq!(printed[1].0, "log");
+	assert_eq!(printed[1].1.stdout, b"again");
+}
+
+#[test]
+fn prepared_calls() {
+	let mut bg = bindgen();
+	let prepared = bg.prepare("add").unwrap();
+	assert_eq!(prepared.params(), Some(&[WireType::I32, WireType::I32][..]));
+	for i in 0..100 {
+		let mut rv = bg.run_prepared(&prepared, params![i, 2]).unwrap().unwrap();
+		assert_eq!(*rv.pop().unwrap().downcast::<i32>().unwrap(), i + 2);
+	}
+
+	assert_eq!(bg.run_prepared(&prepared, params![1]).unwrap().unwrap_err(), "Invalid params count, expect 2, got 1");
+	assert_eq!(bg.run_prepared(&prepared, params![1, 2u64]).unwrap().unwrap_err(), "Parameter 1 is U64, expect I32");
+	assert!(matches!(bg.prepare("missing"), Err(Error::MissingFunction(_))));
+}
+
+#[test]
+fn prepared_calls_survive_recycling() {
+	let mut bg = bindgen();
+	let (add, trap) = (bg.prepare("add").unwrap(), bg.prepare("trap").unwrap());
+	assert!(matches!(bg.run_prepared(&trap, vec![]), Err(Error::Trap(_))));
+	assert!(bg.is_poisoned());
+	let mut rv = bg.run_prepared(&add, params![40, 2]).unwrap().unwrap();
+	assert_eq!(*rv.pop().unwrap().downcast::<i32>().unwrap(), 42);
+	assert_eq!(bg.recycle_count(), 1);
+}
+
+#[test]
+fn kept_values() {
+	let mut bg = bindgen();
+	bg.snapshot().unwrap();
+	let mut kept = bg.run_wasm_kept("echo", params!["kept in the guest"]).unwrap().unwrap();
+	let bytes = bg.view_value(&kept[0]).unwrap();
+	assert!(bytes.is_borrowed());
+	assert_eq!(&bytes[..], b"kept in the guest");
+	drop(bytes);
+	let mut echoed = bg.run_wasm_kept("echo", vec![kept.pop().unwrap().into()]).unwrap().unwrap();
+
+	// Gone with the memory it was in
+	bg.reset().unwrap();
+	assert!(matches!(bg.view_value(&echoed[0]), Err(Error::StaleValue)));
+	assert!(matches!(bg.run_wasm("echo", vec![echoed.pop().unwrap().into()]), Err(Error::StaleValue)));
+
+	let backend = WasmiBackend::new(wat::parse_str(GUEST).unwrap()).unwrap();
+	let mut isolated = Bindgen::with_config(backend, BindgenConfig::new().isolated(true)).unwrap();
+	assert!(matches!(isolated.run_wasm_kept("echo", params!["lost"]), Err(Error::Unsupported(_))));
+}
@@ -0,0 +1,72 @@
+//! Compares calls by name with prepared calls, for a function with small arguments.
+//!
+//! Run with `cargo bench --bench prepared`.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use wasmedge_bindgen_host::*;
+
+const GUEST: &str = r#"
+(module
+	(memory (export "memory") 1)
+	(global $top (mut i32) (i32.const 1024))
+	(@custom "wasmedge_bindgen" "\01\03\00add\00\02\06\06\01\06")
+
+	(func $allocate (export "allocate") (param $size i32) (result i32)
+		(local $p i32)
+		(local.set $p (global.get $top))
+		(global.set $top (i32.and (i32.add (i32.add (global.get $top) (local.get $size)) (i32.const 7)) (i32.const -8)))
+		(if (i32.gt_u (global.get $top) (i32.const 60000)) (then (global.set $top (i32.const 1024))))
+		(local.get $p))
+
+	(func (export "deallocate") (param i32 i32))
+
+	(func (export "add") (param $params i32) (param $count i32) (result i32)
+		(local $out i32) (local $entry i32) (local $h i32)
+		(local.set $out (call $allocate (i32.const 4)))
+		(i32.store (local.get $out)
+			(i32.add (i32.load (i32.load (local.get $params))) (i32.load (i32.load offset=8 (local.get $params)))))
+		(local.set $entry (call $allocate (i32.const 12)))
+		(i32.store (local.get $entry) (local.get $out))
+		(i32.store offset=4 (local.get $entry) (i32.const 6))
+		(i32.store offset=8 (local.get $entry) (i32.const 4))
+		(local.set $h (call $allocate (i32.const 9)))
+		(i32.store8 (local.get $h) (i32.const 0))
+		(i32.store offset=1 (local.get $h) (local.get $entry))
+		(i32.store offset=5 (local.get $h) (i32.const 1))
+		(local.get $h))
+)
+"#;
+
+const CALLS: i32 = 20_000;
+const ROUNDS: usize = 10;
+
+/// Time per call of the fastest round, to leave out noise from the machine.
+fn time(mut call: impl FnMut(i32)) -> Duration {
+	let start = Instant::now();
+	for i in 0..CALLS {
+		call(i);
+	}
+	start.elapsed() / CALLS as u32
+}
+
+fn main() {
+	let wasm = wat::parse_str(GUEST).unwrap();
+	let mut bg = Bindgen::builder().bytes(wasm).build().unwrap();
+	let prepared = bg.prepare("add").unwrap();
+
+	let (mut by_name, mut by_handle) = (Duration::MAX, Duration::MAX);
+	// Alternate rounds so both see the same conditions
+	for _ in 0..ROUNDS {
+		by_name = by_name.min(time(|i| {
+			black_box(bg.run_wasm("add", params![i, 2]).unwrap().unwrap());
+		}));
+		by_handle = by_handle.min(time(|i| {
+			black_box(bg.run_prepared(&prepared, params![i, 2]).unwrap().unwrap());
+		}));
+	}
+	println!(" by name: {:?} per call", by_name);
+	println!("prepared: {:?} per call", by_handle);
+	println!("prepared calls take {:.0}% of the time", by_handle.as_secs_f64() / by_name.as_secs_f64() * 100.0);
+}
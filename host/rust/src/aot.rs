@@ -0,0 +1,80 @@
+//! Ahead-of-time compilation of guest modules with WasmEdge's compiler,
+//! cached on disk across runs.
+
+use std::ffi::CStr;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use sha2::{Digest, Sha256};
+#[cfg(feature = "aot")]
+use wasmedge_sys::*;
+use wasmedge_sys::ffi;
+use wasmedge_types::*;
+
+pub(crate) struct AotCache {
+	pub(crate) dir: PathBuf,
+	pub(crate) level: CompilerOptimizationLevel,
+}
+
+impl AotCache {
+	/// Path of the artifact for `wasm`, named after the SHA-256 of the module,
+	/// the version of the WasmEdge library compiling it, the optimization level
+	/// and the version of this crate.
+	fn artifact(&self, wasm: &[u8]) -> PathBuf {
+		let mut hasher = Sha256::new();
+		// NUL separated, with the module last, so that no two keys hash the same bytes
+		for field in [wasmedge_version(), format!("{:?}", self.level), String::from(env!("CARGO_PKG_VERSION"))] {
+			hasher.update(field);
+			hasher.update([0]);
+		}
+		hasher.update(wasm);
+		self.dir.join(format!("{:x}.{}", hasher.finalize(), std::env::consts::DLL_EXTENSION))
+	}
+
+	/// The compiled artifact of `wasm`, compiling it unless already cached.
+	/// `None` when compilation is unavailable or failed, to interpret it instead.
+	pub(crate) fn compiled(&self, wasm: &[u8]) -> Option<PathBuf> {
+		let artifact = self.artifact(wasm);
+		if artifact.exists() {
+			return Some(artifact);
+		}
+		self.compile(wasm, &artifact).ok().map(|_| artifact)
+	}
+
+	#[cfg(feature = "aot")]
+	fn compile(&self, wasm: &[u8], artifact: &Path) -> Result<(), Box<dyn std::error::Error>> {
+		fs::create_dir_all(&self.dir)?;
+		// Written under temporary names and renamed, so that concurrent runs
+		// never load a partial artifact
+		let input = artifact.with_extension(format!("{}.wasm", std::process::id()));
+		let output = artifact.with_extension(format!("{}.tmp", std::process::id()));
+		fs::write(&input, wasm)?;
+		let mut config = Config::create()?;
+		config.set_aot_optimization_level(self.level);
+		config.set_aot_compiler_output_format(CompilerOutputFormat::Native);
+		let compiled = Compiler::create(Some(config)).and_then(|compiler| compiler.compile_from_file(&input, &output));
+		let _ = fs::remove_file(&input);
+		if let Err(e) = compiled {
+			let _ = fs::remove_file(&output);
+			return Err(e.into());
+		}
+		fs::rename(&output, artifact)?;
+		Ok(())
+	}
+
+	#[cfg(not(feature = "aot"))]
+	fn compile(&self, _wasm: &[u8], _artifact: &Path) -> Result<(), Box<dyn std::error::Error>> {
+		Err("built without the aot feature".into())
+	}
+
+	/// Drops an artifact that failed to load, e.g. a corrupt one, to compile
+	/// it again next time.
+	pub(crate) fn evict(&self, artifact: &Path) {
+		let _ = fs::remove_file(artifact);
+	}
+}
+
+/// Version of the WasmEdge library linked in, e.g. `0.10.1`.
+fn wasmedge_version() -> String {
+	unsafe { CStr::from_ptr(ffi::WasmEdge_VersionGet()) }.to_string_lossy().into_owned()
+}
@@ -0,0 +1,48 @@
+//! Replays a recording made with `Bindgen::record_to` against a module and
+//! reports the calls whose outcome changed.
+//!
+//! Usage: bindgen-replay <module.wasm> <recording> [--all]
+
+use std::env;
+use std::fs::File;
+use std::io::BufReader;
+use std::process::exit;
+
+use wasmedge_bindgen_host::*;
+use wasmedge_bindgen_host::record::read_recording;
+
+fn main() {
+	let args: Vec<String> = env::args().collect();
+	if args.len() < 3 {
+		eprintln!("Usage: {} <module.wasm> <recording> [--all]", args[0]);
+		exit(2);
+	}
+	let show_all = args.iter().any(|a| a == "--all");
+
+	let calls = match File::open(&args[2]).and_then(|f| read_recording(BufReader::new(f))) {
+		Ok(calls) => calls,
+		Err(e) => {
+			eprintln!("Can't read recording {}: {}", args[2], e);
+			exit(2);
+		}
+	};
+
+	let mut bg = match Bindgen::builder().wasi(&[], &[], &[]).file(&args[1]).build() {
+		Ok(bg) => bg,
+		Err(e) => {
+			eprintln!("Can't load module {}: {}", args[1], e);
+			exit(2);
+		}
+	};
+
+	let replayed = bg.replay(&calls);
+	let mismatches = replayed.iter().filter(|r| !r.is_match()).count();
+	for r in replayed.iter().filter(|r| show_all || !r.is_match()) {
+		println!("{}", r);
+	}
+	println!("{} calls replayed, {} mismatches", replayed.len(), mismatches);
+
+	if mismatches > 0 {
+		exit(1);
+	}
+}
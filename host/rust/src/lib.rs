@@ -1,260 +1,23 @@
-use std::any::Any;
+use std::borrow::{Borrow, Cow};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
 use std::ptr::NonNull;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
 use core::ops::{Deref, DerefMut};
 
-use num_derive::FromPrimitive;    
-use num_traits::FromPrimitive;
 use wasmedge_sys::*;
 use wasmedge_types::*;
+use wasmedge_types::error::{CoreError, CoreExecutionError, WasmEdgeError};
+use wasmedge_types::ValType as WasmType;
 
-pub enum Param<'a> {
-	I8(i8),
-	U8(u8),
-	I16(i16),
-	U16(u16),
-	I32(i32),
-	U32(u32),
-	I64(i64),
-	U64(u64),
-	F32(f32),
-	F64(f64),
-	Bool(bool),
-	VecI8(&'a Vec<i8>),
-	VecU8(&'a Vec<u8>),
-	VecI16(&'a Vec<i16>),
-	VecU16(&'a Vec<u16>),
-	VecI32(&'a Vec<i32>),
-	VecU32(&'a Vec<u32>),
-	VecI64(&'a Vec<i64>),
-	VecU64(&'a Vec<u64>),
-	String(&'a str),
-}
-
-impl<'a> Param<'a> {
-	fn settle(&self, vm: &Vm, mem: &mut Memory) -> WasmEdgeResult<(i32, i32)> {
-		match self {
-			Param::I8(v) => {
-				let length = 1;
-				let pointer = allocate(vm, length)?;
-				mem.set_data(vec![*v as u8], pointer as u32)?;
-				Ok((pointer, length))
-			}
-			Param::U8(v) => {
-				let length = 1;
-				let pointer = allocate(vm, length)?;
-				mem.set_data(vec![*v], pointer as u32)?;
-				Ok((pointer, length))
-			}
-			Param::I16(v) => {
-				let length = 1;
-				let pointer = allocate(vm, length * 2)?;
-				let bytes = v.to_le_bytes();
-				mem.set_data(bytes, pointer as u32)?;
-				Ok((pointer, length))
-			}
-			Param::U16(v) => {
-				let length = 2;
-				let pointer = allocate(vm, length * 2)?;
-				let bytes = v.to_le_bytes();
-				mem.set_data(bytes, pointer as u32)?;
-				Ok((pointer, length))
-			}
-			Param::I32(v) => {
-				let length = 1;
-				let pointer = allocate(vm, length * 4)?;
-				let bytes = v.to_le_bytes();
-				mem.set_data(bytes, pointer as u32)?;
-				Ok((pointer, length))
-			}
-			Param::U32(v) => {
-				let length = 1;
-				let pointer = allocate(vm, length * 4)?;
-				let bytes = v.to_le_bytes();
-				mem.set_data(bytes, pointer as u32)?;
-				Ok((pointer, length))
-			}
-			Param::I64(v) => {
-				let length = 1;
-				let pointer = allocate(vm, length * 8)?;
-				let bytes = v.to_le_bytes();
-				mem.set_data(bytes, pointer as u32)?;
-				Ok((pointer, length))
-			}
-			Param::U64(v) => {
-				let length = 1;
-				let pointer = allocate(vm, length * 8)?;
-				let bytes = v.to_le_bytes();
-				mem.set_data(bytes, pointer as u32)?;
-				Ok((pointer, length))
-			}
-			Param::F32(v) => {
-				let length = 1;
-				let pointer = allocate(vm, length * 4)?;
-				let bytes = v.to_le_bytes();
-				mem.set_data(bytes, pointer as u32)?;
-				Ok((pointer, length))
-			}
-			Param::F64(v) => {
-				let length = 1;
-				let pointer = allocate(vm, length * 8)?;
-				let bytes = v.to_le_bytes();
-				mem.set_data(bytes, pointer as u32)?;
-				Ok((pointer, length))
-			}
-			Param::Bool(v) => {
-				let length = 1;
-				let pointer = allocate(vm, length)?;
-				let byte: u8 = match v {
-					true => 1,
-					false => 0
-				};
-				mem.set_data(vec![byte], pointer as u32)?;
-				Ok((pointer, length))
-			}
-			Param::VecI8(v) => {
-				let length = v.len() as i32;
-				let pointer = allocate(vm, length)?;
-				let mut bytes = vec![0; length as usize];
-				for (pos, iv) in v.iter().enumerate() {
-					bytes[pos] = *iv as u8;
-				}
-				mem.set_data(bytes, pointer as u32)?;
-				Ok((pointer, length))
-			}
-			Param::VecU8(v) => {
-				let length = v.len() as i32;
-				let pointer = allocate(vm, length)?;
-				let mut bytes = vec![0; length as usize];
-				for (pos, iv) in v.iter().enumerate() {
-					bytes[pos] = *iv;
-				}
-				mem.set_data(bytes, pointer as u32)?;
-				Ok((pointer, length))
-			}
-			Param::VecI16(v) => {
-				let length = v.len() as i32;
-				let pointer = allocate(vm, length * 2)?;
-				let mut bytes = vec![0; length as usize * 2];
-				for (pos, iv) in v.iter().enumerate() {
-					let b = iv.to_le_bytes();
-					for i in 0..2 {
-						bytes[pos * 2 + i] = b[i];
-					}
-				}
-				mem.set_data(bytes, pointer as u32)?;
-				Ok((pointer, length))
-			}
-			Param::VecU16(v) => {
-				let length = v.len() as i32;
-				let pointer = allocate(vm, length * 2)?;
-				let mut bytes = vec![0; length as usize * 2];
-				for (pos, iv) in v.iter().enumerate() {
-					let b = iv.to_le_bytes();
-					for i in 0..2 {
-						bytes[pos * 2 + i] = b[i];
-					}
-				}
-				mem.set_data(bytes, pointer as u32)?;
-				Ok((pointer, length))
-			}
-			Param::VecI32(v) => {
-				let length = v.len() as i32;
-				let pointer = allocate(vm, length * 4)?;
-				let mut bytes = vec![0; length as usize * 4];
-				for (pos, iv) in v.iter().enumerate() {
-					let b = iv.to_le_bytes();
-					for i in 0..4 {
-						bytes[pos * 4 + i] = b[i];
-					}
-				}
-				mem.set_data(bytes, pointer as u32)?;
-				Ok((pointer, length))
-			}
-			Param::VecU32(v) => {
-				let length = v.len() as i32;
-				let pointer = allocate(vm, length * 4)?;
-				let mut bytes = vec![0; length as usize * 4];
-				for (pos, iv) in v.iter().enumerate() {
-					let b = iv.to_le_bytes();
-					for i in 0..4 {
-						bytes[pos * 4 + i] = b[i];
-					}
-				}
-				mem.set_data(bytes, pointer as u32)?;
-				Ok((pointer, length))
-			}
-			Param::VecI64(v) => {
-				let length = v.len() as i32;
-				let pointer = allocate(vm, length * 8)?;
-				let mut bytes = vec![0; length as usize * 8];
-				for (pos, iv) in v.iter().enumerate() {
-					let b = iv.to_le_bytes();
-					for i in 0..8 {
-						bytes[pos * 8 + i] = b[i];
-					}
-				}
-				mem.set_data(bytes, pointer as u32)?;
-				Ok((pointer, length))
-			}
-			Param::VecU64(v) => {
-				let length = v.len() as i32;
-				let pointer = allocate(vm, length * 8)?;
-				let mut bytes = vec![0; length as usize * 8];
-				for (pos, iv) in v.iter().enumerate() {
-					let b = iv.to_le_bytes();
-					for i in 0..8 {
-						bytes[pos * 8 + i] = b[i];
-					}
-				}
-				mem.set_data(bytes, pointer as u32)?;
-				Ok((pointer, length))
-			}
-			Param::String(v) => {
-				let bytes = v.as_bytes().to_vec();
-				let length = bytes.len() as i32;
-				let pointer = allocate(vm, length)?;
-				mem.set_data(bytes, pointer as u32)?;
-				Ok((pointer, length))
-			}
-		}
-	}
-}
+use aot::AotCache;
 
-fn allocate(vm: &Vm, size: i32) -> WasmEdgeResult<i32> {
-	match vm.run_function("allocate", vec![WasmValue::from_i32(size)]) {
-		Ok(rv) => {
-			Ok(rv[0].to_i32())
-		}
-		Err(e) => {
-			Err(e)
-		}
-	}
-}
+mod aot;
+mod wasi;
 
-#[derive(FromPrimitive)]
-enum RetTypes {
-	U8 = 1,
-	I8 = 2,
-	U16 = 3,
-	I16 = 4,
-	U32 = 5,
-	I32 = 6,
-	U64 = 7,
-	I64 = 8,
-	F32 = 9,
-	F64 = 10,
-	Bool = 11,
-	Char = 12,
-	U8Array = 21,
-	I8Array = 22,
-	U16Array = 23,
-	I16Array = 24,
-	U32Array = 25,
-	I32Array = 26,
-	U64Array = 27,
-	I64Array = 28,
-	String = 31,
-}
+pub use wasmedge_bindgen_host_core::{params, record, AllocSignature, AsyncBindgen, Backend, BindgenConfig, CallStats, DeallocSignature, Encoded, Error, FuncHandle, GuestBytes, GuestParam, GuestResults, GuestValue, Locked, Manifest, Metrics, Outcome, Output, Param, PreparedCall, RecordedCall, Replayed, RunWasm, Signature, Val, ValType, Value, WasmResult, WireType};
 
 // Like Arc but don't check clone count when get mut
 #[derive(Copy)]
@@ -271,7 +34,7 @@ impl Deref for VmArc {
     #[inline]
     fn deref(&self) -> &Vm {
 		unsafe {
-			&self.inner.as_ref()
+			self.inner.as_ref()
 		}
     }
 }
@@ -288,235 +51,591 @@ impl DerefMut for VmArc {
 impl Clone for VmArc {
 	#[inline]
     fn clone(&self) -> VmArc {
-		Self {inner: self.inner}
+		*self
 	}
 }
 
-pub struct Bindgen {
+fn to_wasm_value(v: &Val) -> WasmValue {
+	match *v {
+		Val::I32(v) => WasmValue::from_i32(v),
+		Val::I64(v) => WasmValue::from_i64(v),
+		Val::F32(v) => WasmValue::from_f32(v),
+		Val::F64(v) => WasmValue::from_f64(v),
+	}
+}
+
+fn from_wasm_value(v: &WasmValue) -> Result<Val, Error> {
+	match v.ty() {
+		WasmType::I32 => Ok(Val::I32(v.to_i32())),
+		WasmType::I64 => Ok(Val::I64(v.to_i64())),
+		WasmType::F32 => Ok(Val::F32(v.to_f32())),
+		WasmType::F64 => Ok(Val::F64(v.to_f64())),
+		ty => Err(Error::InvalidReturn(format!("Unsupported value type {:?}", ty))),
+	}
+}
+
+fn from_wasm_type(ty: WasmType) -> ValType {
+	match ty {
+		WasmType::I32 => ValType::I32,
+		WasmType::I64 => ValType::I64,
+		WasmType::F32 => ValType::F32,
+		WasmType::F64 => ValType::F64,
+		WasmType::V128 => ValType::V128,
+		WasmType::FuncRef => ValType::FuncRef,
+		WasmType::ExternRef => ValType::ExternRef,
+	}
+}
+
+/// A trap when the engine or a host function stopped the guest midway. Calls
+/// rejected before running, e.g. for mismatched arguments, are runtime errors.
+fn call_error<E>(e: E) -> Error
+where
+	E: Borrow<WasmEdgeError> + std::error::Error + Send + Sync + 'static,
+{
+	match e.borrow() {
+		WasmEdgeError::Core(CoreError::Execution(CoreExecutionError::FuncSigMismatch)) => Error::runtime(e),
+		WasmEdgeError::Core(CoreError::Execution(_)) | WasmEdgeError::User(_) => Error::trap(e),
+		_ => Error::runtime(e),
+	}
+}
+
+/// [`Backend`] running guests in a wasmedge-sys `Vm`.
+pub struct WasmEdgeBackend {
 	vm: VmArc, // Can't use Arc because vm can be get_mut after cloned for hostfunc
+	manifest: Option<Manifest>,
+	// The module loaded in the Vm, when known, to load it again if a reload
+	// fails to instantiate
+	loaded: Option<Arc<Module>>,
+	// Exports of a module being checked before a reload
+	checking: Option<Exports>,
+	output: Option<Arc<Mutex<Output>>>,
+	// Bumped whenever the instance in the Vm may have changed, shared with the clones
+	epoch: Arc<AtomicU64>,
+	// Epoch of the instance the handles below were looked up in
+	resolved_at: u64,
+	funcs: Vec<Resolved>,
+	memory: Option<Memory>,
 }
 
-impl Clone for Bindgen {
+// The handles are only used together with the Vm, which is already shared
+unsafe impl Send for WasmEdgeBackend {}
+unsafe impl Sync for WasmEdgeBackend {}
+
+/// Exports of a module loaded but not instantiated: signatures of the
+/// functions, and minimum sizes of the memories in bytes.
+struct Exports {
+	funcs: HashMap<String, (Vec<ValType>, Vec<ValType>)>,
+	memories: HashMap<String, u64>,
+}
+
+impl Exports {
+	fn of(module: &Module) -> Result<Self, Error> {
+		let mut exports = Exports { funcs: HashMap::new(), memories: HashMap::new() };
+		for export in module.exports() {
+			match export.ty().map_err(Error::runtime)? {
+				ExternalInstanceType::Func(ty) => {
+					let signature = (ty.params_type_iter().map(from_wasm_type).collect(), ty.returns_type_iter().map(from_wasm_type).collect());
+					exports.funcs.insert(export.name().to_string(), signature);
+				}
+				ExternalInstanceType::Memory(ty) => {
+					exports.memories.insert(export.name().to_string(), *ty.limit().start() as u64 * 65536);
+				}
+				_ => {}
+			}
+		}
+		Ok(exports)
+	}
+}
+
+/// A function resolved for calls by handle.
+struct Resolved {
+	name: String,
+	func: Option<Function>,
+}
+
+impl Clone for WasmEdgeBackend {
+	// The clone looks the handles up again on its first call
 	fn clone(&self) -> Self {
-		Bindgen {
+		WasmEdgeBackend {
 			vm: self.vm,
+			manifest: self.manifest.clone(),
+			loaded: self.loaded.clone(),
+			checking: None,
+			output: self.output.clone(),
+			epoch: self.epoch.clone(),
+			resolved_at: u64::MAX,
+			funcs: self.funcs.iter().map(|resolved| Resolved { name: resolved.name.clone(), func: None }).collect(),
+			memory: None,
 		}
 	}
 }
 
-unsafe impl Send for Bindgen {}
-unsafe impl Sync for Bindgen {}
+impl WasmEdgeBackend {
+	fn new(vm: Vm, manifest: Option<Manifest>) -> Self {
+		let mut backend = WasmEdgeBackend {
+			vm: VmArc {inner: Box::leak(Box::new(vm)).into()},
+			manifest,
+			loaded: None,
+			checking: None,
+			output: None,
+			epoch: Arc::new(AtomicU64::new(0)),
+			resolved_at: u64::MAX,
+			funcs: Vec::new(),
+			memory: None,
+		};
+		backend.refresh();
+		backend
+	}
 
-impl Bindgen {
-	pub fn new(vm: Vm) -> Self {
+	fn instance(&self) -> Result<Instance, Error> {
+		self.vm.active_module().map_err(Error::runtime)
+	}
 
-		let vm_inner = Box::new(vm);
-		Bindgen {
-			vm: VmArc {inner: Box::leak(vm_inner).into()},
+	// Loads `module` in the Vm and instantiates it as the active module
+	fn activate(&mut self, module: &Module) -> WasmEdgeResult<()> {
+		self.vm.load_wasm_from_module(module)?;
+		self.vm.validate()?;
+		self.vm.instantiate()
+	}
+
+	// Whether the handles come from the instance now in the Vm
+	fn is_fresh(&self) -> bool {
+		self.resolved_at == self.epoch.load(Ordering::Acquire)
+	}
+
+	// Marks the handles of every backend sharing the Vm as out of date
+	fn invalidate(&self) {
+		self.epoch.fetch_add(1, Ordering::AcqRel);
+	}
+
+	// Looks the handles up again if the instance changed since
+	fn refresh(&mut self) {
+		let epoch = self.epoch.load(Ordering::Acquire);
+		if self.resolved_at == epoch {
+			return;
+		}
+		let instance = self.instance().ok();
+		for resolved in self.funcs.iter_mut() {
+			resolved.func = instance.as_ref().and_then(|instance| instance.get_func(&resolved.name).ok());
 		}
+		self.memory = instance.and_then(|instance| instance.get_memory("memory").ok());
+		self.resolved_at = epoch;
 	}
 
-	pub fn instantiate(&mut self) {
-		_ = self.vm.instantiate();
+	fn lookup_memory(&self, name: &str) -> Result<Memory, Error> {
+		self.instance()?.get_memory(name).map_err(|_| Error::MissingMemory(name.to_string()))
 	}
 
-	pub fn vm(&mut self) -> &mut Vm {
-		unsafe {
-			self.vm.inner.as_mut()
+	fn with_memory<R>(&self, name: &str, f: impl FnOnce(&Memory) -> WasmEdgeResult<R>) -> Result<R, Error> {
+		match &self.memory {
+			Some(memory) if name == "memory" && self.is_fresh() => f(memory),
+			_ => f(&self.lookup_memory(name)?),
+		}.map_err(Error::runtime)
+	}
+
+	fn with_memory_mut<R>(&mut self, name: &str, f: impl FnOnce(&mut Memory) -> WasmEdgeResult<R>) -> Result<R, Error> {
+		self.refresh();
+		if name != "memory" || self.memory.is_none() {
+			return f(&mut self.lookup_memory(name)?).map_err(Error::runtime);
 		}
+		f(self.memory.as_mut().unwrap()).map_err(Error::runtime)
 	}
 
-	pub fn run_wasm(&mut self, func_name: impl AsRef<str>, inputs: Vec<Param>) -> WasmEdgeResult<Result<Vec<Box<dyn Any + Send + Sync>>, String>> {
-		let inputs_count = inputs.len() as i32;
+	fn call_func(&self, func: &Function, args: &[Val]) -> Result<Vec<Val>, Error> {
+		let rets = self.vm.run_func(func, args.iter().map(to_wasm_value)).map_err(call_error)?;
+		rets.iter().map(from_wasm_value).collect()
+	}
+}
 
-		// allocate new frame for passing pointers
-		let pointer_of_pointers = match self.vm.run_function("allocate", vec![WasmValue::from_i32(inputs_count * 4 * 2)]) {
-			Ok(rv) => {
-				rv[0].to_i32()
-			}
-			Err(e) => {
-				return Err(e);
+impl Backend for WasmEdgeBackend {
+	fn call(&mut self, name: &str, args: &[Val]) -> Result<Vec<Val>, Error> {
+		let func = self.instance()?.get_func(name).map_err(|_| Error::MissingFunction(name.to_string()))?;
+		self.call_func(&func, args)
+	}
+
+	fn resolve(&mut self, name: &str) -> Result<Option<FuncHandle>, Error> {
+		if let Some(index) = self.funcs.iter().position(|resolved| resolved.name == name) {
+			return Ok(Some(FuncHandle(index as u32)));
+		}
+		self.refresh();
+		let func = self.instance()?.get_func(name).map_err(|_| Error::MissingFunction(name.to_string()))?;
+		self.funcs.push(Resolved { name: name.to_string(), func: Some(func) });
+		Ok(Some(FuncHandle(self.funcs.len() as u32 - 1)))
+	}
+
+	fn call_handle(&mut self, handle: FuncHandle, args: &[Val]) -> Result<Vec<Val>, Error> {
+		self.refresh();
+		match self.funcs.get(handle.0 as usize) {
+			Some(Resolved { func: Some(func), .. }) => self.call_func(func, args),
+			Some(Resolved { name, func: None }) => Err(Error::MissingFunction(name.clone())),
+			None => Err(Error::Unsupported(format!("unknown function handle {}", handle.0))),
+		}
+	}
+
+	fn read(&self, memory: &str, offset: u32, len: u32) -> Result<Vec<u8>, Error> {
+		self.with_memory(memory, |memory| memory.get_data(offset, len))
+	}
+
+	fn write(&mut self, memory: &str, offset: u32, data: &[u8]) -> Result<(), Error> {
+		self.with_memory_mut(memory, |memory| memory.set_data(data, offset))
+	}
+
+	fn view(&self, memory: &str, offset: u32, len: u32) -> Result<Cow<'_, [u8]>, Error> {
+		if len == 0 {
+			return Ok(Cow::Borrowed(&[]));
+		}
+		let pointer = self.with_memory(memory, |memory| memory.data_pointer(offset, len))?;
+		// The memory can't grow or be written while the backend is borrowed
+		Ok(Cow::Borrowed(unsafe { std::slice::from_raw_parts(pointer, len as usize) }))
+	}
+
+	fn view_mut(&mut self, memory: &str, offset: u32, len: u32) -> Result<Option<&mut [u8]>, Error> {
+		if len == 0 {
+			return Ok(Some(&mut []));
+		}
+		let pointer = self.with_memory_mut(memory, |memory| memory.data_pointer_mut(offset, len))?;
+		// The memory can't be used otherwise while the backend is borrowed
+		Ok(Some(unsafe { std::slice::from_raw_parts_mut(pointer, len as usize) }))
+	}
+
+	fn memory_size(&self, memory: &str) -> Result<u64, Error> {
+		if let Some(exports) = &self.checking {
+			return exports.memories.get(memory).copied().ok_or_else(|| Error::MissingMemory(memory.to_string()));
+		}
+		self.with_memory(memory, |memory| Ok(memory.size() as u64 * 65536))
+	}
+
+	fn grow(&mut self, memory: &str, pages: u32) -> Result<(), Error> {
+		self.with_memory_mut(memory, |memory| memory.grow(pages))
+	}
+
+	fn has_function(&self, name: &str) -> bool {
+		if let Some(exports) = &self.checking {
+			return exports.funcs.contains_key(name);
+		}
+		match self.instance() {
+			Ok(instance) => instance.get_func(name).is_ok(),
+			Err(_) => false,
+		}
+	}
+
+	fn signature(&self, name: &str) -> Option<(Vec<ValType>, Vec<ValType>)> {
+		if let Some(exports) = &self.checking {
+			return exports.funcs.get(name).cloned();
+		}
+		let ty = self.instance().ok()?.get_func(name).ok()?.ty().ok()?;
+		Some((ty.params_type_iter().map(from_wasm_type).collect(), ty.returns_type_iter().map(from_wasm_type).collect()))
+	}
+
+	fn globals(&self) -> Result<Vec<(String, Val)>, Error> {
+		let instance = self.instance()?;
+		let mut globals = Vec::new();
+		for name in instance.global_names().unwrap_or_default() {
+			let global = instance.get_global(&name).map_err(Error::runtime)?;
+			if global.ty().map_err(Error::runtime)?.mutability() == Mutability::Var {
+				globals.push((name, from_wasm_value(&global.get_value())?));
 			}
-		};
+		}
+		Ok(globals)
+	}
 
-		let mut memory = self.vm.active_module().unwrap().get_memory("memory").unwrap();
+	fn set_global(&mut self, name: &str, value: Val) -> Result<(), Error> {
+		let mut global = self.instance()?
+			.get_global(name).map_err(Error::runtime)?;
+		global.set_value(to_wasm_value(&value)).map_err(Error::runtime)
+	}
 
-		for (pos, inp) in inputs.iter().enumerate() {
-			let sr = inp.settle(&self.vm, &mut memory);
+	fn can_reinstantiate(&self) -> bool {
+		true
+	}
 
-			let (pointer, length_of_input) = match sr {
-				Ok(r) => {
-					(r.0, r.1)
-				}
-				Err(e) => {
-					return Err(e);
-				}
-			};
+	// Instantiates the module loaded in the Vm again
+	fn reinstantiate(&mut self) -> Result<(), Error> {
+		self.invalidate();
+		self.vm.instantiate().map_err(Error::runtime)?;
+		self.refresh();
+		Ok(())
+	}
 
-			memory.set_data(pointer.to_le_bytes(), pointer_of_pointers as u32 + pos as u32 * 4 * 2)?;
-			memory.set_data(length_of_input.to_le_bytes(), pointer_of_pointers as u32 + pos as u32 * 4 * 2 + 4)?;
+	fn take_output(&mut self) -> Output {
+		match &self.output {
+			Some(output) => std::mem::take(&mut *output.lock().unwrap_or_else(|e| e.into_inner())),
+			None => Output::default(),
 		}
+	}
 
-		let rets = self.vm.run_function(func_name, vec![WasmValue::from_i32(pointer_of_pointers), WasmValue::from_i32(inputs_count)])?;
-		// Don't need to deallocate because the memory will be loaded and free in the wasm
-		// self.vm.run_function("deallocate", vec![WasmValue::from_i32(pointer_of_pointers), WasmValue::from_i32(inputs_count * 4 * 2)])?;
+	fn manifest(&self) -> Option<Manifest> {
+		self.manifest.clone()
+	}
 
-		if rets.len() != 1 {
-			return Ok(Err(String::from("Invalid return value")));
+	// The new module is loaded and validated, and its exports checked by
+	// `validate`, before it replaces the module loaded in the Vm and is
+	// instantiated once, linking against the imports registered in the Vm.
+	// Should that fail, the old module is loaded again when known; the active
+	// instance is kept either way.
+	fn reload(&mut self, wasm: &[u8], validate: &dyn Fn(&Self) -> Result<(), Error>) -> Result<(), Error> {
+		let module = Loader::create(None).and_then(|loader| loader.from_bytes(wasm)).map_err(Error::runtime)?;
+		Validator::create(None).and_then(|validator| validator.validate(&module)).map_err(Error::runtime)?;
+		self.checking = Some(Exports::of(&module)?);
+		let checked = validate(self);
+		self.checking = None;
+		checked?;
+		if let Err(e) = self.activate(&module) {
+			if let Some(old) = self.loaded.clone() {
+				let _ = self.vm.load_wasm_from_module(&old).and_then(|_| self.vm.validate());
+			}
+			self.invalidate();
+			return Err(Error::runtime(e));
 		}
-		let rvec = memory.get_data(rets[0].to_i32() as u32, 9)?;
-		let _ = self.vm.run_function("deallocate", vec![WasmValue::from_i32(rets[0].to_i32()), WasmValue::from_i32(9)]);
-
-		let flag = rvec[0];
-		let ret_pointer = i32::from_le_bytes(rvec[1..5].try_into().unwrap());
-		let ret_len = i32::from_le_bytes(rvec[5..9].try_into().unwrap());
-		match flag {
-			0 => Ok(Ok(self.parse_result(ret_pointer, ret_len))),
-			_ => Ok(Err(self.parse_error(ret_pointer, ret_len))),
+		self.invalidate();
+		self.refresh();
+		self.loaded = Some(Arc::new(module));
+		self.manifest = Manifest::from_module(wasm).ok().flatten();
+		Ok(())
+	}
+}
+
+/// Chains bindgen calls between modules instantiated in wasmedge-sys `Vm`s.
+pub type Router = wasmedge_bindgen_host_core::Router<WasmEdgeBackend>;
+
+/// Calls bindgen functions of the module instantiated in a wasmedge-sys `Vm`.
+///
+/// Dereferences to the runtime independent [`wasmedge_bindgen_host_core::Bindgen`],
+/// which provides `run_wasm` and `config`. Its `recycle` instantiates the
+/// module loaded in the `Vm` again, and its `reload` checks the exports of the
+/// new module before instantiating it in place of the old one.
+#[derive(Clone)]
+pub struct Bindgen {
+	inner: wasmedge_bindgen_host_core::Bindgen<WasmEdgeBackend>,
+}
+
+impl Deref for Bindgen {
+	type Target = wasmedge_bindgen_host_core::Bindgen<WasmEdgeBackend>;
+
+	fn deref(&self) -> &Self::Target {
+		&self.inner
+	}
+}
+
+impl DerefMut for Bindgen {
+	fn deref_mut(&mut self) -> &mut Self::Target {
+		&mut self.inner
+	}
+}
+
+impl From<Bindgen> for wasmedge_bindgen_host_core::Bindgen<WasmEdgeBackend> {
+	fn from(bindgen: Bindgen) -> Self {
+		bindgen.inner
+	}
+}
+
+impl Bindgen {
+	pub fn new(vm: Vm) -> Self {
+		Bindgen {
+			inner: wasmedge_bindgen_host_core::Bindgen::new(WasmEdgeBackend::new(vm, None)),
 		}
 	}
 
-	fn parse_error(&self, ret_pointer: i32, ret_len: i32) -> String {
-		let memory = self.vm.active_module().unwrap().get_memory("memory").unwrap();
-		let err_bytes = memory.get_data(ret_pointer as u32, ret_len as u32).unwrap();
-		let _ = self.vm.run_function("deallocate", vec![WasmValue::from_i32(ret_pointer), WasmValue::from_i32(ret_len)]);
-		String::from_utf8(err_bytes).unwrap_or_default()
+	/// Creates a `Bindgen` for an already instantiated `vm`, checking that
+	/// the allocator and memory exports named in `config` are present with
+	/// the signatures it names.
+	pub fn with_config(vm: Vm, config: BindgenConfig) -> Result<Self, Error> {
+		Ok(Bindgen {
+			inner: wasmedge_bindgen_host_core::Bindgen::with_config(WasmEdgeBackend::new(vm, None), config)?,
+		})
 	}
 
-	fn parse_result(&self, ret_pointer: i32, ret_len: i32) -> Vec<Box<dyn Any + Send + Sync>> {
-		let size = ret_len as usize;
-		let memory = self.vm.active_module().unwrap().get_memory("memory").unwrap();
-		let p_data = memory.get_data(ret_pointer as u32, size as u32 * 3 * 4).unwrap();
-		let _ = self.vm.run_function("deallocate", vec![WasmValue::from_i32(ret_pointer), WasmValue::from_i32(size as i32 * 3 * 4)]);
+	/// Like [`with_config`](Self::with_config), for a module whose manifest is
+	/// known, e.g. read with [`Manifest::from_module`] from the bytes it was
+	/// loaded from. Calls then use the packed, direct and batch exports it flags.
+	pub fn with_manifest(vm: Vm, config: BindgenConfig, manifest: Manifest) -> Result<Self, Error> {
+		Ok(Bindgen {
+			inner: wasmedge_bindgen_host_core::Bindgen::with_config(WasmEdgeBackend::new(vm, Some(manifest)), config)?,
+		})
+	}
 
-		let mut p_values = vec![0; size * 3];
+	/// Loads a module into a new `Vm` and instantiates it, see [`BindgenBuilder`].
+	pub fn builder() -> BindgenBuilder {
+		BindgenBuilder::default()
+	}
 
-		for i in 0..size * 3 {
-			p_values[i] = i32::from_le_bytes(p_data[i*4..(i+1)*4].try_into().unwrap());
+	/// Instantiates the running module again, like [`recycle`](wasmedge_bindgen_host_core::Bindgen::recycle).
+	pub fn instantiate(&mut self) -> Result<(), Error> {
+		self.inner.recycle()
+	}
+
+	/// Shares the instance between tasks, running calls off the executor threads.
+	pub fn into_async(self) -> AsyncBindgen<WasmEdgeBackend> {
+		self.inner.into_async()
+	}
+
+	/// Spreads calls over `bindgens`, like [`AsyncBindgen::pooled`].
+	pub fn into_async_pool(bindgens: impl IntoIterator<Item = Bindgen>) -> AsyncBindgen<WasmEdgeBackend> {
+		AsyncBindgen::pooled(bindgens.into_iter().map(|bindgen| bindgen.inner))
+	}
+
+	/// The `Vm` running the module. Handles looked up in its instance are
+	/// looked up again on the next call, in case it was changed.
+	pub fn vm(&mut self) -> &mut Vm {
+		let backend = self.inner.backend_mut();
+		backend.invalidate();
+		unsafe {
+			backend.vm.inner.as_mut()
 		}
+	}
+}
 
-		let mut results: Vec<Box<dyn Any + Send + Sync>> = Vec::with_capacity(size);
+fn strs(items: &[String]) -> Vec<&str> {
+	items.iter().map(String::as_str).collect()
+}
 
-		for i in 0..size {
-			let bytes = memory.get_data(p_values[i*3] as u32, p_values[i*3+2] as u32).unwrap();
-			let _ = self.vm.run_function("deallocate", vec![WasmValue::from_i32(p_values[i*3]), WasmValue::from_i32(p_values[i*3+2])]);
-			match FromPrimitive::from_i32(p_values[i*3+1]) {
-				Some(RetTypes::U8) => {
-					results.push(Box::new(bytes[0]));
-				}
-				Some(RetTypes::I8) => {
-					results.push(Box::new(bytes[0] as i8));
-				}
-				Some(RetTypes::U16) => {
-					let v = u16::from_le_bytes(bytes.try_into().unwrap());
-					results.push(Box::new(v));
-				}
-				Some(RetTypes::I16) => {
-					let v = i16::from_le_bytes(bytes.try_into().unwrap());
-					results.push(Box::new(v));
-				}
-				Some(RetTypes::U32) => {
-					let v = u32::from_le_bytes(bytes.try_into().unwrap());
-					results.push(Box::new(v));
-				}
-				Some(RetTypes::I32) => {
-					let v = i32::from_le_bytes(bytes.try_into().unwrap());
-					results.push(Box::new(v));
-				}
-				Some(RetTypes::U64) => {
-					let v = u64::from_le_bytes(bytes.try_into().unwrap());
-					results.push(Box::new(v));
-				}
-				Some(RetTypes::I64) => {
-					let v = i64::from_le_bytes(bytes.try_into().unwrap());
-					results.push(Box::new(v));
-				}
-				Some(RetTypes::F32) => {
-					let v = f32::from_le_bytes(bytes.try_into().unwrap());
-					results.push(Box::new(v));
-				}
-				Some(RetTypes::F64) => {
-					let v = f64::from_le_bytes(bytes.try_into().unwrap());
-					results.push(Box::new(v));
-				}
-				Some(RetTypes::Bool) => {
-					results.push(Box::new(bytes[0] == 1 as u8));
-				}
-				Some(RetTypes::Char) => {
-					let v = u32::from_le_bytes(bytes.try_into().unwrap());
-					results.push(Box::new(char::from_u32(v)));
-				}
-				Some(RetTypes::U8Array) => {
-					let len = bytes.len();
-					let mut v = vec![0; len];
-					for i in 0..len {
-						v[i] = bytes[i] as u8;
-					}
-					results.push(Box::new(v));
-				}
-				Some(RetTypes::I8Array) => {
-					let len = bytes.len();
-					let mut v = vec![0; len];
-					for i in 0..len {
-						v[i] = bytes[i] as i8;
-					}
-					results.push(Box::new(v));
-				}
-				Some(RetTypes::U16Array) => {
-					let len = bytes.len() / 2;
-					let mut v = vec![0; len];
-					for i in 0..len {
-						v[i] = u16::from_le_bytes(bytes[i*2..(i+1)*2].try_into().unwrap());
-					}
-					results.push(Box::new(v));
-				}
-				Some(RetTypes::I16Array) => {
-					let len = bytes.len() / 2;
-					let mut v = vec![0; len];
-					for i in 0..len {
-						v[i] = i16::from_le_bytes(bytes[i*2..(i+1)*2].try_into().unwrap());
-					}
-					results.push(Box::new(v));
-				}
-				Some(RetTypes::U32Array) => {
-					let len = bytes.len() / 4;
-					let mut v = vec![0; len];
-					for i in 0..len {
-						v[i] = u32::from_le_bytes(bytes[i*4..(i+1)*4].try_into().unwrap());
-					}
-					results.push(Box::new(v));
-				}
-				Some(RetTypes::I32Array) => {
-					let len = bytes.len() / 4;
-					let mut v = vec![0; len];
-					for i in 0..len {
-						v[i] = i32::from_le_bytes(bytes[i*4..(i+1)*4].try_into().unwrap());
-					}
-					results.push(Box::new(v));
-				}
-				Some(RetTypes::U64Array) => {
-					let len = bytes.len() / 8;
-					let mut v = vec![0; len];
-					for i in 0..len {
-						v[i] = u64::from_le_bytes(bytes[i*8..(i+1)*8].try_into().unwrap());
-					}
-					results.push(Box::new(v));
-				}
-				Some(RetTypes::I64Array) => {
-					let len = bytes.len() / 8;
-					let mut v = vec![0; len];
-					for i in 0..len {
-						v[i] = i64::from_le_bytes(bytes[i*8..(i+1)*8].try_into().unwrap());
-					}
-					results.push(Box::new(v));
-				}
-				Some(RetTypes::String) => {
-					results.push(Box::new(String::from_utf8(bytes).unwrap()));
-				}
-				None => {}
+enum Source {
+	File(PathBuf),
+	Bytes(Vec<u8>),
+}
+
+impl Source {
+	fn bytes(&self) -> Result<Cow<'_, [u8]>, Error> {
+		match self {
+			Source::File(path) => fs::read(path).map(Cow::Owned).map_err(Error::Io),
+			Source::Bytes(wasm) => Ok(Cow::Borrowed(wasm)),
+		}
+	}
+}
+
+/// Creates a `Vm`, optionally with WASI, loads, validates and instantiates a
+/// module in it, and wraps it in a [`Bindgen`].
+///
+/// ```ignore
+/// let mut bg = Bindgen::builder().wasi(&["plugin.wasm"], &[], &["/data:./data"]).file("plugin.wasm").build()?;
+/// ```
+#[derive(Default)]
+pub struct BindgenBuilder {
+	wasi: Option<[Vec<String>; 3]>,
+	source: Option<Source>,
+	config: BindgenConfig,
+	aot_dir: Option<PathBuf>,
+	aot_level: Option<CompilerOptimizationLevel>,
+	capture_output: bool,
+}
+
+impl BindgenBuilder {
+	/// Enables WASI with the given arguments, `KEY=VALUE` environment variables
+	/// and `guest_path:host_path` preopened directories.
+	pub fn wasi(mut self, args: &[&str], envs: &[&str], preopens: &[&str]) -> Self {
+		let strings = |items: &[&str]| items.iter().map(|s| s.to_string()).collect();
+		self.wasi = Some([strings(args), strings(envs), strings(preopens)]);
+		self
+	}
+
+	/// Captures what the guest writes to stdout and stderr, for `last_call_output`
+	/// and `on_output`, instead of letting it through to the host's. WASI is then
+	/// provided by the host instead of WasmEdge: the guest sees the arguments and
+	/// environment variables given to [`wasi`](Self::wasi), the clock and
+	/// randomness, while files and sockets fail with `ENOSYS`. Preopened
+	/// directories need WasmEdge's WASI, so they can't be combined with it.
+	/// A `Vm` set up by hand keeps WasmEdge's WASI and its output.
+	pub fn capture_output(mut self, capture: bool) -> Self {
+		self.capture_output = capture;
+		self
+	}
+
+	pub fn file(mut self, path: impl AsRef<Path>) -> Self {
+		self.source = Some(Source::File(path.as_ref().to_path_buf()));
+		self
+	}
+
+	pub fn bytes(mut self, wasm: impl Into<Vec<u8>>) -> Self {
+		self.source = Some(Source::Bytes(wasm.into()));
+		self
+	}
+
+	pub fn config(mut self, config: BindgenConfig) -> Self {
+		self.config = config;
+		self
+	}
+
+	/// Compiles the module ahead of time with WasmEdge's compiler, into `dir`
+	/// where the following runs find it, keyed by the SHA-256 of the module,
+	/// the WasmEdge version and the optimization level. Needs the `aot`
+	/// feature; without it, or when compilation fails, the module is interpreted.
+	pub fn aot_cache(mut self, dir: impl AsRef<Path>) -> Self {
+		self.aot_dir = Some(dir.as_ref().to_path_buf());
+		self
+	}
+
+	/// Optimization level of the AOT compiler, `O3` by default.
+	pub fn aot_optimization_level(mut self, level: CompilerOptimizationLevel) -> Self {
+		self.aot_level = Some(level);
+		self
+	}
+
+	/// Runs every step, [initializes](wasmedge_bindgen_host_core::Bindgen::initialize) reactor modules, and
+	/// checks the allocator and memory exports named in the config. The
+	/// manifest of the module is read from its bytes.
+	pub fn build(self) -> Result<Bindgen, Error> {
+		let source = self.source.ok_or_else(|| Error::InvalidConfig(String::from("No module to load, call file or bytes")))?;
+
+		let output = self.capture_output.then(|| Arc::new(Mutex::new(Output::default())));
+		let mut config = Config::create().map_err(Error::runtime)?;
+		config.wasi(self.wasi.is_some() && output.is_none());
+		let mut vm = Vm::create(Some(config), None).map_err(Error::runtime)?;
+		match (&self.wasi, &output) {
+			(Some([_, _, preopens]), Some(_)) if !preopens.is_empty() => {
+				return Err(Error::InvalidConfig(String::from("Preopened directories need WasmEdge's WASI, which can't capture output")));
+			}
+			(wasi, Some(output)) => {
+				let (args, envs) = wasi.as_ref().map(|[args, envs, _]| (&args[..], &envs[..])).unwrap_or_default();
+				let import = wasi::import(output.clone(), args, envs)?;
+				vm.register_wasm_from_import(ImportObject::Import(import)).map_err(Error::runtime)?;
+			}
+			(Some([args, envs, preopens]), None) => {
+				vm.wasi_module_mut().map_err(Error::runtime)?
+					.init_wasi(Some(strs(args)), Some(strs(envs)), Some(strs(preopens)));
 			}
+			(None, None) => {}
 		}
 
-		results
+		let wasm = source.bytes()?;
+		let manifest = Manifest::from_module(&wasm).ok().flatten();
+		let compiled = match self.aot_dir {
+			Some(dir) => {
+				let aot = AotCache { dir, level: self.aot_level.unwrap_or(CompilerOptimizationLevel::O3) };
+				aot.compiled(&wasm).map(|artifact| (aot, artifact))
+			}
+			None => None,
+		};
+		// Loaded as a `Module` kept by the backend, to load it again if a reload fails
+		let loader = Loader::create(None).map_err(Error::runtime)?;
+		let load = |module: WasmEdgeResult<Module>, vm: &mut Vm| -> WasmEdgeResult<Module> {
+			let module = module?;
+			vm.load_wasm_from_module(&module)?;
+			vm.validate()?;
+			Ok(module)
+		};
+		let aot = match &compiled {
+			Some((aot, artifact)) => match load(loader.from_file(artifact), &mut vm) {
+				Ok(module) => Some(module),
+				Err(_) => {
+					aot.evict(artifact);
+					None
+				}
+			},
+			None => None,
+		};
+		let module = match aot {
+			Some(module) => module,
+			None => load(loader.from_bytes(&wasm), &mut vm).map_err(Error::runtime)?,
+		};
+		vm.instantiate().map_err(Error::runtime)?;
+
+		let mut backend = WasmEdgeBackend::new(vm, manifest);
+		backend.loaded = Some(Arc::new(module));
+		backend.output = output;
+		let mut bg = Bindgen {
+			inner: wasmedge_bindgen_host_core::Bindgen::with_config(backend, self.config)?,
+		};
+		bg.initialize()?;
+		Ok(bg)
 	}
-}
\ No newline at end of file
+}
@@ -0,0 +1,202 @@
+use std::io::Read;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use wasmedge_sys::*;
+use wasmedge_types::error::HostFuncError;
+use wasmedge_types::ValType as WasmType;
+
+use crate::{Error, Output};
+
+// WASI errnos
+const ERRNO_BADF: i32 = 8;
+const ERRNO_FAULT: i32 = 21;
+const ERRNO_IO: i32 = 29;
+const ERRNO_NOSYS: i32 = 52;
+
+// The functions of `wasi_snapshot_preview1` with their parameters, `i` for
+// i32 and `I` for i64. All but `proc_exit` return an errno.
+const FUNCTIONS: &[(&str, &str)] = &[
+	("args_get", "ii"),
+	("args_sizes_get", "ii"),
+	("environ_get", "ii"),
+	("environ_sizes_get", "ii"),
+	("clock_res_get", "ii"),
+	("clock_time_get", "iIi"),
+	("fd_advise", "iIIi"),
+	("fd_allocate", "iII"),
+	("fd_close", "i"),
+	("fd_datasync", "i"),
+	("fd_fdstat_get", "ii"),
+	("fd_fdstat_set_flags", "ii"),
+	("fd_fdstat_set_rights", "iII"),
+	("fd_filestat_get", "ii"),
+	("fd_filestat_set_size", "iI"),
+	("fd_filestat_set_times", "iIIi"),
+	("fd_pread", "iiiIi"),
+	("fd_prestat_get", "ii"),
+	("fd_prestat_dir_name", "iii"),
+	("fd_pwrite", "iiiIi"),
+	("fd_read", "iiii"),
+	("fd_readdir", "iiiIi"),
+	("fd_renumber", "ii"),
+	("fd_seek", "iIii"),
+	("fd_sync", "i"),
+	("fd_tell", "ii"),
+	("fd_write", "iiii"),
+	("path_create_directory", "iii"),
+	("path_filestat_get", "iiiii"),
+	("path_filestat_set_times", "iiiiIIi"),
+	("path_link", "iiiiiii"),
+	("path_open", "iiiiiIIii"),
+	("path_readlink", "iiiiii"),
+	("path_remove_directory", "iii"),
+	("path_rename", "iiiiii"),
+	("path_symlink", "iiiii"),
+	("path_unlink_file", "iii"),
+	("poll_oneoff", "iiii"),
+	("proc_exit", "i"),
+	("proc_raise", "i"),
+	("sched_yield", ""),
+	("random_get", "ii"),
+	("sock_accept", "iii"),
+	("sock_recv", "iiiiii"),
+	("sock_send", "iiiii"),
+	("sock_shutdown", "ii"),
+];
+
+/// What the host functions share: the captured output, and the arguments
+/// and environment variables as the guest sees them, NUL terminated.
+struct Wasi {
+	output: Arc<Mutex<Output>>,
+	args: Vec<Vec<u8>>,
+	envs: Vec<Vec<u8>>,
+}
+
+/// A `wasi_snapshot_preview1` module to register instead of WasmEdge's WASI,
+/// capturing what guests write to stdout and stderr into `output`. The guest
+/// sees `args` and `envs`, `clock_time_get` reads the host's clock,
+/// `random_get` the host's `/dev/urandom`, `sched_yield` returns, and
+/// `proc_exit` ends the call with a trap. All others, including every file and
+/// socket function, fail with `ENOSYS`.
+pub(crate) fn import(output: Arc<Mutex<Output>>, args: &[String], envs: &[String]) -> Result<ImportModule, Error> {
+	let terminated = |items: &[String]| items.iter().map(|s| [s.as_bytes(), &[0]].concat()).collect();
+	let wasi = Arc::new(Wasi { output, args: terminated(args), envs: terminated(envs) });
+	let mut module = ImportModule::create("wasi_snapshot_preview1").map_err(Error::runtime)?;
+	for &(name, params) in FUNCTIONS {
+		let params = params.chars().map(|c| if c == 'I' { WasmType::I64 } else { WasmType::I32 });
+		let results = if name == "proc_exit" { vec![] } else { vec![WasmType::I32] };
+		let ty = FuncType::create(params, results).map_err(Error::runtime)?;
+		let wasi = wasi.clone();
+		let func = Function::create(&ty, Box::new(move |frame: &CallingFrame, params: Vec<WasmValue>| wasi.call(name, frame, &params)), 0)
+			.map_err(Error::runtime)?;
+		module.add_func(name, func);
+	}
+	Ok(module)
+}
+
+impl Wasi {
+	fn call(&self, name: &str, frame: &CallingFrame, params: &[WasmValue]) -> Result<Vec<WasmValue>, HostFuncError> {
+		let arg = |i: usize| params.get(i).map(|p| p.to_i32()).unwrap_or(0) as u32;
+		if name == "proc_exit" {
+			return Err(HostFuncError::User(arg(0)));
+		}
+		let errno = match frame.memory_mut(0) {
+			Some(mut memory) => match self.run(name, &mut memory, arg) {
+				Ok(errno) => errno,
+				Err(_) => ERRNO_FAULT,
+			},
+			None => ERRNO_FAULT,
+		};
+		Ok(vec![WasmValue::from_i32(errno)])
+	}
+
+	fn run(&self, name: &str, memory: &mut Memory, arg: impl Fn(usize) -> u32) -> WasmEdgeResult<i32> {
+		match name {
+			"fd_write" => return self.fd_write(memory, arg(0), arg(1), arg(2), arg(3)),
+			"args_sizes_get" => sizes(memory, &self.args, arg(0), arg(1))?,
+			"args_get" => return strings(memory, &self.args, arg(0), arg(1)),
+			"environ_sizes_get" => sizes(memory, &self.envs, arg(0), arg(1))?,
+			"environ_get" => return strings(memory, &self.envs, arg(0), arg(1)),
+			"clock_time_get" => {
+				let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+				memory.set_data((now.as_nanos() as u64).to_le_bytes(), arg(2))?;
+			}
+			"random_get" => return random_get(memory, arg(0), arg(1)),
+			"sched_yield" => {}
+			_ => return Ok(ERRNO_NOSYS),
+		}
+		Ok(0)
+	}
+
+	fn fd_write(&self, memory: &mut Memory, fd: u32, iovs: u32, iovs_len: u32, nwritten: u32) -> WasmEdgeResult<i32> {
+		if fd != 1 && fd != 2 {
+			return Ok(ERRNO_BADF);
+		}
+		let mut written = Vec::new();
+		for i in 0..iovs_len {
+			let Some(iov) = element(iovs, i, 8) else {
+				return Ok(ERRNO_FAULT);
+			};
+			let iov = memory.get_data(iov, 8)?;
+			let (pointer, len) = (u32::from_le_bytes(iov[..4].try_into().unwrap()), u32::from_le_bytes(iov[4..].try_into().unwrap()));
+			written.extend(memory.get_data(pointer, len)?);
+		}
+		memory.set_data((written.len() as u32).to_le_bytes(), nwritten)?;
+		let mut output = self.output.lock().unwrap_or_else(|e| e.into_inner());
+		match fd {
+			1 => output.stdout.extend_from_slice(&written),
+			_ => output.stderr.extend_from_slice(&written),
+		}
+		Ok(0)
+	}
+}
+
+/// Address of element `index` of `size` bytes in an array at `base`, unless
+/// it overflows.
+fn element(base: u32, index: u32, size: u32) -> Option<u32> {
+	index.checked_mul(size)?.checked_add(base)
+}
+
+// Fills the buffer a chunk at a time, so that the host never allocates more
+// than a chunk whatever length the guest asks for
+fn random_get(memory: &mut Memory, buffer: u32, len: u32) -> WasmEdgeResult<i32> {
+	const CHUNK: u32 = 4096;
+	match buffer.checked_add(len) {
+		Some(end) if end as u64 <= memory.size() as u64 * 65536 => {}
+		_ => return Ok(ERRNO_FAULT),
+	}
+	let Ok(mut random) = std::fs::File::open("/dev/urandom") else {
+		return Ok(ERRNO_IO);
+	};
+	let mut chunk = [0; CHUNK as usize];
+	for offset in (0..len).step_by(CHUNK as usize) {
+		let chunk = &mut chunk[..CHUNK.min(len - offset) as usize];
+		if random.read_exact(chunk).is_err() {
+			return Ok(ERRNO_IO);
+		}
+		memory.set_data(&*chunk, buffer + offset)?;
+	}
+	Ok(0)
+}
+
+fn sizes(memory: &mut Memory, items: &[Vec<u8>], count: u32, size: u32) -> WasmEdgeResult<()> {
+	memory.set_data((items.len() as u32).to_le_bytes(), count)?;
+	memory.set_data((items.iter().map(Vec::len).sum::<usize>() as u32).to_le_bytes(), size)
+}
+
+fn strings(memory: &mut Memory, items: &[Vec<u8>], pointers: u32, buffer: u32) -> WasmEdgeResult<i32> {
+	let mut offset = buffer;
+	for (i, item) in items.iter().enumerate() {
+		let Some(pointer) = element(pointers, i as u32, 4) else {
+			return Ok(ERRNO_FAULT);
+		};
+		memory.set_data(offset.to_le_bytes(), pointer)?;
+		memory.set_data(item, offset)?;
+		let Some(next) = offset.checked_add(item.len() as u32) else {
+			return Ok(ERRNO_FAULT);
+		};
+		offset = next;
+	}
+	Ok(0)
+}
@@ -0,0 +1,317 @@
+use wasmedge_bindgen_host::*;
+use wasmedge_sys::Vm;
+
+// A guest following the bindgen ABI by hand: a bump allocator, and exports
+// returning the header, entries and values the macro would generate, with
+// the packed variant of `add`.
+const GUEST: &str = r#"
+(module
+	(memory (export "memory") 1)
+	(global $top (export "top") (mut i32) (i32.const 1024))
+	(data (i32.const 16) "expect 2 params")
+
+	;; Manifest records: add(i32, i32) -> i32, packed, echo(String) -> String
+	(@custom "wasmedge_bindgen" "\01\03\00add\02\02\06\06\01\06")
+	(@custom "wasmedge_bindgen" "\01\04\00echo\00\01\1f\01\1f")
+
+	(func $allocate (export "allocate") (param $size i32) (result i32)
+		(local $p i32)
+		(local.set $p (global.get $top))
+		(global.set $top (i32.and (i32.add (i32.add (global.get $top) (local.get $size)) (i32.const 7)) (i32.const -8)))
+		(local.get $p))
+
+	(func (export "deallocate") (param i32 i32))
+
+	(func $header (param $flag i32) (param $pointer i32) (param $len i32) (result i32)
+		(local $h i32)
+		(local.set $h (call $allocate (i32.const 9)))
+		(i32.store8 (local.get $h) (local.get $flag))
+		(i32.store offset=1 (local.get $h) (local.get $pointer))
+		(i32.store offset=5 (local.get $h) (local.get $len))
+		(local.get $h))
+
+	(func $entry (param $pointer i32) (param $ty i32) (param $size i32) (result i32)
+		(local $e i32)
+		(local.set $e (call $allocate (i32.const 12)))
+		(i32.store (local.get $e) (local.get $pointer))
+		(i32.store offset=4 (local.get $e) (local.get $ty))
+		(i32.store offset=8 (local.get $e) (local.get $size))
+		(local.get $e))
+
+	;; add(a: i32, b: i32) -> i32
+	(func (export "add") (param $params i32) (param $count i32) (result i32)
+		(local $out i32)
+		(if (i32.ne (local.get $count) (i32.const 2))
+			(then (return (call $header (i32.const 1) (i32.const 16) (i32.const 15)))))
+		(local.set $out (call $allocate (i32.const 4)))
+		(i32.store (local.get $out)
+			(i32.add (i32.load (i32.load (local.get $params))) (i32.load (i32.load offset=8 (local.get $params)))))
+		(call $header (i32.const 0) (call $entry (local.get $out) (i32.const 6) (i32.const 4)) (i32.const 1)))
+
+	;; add, reading the values of the two records after the count and writing
+	;; one record after the flag and count
+	(func (export "__wasmedge_bindgen_packed_add") (param $params i32) (param $len i32) (result i64)
+		(local $out i32)
+		(local.set $out (call $allocate (i32.const 14)))
+		(i32.store8 (local.get $out) (i32.const 0))
+		(i32.store offset=1 (local.get $out) (i32.const 1))
+		(i32.store8 offset=5 (local.get $out) (i32.const 6))
+		(i32.store offset=6 (local.get $out) (i32.const 4))
+		(i32.store offset=10 (local.get $out)
+			(i32.add (i32.load offset=9 (local.get $params)) (i32.load offset=18 (local.get $params))))
+		(i64.or (i64.extend_i32_u (local.get $out)) (i64.shl (i64.const 14) (i64.const 32))))
+
+	;; echo(s: String) -> String, handing the parameter buffer back
+	(func (export "echo") (param $params i32) (param $count i32) (result i32)
+		(call $header (i32.const 0)
+			(call $entry (i32.load (local.get $params)) (i32.const 31) (i32.load offset=4 (local.get $params)))
+			(i32.const 1)))
+
+	(func (export "trap") (param i32 i32) (result i32)
+		unreachable)
+)
+"#;
+
+fn bindgen() -> Bindgen {
+	Bindgen::builder().bytes(wat::parse_str(GUEST).unwrap()).build().unwrap()
+}
+
+#[test]
+fn scalars() {
+	let mut rv = bindgen().run_wasm("add", params![40, 2]).unwrap().unwrap();
+	assert_eq!(*rv.pop().unwrap().downcast::<i32>().unwrap(), 42);
+}
+
+#[test]
+fn strings() {
+	let mut rv = bindgen().run_wasm("echo", params!["hello"]).unwrap().unwrap();
+	assert_eq!(*rv.pop().unwrap().downcast::<String>().unwrap(), "hello");
+}
+
+#[test]
+fn packed_calls() {
+	let mut bg = bindgen();
+	assert!(bg.manifest().unwrap().function("add").unwrap().packed);
+	let mut rv = bg.run_wasm("add", params![40, 2]).unwrap().unwrap();
+	assert_eq!(*rv.pop().unwrap().downcast::<i32>().unwrap(), 42);
+	// One buffer for both parameters, instead of the frame and one per parameter
+	assert_eq!(bg.last_call_stats().allocate_calls, 1);
+}
+
+#[test]
+fn manifest_given_to_the_constructor() {
+	let wasm = wat::parse_str(GUEST).unwrap();
+	let mut vm = Vm::create(None, None).unwrap();
+	vm.load_wasm_from_bytes(&wasm).unwrap();
+	vm.validate().unwrap();
+	vm.instantiate().unwrap();
+	let manifest = Manifest::from_module(&wasm).unwrap().unwrap();
+	let mut bg = Bindgen::with_manifest(vm, BindgenConfig::default(), manifest).unwrap();
+	bg.run_wasm("add", params![1, 2]).unwrap().unwrap();
+	assert_eq!(bg.last_call_stats().allocate_calls, 1);
+}
+
+#[test]
+fn prepared_calls_after_reinstantiation() {
+	let mut bg = bindgen();
+	let prepared = bg.prepare("add").unwrap();
+	bg.instantiate().unwrap();
+	let mut rv = bg.run_prepared(&prepared, params![40, 2]).unwrap().unwrap();
+	assert_eq!(*rv.pop().unwrap().downcast::<i32>().unwrap(), 42);
+	// A clone sharing the Vm instantiates it again behind the original's back
+	bg.clone().instantiate().unwrap();
+	let mut rv = bg.run_prepared(&prepared, params![1, 2]).unwrap().unwrap();
+	assert_eq!(*rv.pop().unwrap().downcast::<i32>().unwrap(), 3);
+}
+
+#[test]
+fn reactors_initialized_after_recycling() {
+	// A reactor whose `_initialize` sets a global it doesn't export
+	let reactor = GUEST.replace(
+		"(func (export \"deallocate\") (param i32 i32))",
+		r#"(func (export "deallocate") (param i32 i32))
+	(global $ready (mut i32) (i32.const 0))
+	(func (export "_initialize") (global.set $ready (i32.const 1)))
+	(func (export "ready") (result i32) (global.get $ready))"#,
+	);
+	let mut bg = Bindgen::builder().bytes(wat::parse_str(reactor).unwrap()).build().unwrap();
+	bg.snapshot().unwrap();
+	assert!(bg.run_wasm("trap", vec![]).is_err());
+	assert_eq!(add(&mut bg), 42);
+	assert_eq!(bg.recycle_count(), 1);
+	assert_eq!(bg.backend_mut().call("ready", &[]).unwrap(), [Val::I32(1)]);
+
+	bg.reset().unwrap();
+	assert_eq!(bg.backend_mut().call("ready", &[]).unwrap(), [Val::I32(1)]);
+}
+
+fn add(bg: &mut Bindgen) -> i32 {
+	let mut rv = bg.run_wasm("add", params![40, 2]).unwrap().unwrap();
+	*rv.pop().unwrap().downcast::<i32>().unwrap()
+}
+
+#[test]
+fn reload_swaps_only_valid_modules() {
+	let mut bg = bindgen();
+	let no_allocator = GUEST.replace("(func $allocate (export \"allocate\")", "(func $allocate");
+	assert!(matches!(bg.reload(wat::parse_str(no_allocator).unwrap()), Err(Error::MissingFunction(_))));
+	assert!(bg.reload(b"not wasm").is_err());
+	assert_eq!(add(&mut bg), 42);
+
+	let bonus = GUEST
+		.replace("(i32.add (i32.load offset=9", "(i32.add (i32.const 1000) (i32.add (i32.load offset=9")
+		.replace("(i32.load offset=18 (local.get $params))))", "(i32.load offset=18 (local.get $params)))))");
+	bg.reload(wat::parse_str(bonus).unwrap()).unwrap();
+	assert_eq!(add(&mut bg), 1042);
+	// Recycling after a trap instantiates the reloaded module again
+	assert!(bg.run_wasm("trap", vec![]).unwrap_err().is_trap());
+	assert_eq!(add(&mut bg), 1042);
+	assert_eq!(bg.recycle_count(), 1);
+}
+
+/// `GUEST` with a `log` function writing its string parameter to stdout and
+/// "logged" to stderr with WASI `fd_write`, then returning it like `echo`, and
+/// an `arg_count` function returning the number of WASI arguments.
+#[test]
+fn reloads_register_no_modules() {
+	let mut bg = bindgen();
+	let registered = |bg: &mut Bindgen| bg.vm().store_mut().unwrap().module_len();
+	let before = registered(&mut bg);
+	let no_allocator = GUEST.replace("(func $allocate (export \"allocate\")", "(func $allocate");
+	for _ in 0..10 {
+		bg.reload(wat::parse_str(GUEST).unwrap()).unwrap();
+		assert!(bg.reload(wat::parse_str(&no_allocator).unwrap()).is_err());
+		assert_eq!(add(&mut bg), 42);
+	}
+	assert_eq!(registered(&mut bg), before);
+}
+
+fn printing_guest() -> Vec<u8> {
+	let guest = GUEST
+		.replace("(module", r#"(module
+	(import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+	(import "wasi_snapshot_preview1" "args_sizes_get" (func $args_sizes_get (param i32 i32) (result i32)))"#)
+		.replace("(data (i32.const 16) \"expect 2 params\")", r#"(data (i32.const 16) "expect 2 params") (data (i32.const 32) "logged")"#)
+		.replace(";; echo(s: String)", r#"(func (export "log") (param $params i32) (param $count i32) (result i32)
+		(local $iov i32)
+		(local.set $iov (call $allocate (i32.const 12)))
+		(i32.store (local.get $iov) (i32.load (local.get $params)))
+		(i32.store offset=4 (local.get $iov) (i32.load offset=4 (local.get $params)))
+		(drop (call $fd_write (i32.const 1) (local.get $iov) (i32.const 1) (i32.add (local.get $iov) (i32.const 8))))
+		(i32.store (local.get $iov) (i32.const 32))
+		(i32.store offset=4 (local.get $iov) (i32.const 6))
+		(drop (call $fd_write (i32.const 2) (local.get $iov) (i32.const 1) (i32.add (local.get $iov) (i32.const 8))))
+		(call $header (i32.const 0)
+			(call $entry (i32.load (local.get $params)) (i32.const 31) (i32.load offset=4 (local.get $params)))
+			(i32.const 1)))
+
+	(func (export "arg_count") (result i32)
+		(drop (call $args_sizes_get (i32.const 8) (i32.const 12)))
+		(i32.load (i32.const 8)))
+
+	;; echo(s: String)"#);
+	wat::parse_str(guest).unwrap()
+}
+
+#[test]
+fn captured_output() {
+	let mut bg = Bindgen::builder().wasi(&["plugin.wasm", "-v"], &[], &[]).capture_output(true).bytes(printing_guest()).build().unwrap();
+	let (rv, output) = bg.run_wasm_with_output("log", params!["hello stdout"]).unwrap();
+	assert!(rv.is_ok());
+	assert_eq!(output.stdout, b"hello stdout");
+	assert_eq!(output.stderr, b"logged");
+	bg.run_wasm("echo", params!["quiet"]).unwrap().unwrap();
+	assert!(bg.last_call_output().is_empty());
+	assert_eq!(bg.backend_mut().call("arg_count", &[]).unwrap(), [Val::I32(2)]);
+
+	let preopened = Bindgen::builder().wasi(&[], &[], &["/data:."]).capture_output(true).bytes(printing_guest()).build();
+	assert!(matches!(preopened, Err(Error::InvalidConfig(_))));
+}
+
+/// `GUEST` with `divmod(a: i32, b: i32) -> (i32, i32)` flagged direct, and a
+/// direct export returning both values, as guests in languages with
+/// multi-value functions can.
+fn multi_value_guest() -> Vec<u8> {
+	let guest = GUEST
+		.replace(r#"(@custom "wasmedge_bindgen" "\01\04\00echo"#, r#"(@custom "wasmedge_bindgen" "\01\06\00divmod\04\02\06\06\02\06\06")
+	(@custom "wasmedge_bindgen" "\01\04\00echo"#)
+		.replace("(func (export \"trap\")", r#"(func (export "divmod") (param i32 i32) (result i32)
+		unreachable)
+
+	(func (export "__wasmedge_bindgen_direct_divmod") (param $a i32) (param $b i32) (result i32 i32)
+		(i32.div_s (local.get $a) (local.get $b))
+		(i32.rem_s (local.get $a) (local.get $b)))
+
+	(func (export "trap")"#);
+	wat::parse_str(guest).unwrap()
+}
+
+#[test]
+fn multi_value_direct_exports() {
+	let mut bg = Bindgen::builder().bytes(multi_value_guest()).build().unwrap();
+	assert!(bg.manifest().unwrap().function("divmod").unwrap().direct);
+	let prepared = bg.prepare("divmod").unwrap();
+	let rv = bg.run_prepared(&prepared, params![17, 5]).unwrap().unwrap();
+	assert_eq!((*rv[0].downcast_ref::<i32>().unwrap(), *rv[1].downcast_ref::<i32>().unwrap()), (3, 2));
+	// Neither the allocator nor the linear memory
+	assert_eq!(bg.last_call_stats().allocate_calls, 0);
+}
+
+/// `GUEST` with batch exports: one for `add`, with every call taking two
+/// four-byte parameters, and one for `echo` rejecting every batch.
+fn batch_guest() -> Vec<u8> {
+	let guest = GUEST
+		.replace(r#"\01\03\00add\02"#, r#"\01\03\00add\0a"#)
+		.replace(r#"\01\04\00echo\00"#, r#"\01\04\00echo\08"#)
+		.replace("(func (export \"trap\")", r#"(func (export "__wasmedge_bindgen_batch_add") (param $params i32) (param $len i32) (result i64)
+		(local $count i32) (local $i i32) (local $in i32) (local $out i32) (local $size i32) (local $o i32)
+		(local.set $count (i32.load (local.get $params)))
+		(local.set $size (i32.add (i32.const 5) (i32.mul (local.get $count) (i32.const 18))))
+		(local.set $out (call $allocate (local.get $size)))
+		(i32.store8 (local.get $out) (i32.const 0))
+		(i32.store offset=1 (local.get $out) (local.get $count))
+		(local.set $in (i32.add (local.get $params) (i32.const 4)))
+		(local.set $o (i32.add (local.get $out) (i32.const 5)))
+		(block $done
+			(loop $next
+				(br_if $done (i32.ge_u (local.get $i) (local.get $count)))
+				;; The size and packed results of a call, read from its size and packed params
+				(i32.store (local.get $o) (i32.const 14))
+				(i32.store8 offset=4 (local.get $o) (i32.const 0))
+				(i32.store offset=5 (local.get $o) (i32.const 1))
+				(i32.store8 offset=9 (local.get $o) (i32.const 6))
+				(i32.store offset=10 (local.get $o) (i32.const 4))
+				(i32.store offset=14 (local.get $o)
+					(i32.add (i32.load offset=13 (local.get $in)) (i32.load offset=22 (local.get $in))))
+				(local.set $in (i32.add (local.get $in) (i32.const 26)))
+				(local.set $o (i32.add (local.get $o) (i32.const 18)))
+				(local.set $i (i32.add (local.get $i) (i32.const 1)))
+				(br $next)))
+		(i64.or (i64.extend_i32_u (local.get $out)) (i64.shl (i64.extend_i32_u (local.get $size)) (i64.const 32))))
+
+	(func (export "__wasmedge_bindgen_batch_echo") (param i32 i32) (result i64)
+		(local $out i32)
+		(local.set $out (call $allocate (i32.const 5)))
+		(i32.store8 (local.get $out) (i32.const 1))
+		(i32.store offset=1 (local.get $out) (i32.const 0x73706f6f)) ;; "oops"
+		(i64.or (i64.extend_i32_u (local.get $out)) (i64.shl (i64.const 5) (i64.const 32))))
+
+	(func (export "trap")"#);
+	wat::parse_str(guest).unwrap()
+}
+
+#[test]
+fn batches() {
+	let mut bg = Bindgen::builder().bytes(batch_guest()).build().unwrap();
+	let results = bg.call_batch("add", (0..100).map(|i| params![i, 2]).collect()).unwrap();
+	assert_eq!(results.len(), 100);
+	for (i, result) in results.into_iter().enumerate() {
+		assert_eq!(*result.unwrap()[0].downcast_ref::<i32>().unwrap(), i as i32 + 2);
+	}
+	// One crossing for the whole batch
+	assert_eq!(bg.last_call_stats().allocate_calls, 1);
+
+	let results = bg.call_batch("echo", vec![params!["a"], params!["b"]]).unwrap();
+	assert!(results.iter().all(|result| result.as_ref().err().map(String::as_str) == Some("oops")));
+	assert!(!bg.is_poisoned());
+}
@@ -1,30 +1,9 @@
-use wasmedge_sys::*;
 use std::env;
-use std::path::Path;
 use wasmedge_bindgen_host::*;
 
 fn main() {
-	let mut config = Config::create().unwrap();
-	config.wasi(true);
-
-	let mut vm = Vm::create(Some(config), None).unwrap();
-
-	// get default wasi module
-	let mut wasi_module = vm.wasi_module_mut().unwrap();
-	// init the default wasi module
-	wasi_module.init_wasi(
-		Some(vec![]),
-		Some(vec![]),
-		Some(vec![]),
-	);
-
 	let args: Vec<String> = env::args().collect();
-	let wasm_path = Path::new(&args[1]);
-	let _ = vm.load_wasm_from_file(wasm_path);
-	let _ = vm.validate();
-
-	let mut bg = Bindgen::new(vm);
-	bg.instantiate();
+	let mut bg = Bindgen::builder().wasi(&[], &[], &[]).file(&args[1]).build().unwrap();
 
 	// create_line: string, string, string -> string (inputs are JSON stringified)	
 	let params = vec![Param::String("{\"x\":2.5,\"y\":7.8}"), Param::String("{\"x\":2.5,\"y\":5.8}"), Param::String("A thin red line")];